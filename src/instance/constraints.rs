@@ -0,0 +1,503 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use sxd_document::{dom, Package};
+use sxd_xpath::context::Evaluation;
+use sxd_xpath::function::{self, Args, Function};
+use sxd_xpath::nodeset::{Node, Nodeset};
+use sxd_xpath::{Context, Factory, Value, XPath};
+
+use super::containerinstance::ContainerInstance;
+use super::error::InstanceError;
+use super::leafref::LeafNode;
+use super::leafvalue::LeafValue;
+use super::util::*;
+use crate::model::constraint::Condition;
+use crate::model::datamodel::DataModel;
+use crate::model::leafref::has_leafrefs;
+use crate::model::leaftype::{LeafType, Pattern};
+use crate::model::util::{split_prefix, Model};
+
+/// The namespace of the attributes that carry what the YANG functions need
+/// to know about a node, which expressions cannot otherwise refer to.
+const NS: &str = "urn:yinz:instance";
+
+/// The namespace of the variables that hold the elements of the leaves and
+/// leaf-lists of each path, which `deref()` looks its targets up in.
+const NODES_NS: &str = "urn:yinz:instance:nodes";
+
+/// The namespace of the variables that hold the module each prefix of the
+/// expression being evaluated refers to, which `derived-from()` reads.
+const PREFIXES_NS: &str = "urn:yinz:instance:prefixes";
+
+/// A node of the document built from an instance, along with the schema
+/// node whose constraints apply to it.
+struct ConstrainedNode<'d> {
+    el: dom::Element<'d>,
+    path: String,
+    model: Model,
+    /// Whether the `when` conditions are checked, which for a leaf-list is
+    /// only done for its first entry.
+    check_when: bool,
+}
+
+/// Builds XML elements named after the schema nodes of an instance, which
+/// XPath expressions are evaluated against.
+struct Builder<'a, 'd> {
+    document: dom::Document<'d>,
+    model: &'a DataModel,
+    nodes: Vec<ConstrainedNode<'d>>,
+}
+
+impl<'a, 'd> Builder<'a, 'd> {
+    fn add_element(
+        &mut self,
+        target: dom::Element<'d>,
+        model: Model,
+        path: String,
+        check_when: bool,
+    ) -> dom::Element<'d> {
+        let el = self.document.create_element(model.get_yang_name());
+        el.set_attribute_value((NS, "path"), &path);
+
+        target.append_child(el);
+
+        self.nodes.push(ConstrainedNode {
+            el,
+            path,
+            model,
+            check_when,
+        });
+
+        el
+    }
+
+    /// Sets the text of a leaf or leaf-list entry in `module`, and the
+    /// attributes that `enum-value()` and `derived-from()` read.
+    fn set_value(
+        &self,
+        el: dom::Element<'d>,
+        value: &LeafValue,
+        module: Option<&str>,
+        targets: &str,
+    ) {
+        if *value != LeafValue::Empty {
+            el.set_text(&value.to_string());
+        }
+
+        if !targets.is_empty() {
+            el.set_attribute_value((NS, "targets"), targets);
+        }
+
+        match value {
+            LeafValue::Enumeration(x) => {
+                el.set_attribute_value((NS, "enum-value"), &x.value.to_string());
+            }
+            // The module of an identity is left out if it is that of the node.
+            LeafValue::Identityref(x) => {
+                let identity = match (split_prefix(x), module) {
+                    ((None, name), Some(m)) => format!("{}:{}", m, name),
+                    _ => x.clone(),
+                };
+                el.set_attribute_value((NS, "identity"), &identity);
+                el.set_attribute_value((NS, "bases"), &self.model.get_bases(&identity).join(" "));
+            }
+            _ => (),
+        }
+    }
+
+    fn add_children(&mut self, target: dom::Element<'d>, parent: &Parent) {
+        parent.with_children(|children| {
+            for child in children.values() {
+                match child {
+                    Child::ContainerInstance(x) => {
+                        let node = x.as_parent();
+                        let model = Model::Container(x.get_model());
+                        let el = self.add_element(target, model, node.get_path(), true);
+                        self.add_children(el, &node);
+                    }
+                    Child::ListInstance(x) => {
+                        for entry in x.get_entries() {
+                            let model = Model::List(x.get_model());
+                            let el = self.add_element(target, model, entry.get_path(), true);
+                            self.add_children(el, &entry.as_parent());
+                        }
+                    }
+                    Child::LeafInstance(x) => {
+                        let node = x.as_node();
+                        let targets = get_targets(&node, &x.model.leaf_type);
+                        let model = Model::Leaf(x.model.clone());
+                        let el = self.add_element(target, model, node.get_path(), true);
+                        self.set_value(el, &x.value, x.model.module.as_deref(), &targets);
+                    }
+                    Child::LeafListInstance(x) => {
+                        let node = x.as_node();
+                        let targets = get_targets(&node, &x.model.leaf_type);
+
+                        for (i, entry) in x.children.iter().enumerate() {
+                            let model = Model::LeafList(x.model.clone());
+                            let el = self.add_element(target, model, node.get_path(), i == 0);
+                            self.set_value(el, &entry.value, x.model.module.as_deref(), &targets);
+                        }
+                    }
+                    Child::UnknownInstance(_) => (),
+                }
+            }
+        });
+    }
+}
+
+/// Returns the paths of the nodes that a node of type `leaf_type` refers
+/// to, one per line. Only leafrefs are followed.
+fn get_targets(node: &LeafNode, leaf_type: &LeafType) -> String {
+    if !has_leafrefs(leaf_type) {
+        return String::new();
+    }
+
+    node.follow()
+        .iter()
+        .map(|t| t.get_path())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn get_attribute<'d>(node: Node<'d>, name: &str) -> Option<&'d str> {
+    node.element()?.attribute_value((NS, name))
+}
+
+fn first_node<'d>(args: &mut Args<'d>) -> Result<Option<Node<'d>>, function::Error> {
+    Ok(args.pop_nodeset()?.document_order_first())
+}
+
+/// `current()`, which returns the node the expression is evaluated for.
+struct Current;
+
+impl Function for Current {
+    fn evaluate<'c, 'd>(
+        &self,
+        context: &Evaluation<'c, 'd>,
+        args: Vec<Value<'d>>,
+    ) -> Result<Value<'d>, function::Error> {
+        Args(args).exactly(0)?;
+
+        context
+            .value_of((NS, "current").into())
+            .cloned()
+            .ok_or(function::Error::ArgumentMissing)
+    }
+}
+
+/// `deref(node-set)`, which returns the nodes the leafref that is the first
+/// node of its argument refers to.
+struct Deref;
+
+impl Function for Deref {
+    fn evaluate<'c, 'd>(
+        &self,
+        context: &Evaluation<'c, 'd>,
+        args: Vec<Value<'d>>,
+    ) -> Result<Value<'d>, function::Error> {
+        let mut args = Args(args);
+        args.exactly(1)?;
+
+        let mut nodes = Nodeset::new();
+        let node = match first_node(&mut args)? {
+            Some(x) => x,
+            None => return Ok(Value::Nodeset(nodes)),
+        };
+        let targets: Vec<&str> = get_attribute(node, "targets")
+            .map(|t| t.split('\n').collect())
+            .unwrap_or_default();
+        let value = node.string_value();
+
+        for target in targets {
+            if let Some(Value::Nodeset(elements)) = context.value_of((NODES_NS, target).into()) {
+                for el in elements.iter().filter(|e| e.string_value() == value) {
+                    nodes.add(el);
+                }
+            }
+        }
+
+        Ok(Value::Nodeset(nodes))
+    }
+}
+
+/// `derived-from(node-set, identity)` and `derived-from-or-self()`, which
+/// tell whether any of the nodes is an identityref derived from the
+/// identity.
+struct DerivedFrom {
+    or_self: bool,
+}
+
+impl Function for DerivedFrom {
+    fn evaluate<'c, 'd>(
+        &self,
+        context: &Evaluation<'c, 'd>,
+        args: Vec<Value<'d>>,
+    ) -> Result<Value<'d>, function::Error> {
+        let mut args = Args(args);
+        args.exactly(2)?;
+
+        let identity = args.pop_string()?;
+        let (prefix, name) = split_prefix(&identity);
+        let module = match context.value_of((PREFIXES_NS, prefix.unwrap_or_default()).into()) {
+            Some(Value::String(x)) => x.clone(),
+            _ => {
+                return Err(function::Error::Other(format!(
+                    "unknown prefix in {}",
+                    identity
+                )))
+            }
+        };
+        let identity = format!("{}:{}", module, name);
+        let identity = identity.as_str();
+
+        let is_derived = args.pop_nodeset()?.iter().any(|node| {
+            let is_self = get_attribute(node, "identity") == Some(identity);
+            let is_base =
+                get_attribute(node, "bases").is_some_and(|b| b.split(' ').any(|x| x == identity));
+
+            is_base || (self.or_self && is_self)
+        });
+
+        Ok(Value::Boolean(is_derived))
+    }
+}
+
+/// `enum-value(node-set)`, which returns the value of the enum that is the
+/// first node of its argument.
+struct EnumValue;
+
+impl Function for EnumValue {
+    fn evaluate<'c, 'd>(
+        &self,
+        _context: &Evaluation<'c, 'd>,
+        args: Vec<Value<'d>>,
+    ) -> Result<Value<'d>, function::Error> {
+        let mut args = Args(args);
+        args.exactly(1)?;
+
+        let value = first_node(&mut args)?
+            .and_then(|n| get_attribute(n, "enum-value"))
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(f64::NAN);
+
+        Ok(Value::Number(value))
+    }
+}
+
+/// `bit-is-set(node-set, bit)`, which tells whether the bit is set in the
+/// bits value that is the first node of its argument.
+struct BitIsSet;
+
+impl Function for BitIsSet {
+    fn evaluate<'c, 'd>(
+        &self,
+        _context: &Evaluation<'c, 'd>,
+        args: Vec<Value<'d>>,
+    ) -> Result<Value<'d>, function::Error> {
+        let mut args = Args(args);
+        args.exactly(2)?;
+
+        let bit = args.pop_string()?;
+        let is_set = first_node(&mut args)?
+            .is_some_and(|n| n.string_value().split_whitespace().any(|b| b == bit));
+
+        Ok(Value::Boolean(is_set))
+    }
+}
+
+/// `re-match(subject, pattern)`, which tells whether the whole subject
+/// matches the XSD regular expression. Patterns are compiled once for all
+/// the nodes they are matched against.
+#[derive(Default)]
+struct ReMatch {
+    patterns: RefCell<HashMap<String, Pattern>>,
+}
+
+impl Function for ReMatch {
+    fn evaluate<'c, 'd>(
+        &self,
+        _context: &Evaluation<'c, 'd>,
+        args: Vec<Value<'d>>,
+    ) -> Result<Value<'d>, function::Error> {
+        let mut args = Args(args);
+        args.exactly(2)?;
+
+        let regex = args.pop_string()?;
+        let subject = args.pop_string()?;
+        let mut patterns = self.patterns.borrow_mut();
+
+        if !patterns.contains_key(&regex) {
+            let pattern = Pattern::new(&regex, false, "")
+                .map_err(|_| function::Error::Other(format!("invalid pattern {}", regex)))?;
+            patterns.insert(regex.clone(), pattern);
+        }
+
+        Ok(Value::Boolean(patterns[&regex].allows(&subject)))
+    }
+}
+
+/// Returns the context that expressions are evaluated in, for the document
+/// whose top-level nodes are in `root` and whose leaves and leaf-lists are
+/// among `nodes`.
+fn get_context<'d>(root: dom::Element<'d>, nodes: &[ConstrainedNode<'d>]) -> Context<'d> {
+    let mut context = Context::new();
+    context.set_function("current", Current);
+    context.set_function("deref", Deref);
+    context.set_function("derived-from", DerivedFrom { or_self: false });
+    context.set_function("derived-from-or-self", DerivedFrom { or_self: true });
+    context.set_function("enum-value", EnumValue);
+    context.set_function("bit-is-set", BitIsSet);
+    context.set_function("re-match", ReMatch::default());
+
+    context.set_namespace("yinz", NS);
+
+    let mut top = Nodeset::new();
+    top.add(root);
+    context.set_variable((NS, "root"), top);
+
+    let mut by_path: HashMap<&str, Nodeset<'d>> = HashMap::new();
+
+    for node in nodes {
+        if let Model::Leaf(_) | Model::LeafList(_) = node.model {
+            by_path.entry(&node.path).or_default().add(node.el);
+        }
+    }
+
+    for (path, elements) in by_path {
+        context.set_variable((NODES_NS, path), elements);
+    }
+
+    context
+}
+
+/// Compiles the `when` and `must` conditions of `nodes`, each only once
+/// however many nodes it applies to.
+fn compile<'a>(nodes: &'a [ConstrainedNode]) -> HashMap<&'a str, XPath> {
+    let mut compiled = HashMap::new();
+    let conditions = nodes.iter().flat_map(|n| {
+        let when = n.model.get_when().iter().map(|w| &w.condition);
+        when.chain(n.model.get_musts().iter().map(|m| &m.condition))
+    });
+
+    for condition in conditions {
+        let prepared = condition.prepared();
+
+        if !compiled.contains_key(prepared) {
+            // The model only has conditions that compile.
+            if let Ok(Some(xpath)) = Factory::new().build(prepared) {
+                compiled.insert(prepared, xpath);
+            }
+        }
+    }
+
+    compiled
+}
+
+/// Evaluates `condition` as a boolean with `node` as the context node.
+fn evaluate<'d>(
+    condition: &Condition,
+    compiled: &HashMap<&str, XPath>,
+    node: Node<'d>,
+    context: &mut Context<'d>,
+) -> Result<bool, String> {
+    let xpath = compiled
+        .get(condition.prepared())
+        .ok_or_else(|| "the expression is empty".to_string())?;
+
+    let mut current = Nodeset::new();
+    current.add(node);
+    context.set_variable((NS, "current"), current);
+
+    for (prefix, module) in condition.modules() {
+        context.set_variable((PREFIXES_NS, prefix.as_str()), module.as_str());
+    }
+
+    xpath
+        .evaluate(context, node)
+        .map(|v| v.boolean())
+        .map_err(|e| e.to_string())
+}
+
+fn report(error: InstanceError, errors: &mut Vec<InstanceError>) {
+    // The entries of a leaf-list share its path, and may fail alike.
+    if !errors.contains(&error) {
+        errors.push(error);
+    }
+}
+
+/// Evaluates the `when` and `must` conditions of every node of an instance
+/// of `model` whose root is `root`. The `must` conditions of a node are not
+/// evaluated if one of its `when` conditions is false.
+pub fn check_constraints(
+    root: &ContainerInstance,
+    model: &DataModel,
+    errors: &mut Vec<InstanceError>,
+) {
+    let package = Package::new();
+    let document = package.as_document();
+    let top = document.create_element("data");
+    document.root().append_child(top);
+
+    let mut builder = Builder {
+        document,
+        model,
+        nodes: Vec::new(),
+    };
+    builder.add_children(top, &root.as_parent());
+
+    let mut context = get_context(top, &builder.nodes);
+    let compiled = compile(&builder.nodes);
+
+    for node in &builder.nodes {
+        let invalid = |condition: &str, message: String| InstanceError::InvalidCondition {
+            path: node.path.clone(),
+            condition: condition.to_string(),
+            message,
+        };
+        let mut is_allowed = true;
+
+        for when in node.model.get_when().iter().filter(|_| node.check_when) {
+            let context_node = match (when.in_parent, node.el.parent()) {
+                (true, Some(parent)) => parent.into(),
+                _ => Node::Element(node.el),
+            };
+
+            match evaluate(&when.condition, &compiled, context_node, &mut context) {
+                Ok(true) => (),
+                Ok(false) => {
+                    is_allowed = false;
+                    report(
+                        InstanceError::WhenFalse {
+                            path: node.path.clone(),
+                            condition: when.condition.to_string(),
+                        },
+                        errors,
+                    );
+                }
+                Err(e) => report(invalid(&when.condition.to_string(), e), errors),
+            }
+        }
+
+        for must in node.model.get_musts().iter().filter(|_| is_allowed) {
+            match evaluate(
+                &must.condition,
+                &compiled,
+                Node::Element(node.el),
+                &mut context,
+            ) {
+                Ok(true) => (),
+                Ok(false) => report(
+                    InstanceError::MustViolation {
+                        path: node.path.clone(),
+                        condition: must.condition.to_string(),
+                        error_message: must.error_message.clone(),
+                        error_app_tag: must.error_app_tag.clone(),
+                    },
+                    errors,
+                ),
+                Err(e) => report(invalid(&must.condition.to_string(), e), errors),
+            }
+        }
+    }
+}
@@ -1,20 +1,21 @@
+use indexmap::IndexMap;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::rc::Rc;
 use std::sync::Arc;
-use ustr::{ustr, UstrMap};
+use ustr::{ustr, Ustr};
 
-use super::leafinstance::LeafInstance;
-use super::leaflistinstance::LeafListInstance;
+use super::error::{InstanceError, JsonKind};
+use super::leafvalue::LeafValue;
 use super::listinstance::ListInstance;
+use super::unknowninstance::UnknownInstance;
 use super::util::*;
 use crate::model::container::Container;
-use crate::model::util::{Model, WithChildren};
 
 pub struct ContainerData {
     pub parent: Option<Parent>,
     pub model: Arc<Container>,
-    pub children: Option<Rc<RefCell<UstrMap<Child>>>>,
+    pub children: Option<Rc<RefCell<IndexMap<Ustr, Child>>>>,
 }
 
 type Link = Rc<RefCell<ContainerData>>;
@@ -33,70 +34,187 @@ impl PartialEq for ContainerInstance {
     }
 }
 
-pub fn parse_children(model: Arc<Container>, value: Value, parent: &Link) -> UstrMap<Child> {
-    let mut children: UstrMap<Child> = UstrMap::default();
-
-    if let Value::Object(x) = value {
-        for (k, v) in x.into_iter() {
-            let children_parent = Parent::ContainerData(Rc::downgrade(parent));
-
-            if let Some(child_model) = model.get_child(&k) {
-                match child_model {
-                    Model::Leaf(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::LeafInstance(LeafInstance::new(m.clone(), v, children_parent)),
-                        );
-                    }
-                    Model::Container(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::ContainerInstance(ContainerInstance::new(
-                                m.clone(),
-                                v,
-                                Some(children_parent),
-                            )),
-                        );
-                    }
-                    Model::LeafList(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::LeafListInstance(LeafListInstance::new(
-                                m.clone(),
-                                v,
-                                children_parent,
-                            )),
-                        );
-                    }
-                    Model::List(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::ListInstance(ListInstance::new(m.clone(), v, children_parent)),
-                        );
-                    }
-                }
-            };
+pub fn parse_children(
+    model: Arc<Container>,
+    value: Value,
+    parent: &Link,
+    context: &mut ParseContext,
+) -> Result<IndexMap<Ustr, Child>, InstanceError> {
+    let members = match value {
+        Value::Object(x) => x,
+        x => {
+            let path = parent.borrow().get_path();
+            return Err(InstanceError::unexpected_kind(
+                &path,
+                &[JsonKind::Object],
+                &x,
+            ));
+        }
+    };
+
+    let mut children: IndexMap<Ustr, Child> = IndexMap::new();
+
+    for (k, v) in members.into_iter() {
+        if context.is_done() {
+            break;
+        }
+
+        let children_parent = Parent::ContainerData(Rc::downgrade(parent));
+
+        // Children are keyed like their schema nodes, whatever their names.
+        let (key, child) = match find_model(&model.children, &k, context.options.names) {
+            Some((key, m)) => (key.as_str(), parse_child(m, v, children_parent, context)),
+            None => (k.as_str(), parse_unknown(&k, v, children_parent, context)),
+        };
+
+        match child {
+            Ok(c) => {
+                children.insert(ustr(key), c);
+            }
+            Err(e) => context.report(e),
         }
     }
 
-    children
+    Ok(children)
 }
 
 impl ContainerInstance {
-    pub fn new(model: Arc<Container>, value: Value, parent: Option<Parent>) -> ContainerInstance {
+    pub fn new(
+        model: Arc<Container>,
+        value: Value,
+        parent: Option<Parent>,
+        context: &mut ParseContext,
+    ) -> Result<ContainerInstance, InstanceError> {
         let instance = ContainerInstance(Rc::new(RefCell::new(ContainerData {
             model: model.clone(),
             children: None,
             parent,
         })));
 
-        instance.0.borrow_mut().children = Some(Rc::new(RefCell::new(parse_children(
+        let children = parse_children(model, value, &instance.0, context)?;
+        instance.0.borrow_mut().children = Some(Rc::new(RefCell::new(children)));
+
+        Ok(instance)
+    }
+
+    /// Returns the non-presence container of `model` holding the defaults in
+    /// effect for its descendants when it has no data, if there are any.
+    pub fn from_defaults(model: Arc<Container>, parent: Parent) -> Option<ContainerInstance> {
+        let instance = ContainerInstance(Rc::new(RefCell::new(ContainerData {
             model,
-            value,
-            &instance.0,
-        ))));
+            children: Some(Rc::new(RefCell::new(IndexMap::new()))),
+            parent: Some(parent),
+        })));
+
+        instance.apply_defaults();
+
+        let is_empty = instance
+            .0
+            .borrow()
+            .children
+            .as_ref()
+            .unwrap()
+            .borrow()
+            .is_empty();
+        (!is_empty).then_some(instance)
+    }
+
+    /// Returns this container as the parent of its children.
+    pub fn as_parent(&self) -> Parent {
+        Parent::ContainerData(Rc::downgrade(&self.0))
+    }
+
+    pub fn get_model(&self) -> Arc<Container> {
+        self.0.borrow().model.clone()
+    }
+
+    pub fn to_json(&self, options: &SerializeOptions) -> Value {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        children_to_json(
+            &children,
+            &data.model.children,
+            &data.model.choices,
+            &self.as_parent(),
+            data.model.module.as_deref(),
+            options,
+        )
+    }
 
-        instance
+    /// Whether the container has no children.
+    pub fn is_empty(&self) -> bool {
+        self.0
+            .borrow()
+            .children
+            .as_ref()
+            .unwrap()
+            .borrow()
+            .is_empty()
+    }
+
+    /// Returns the value of the descendant leaf at `path`, given as its
+    /// camelCase names.
+    pub fn get_leaf_value(&self, path: &[&str]) -> Option<LeafValue> {
+        get_leaf_value(&self.0.borrow().children.as_ref().unwrap().borrow(), path)
+    }
+
+    /// Returns the descendant list at `path`, given as its camelCase names.
+    pub fn get_list(&self, path: &[&str]) -> Option<ListInstance> {
+        get_list(&self.0.borrow().children.as_ref().unwrap().borrow(), path)
+    }
+
+    /// Returns the value of the descendant leaf at `path`, given as its
+    /// camelCase names, or the default in effect for it if it has no data.
+    pub fn get_effective_value(&self, path: &[&str]) -> Option<LeafValue> {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        get_effective_value(&children, &data.model.children, &data.model.choices, path)
+    }
+
+    /// Returns the name of the case of the choice `name` that has data, if
+    /// any, where the choice may be nested in the case of another.
+    pub fn get_active_case(&self, name: &str) -> Option<String> {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        get_active_case(&children, data.model.as_ref(), name)
+    }
+
+    /// Adds the defaults in effect to this container and its descendants.
+    pub fn apply_defaults(&self) {
+        let data = self.0.borrow();
+
+        apply_defaults(
+            &mut data.children.as_ref().unwrap().borrow_mut(),
+            &data.model.children,
+            &data.model.choices,
+            &self.as_parent(),
+        );
+    }
+
+    /// Removes the descendants that are not of the `kind` of data to keep,
+    /// returning whether the container should be kept itself. Presence
+    /// containers are configuration even without children.
+    pub fn retain_data(&self, kind: DataKind) -> bool {
+        let data = self.0.borrow();
+        let has_data = retain_data(&mut data.children.as_ref().unwrap().borrow_mut(), kind, &[]);
+
+        has_data || (kind == DataKind::Config && data.model.presence)
+    }
+
+    pub fn validate(&self, errors: &mut Vec<InstanceError>) {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        validate_children(
+            &children,
+            &data.model.children,
+            &data.model.choices,
+            &data.get_path(),
+            errors,
+        );
     }
 
     pub fn visit(&self, f: &dyn Fn(NodeToVisit)) {
@@ -114,6 +232,25 @@ impl ContainerInstance {
                 Child::LeafListInstance(c) => {
                     c.visit(f);
                 }
+                Child::UnknownInstance(_) => (),
+            }
+        }
+    }
+
+    /// Calls `f` with every unknown node below this one.
+    pub fn visit_unknown(&self, f: &dyn Fn(&UnknownInstance)) {
+        for child in self.0.borrow().children.as_ref().unwrap().borrow().values() {
+            match child {
+                Child::ContainerInstance(c) => {
+                    c.visit_unknown(f);
+                }
+                Child::ListInstance(c) => {
+                    c.visit_unknown(f);
+                }
+                Child::UnknownInstance(c) => {
+                    f(c);
+                }
+                _ => (),
             }
         }
     }
@@ -122,11 +259,9 @@ impl ContainerInstance {
 impl ContainerData {
     pub fn get_path(&self) -> String {
         let parent_path = match &self.parent {
-            Some(p) => match p {
-                Parent::ContainerData(x) => x.upgrade().unwrap().borrow().get_path(),
-                Parent::ListChildData(x) => x.upgrade().unwrap().borrow().get_path(),
-            },
-            None => "".to_string(),
+            Some(p) => p.get_path(),
+            // The root of the data tree is not a node of its own.
+            None => return "".to_string(),
         };
 
         format!("{}/{}", parent_path, self.model.name)
@@ -1,60 +1,243 @@
 use serde_json::Value;
+use std::cell::RefCell;
 use std::sync::Arc;
 
+use super::constraints::check_constraints;
 use super::containerinstance::ContainerInstance;
+use super::error::InstanceError;
+use super::leafvalue::LeafValue;
+use super::listinstance::ListInstance;
+use super::unknowninstance::UnknownInstance;
 use super::util::*;
 use crate::model::datamodel::DataModel;
 
 pub struct DataModelInstance {
     pub root: ContainerInstance,
+    pub model: Arc<DataModel>,
 }
 
 impl DataModelInstance {
-    pub fn new(model: Arc<DataModel>, mut value: Value) -> DataModelInstance {
-        let root_name = &model.root.name;
-        let root = ContainerInstance::new(model.root.clone(), value[root_name].take(), None);
+    /// Parses an instance of `model` from its JSON, stopping at the first
+    /// error found in the data.
+    pub fn new(
+        model: Arc<DataModel>,
+        value: Value,
+    ) -> Result<DataModelInstance, Vec<InstanceError>> {
+        DataModelInstance::with_options(model, value, ParseOptions::default())
+    }
+
+    pub fn with_options(
+        model: Arc<DataModel>,
+        value: Value,
+        options: ParseOptions,
+    ) -> Result<DataModelInstance, Vec<InstanceError>> {
+        let mut context = ParseContext::new(options);
+        let root = ContainerInstance::new(model.root.clone(), value, None, &mut context);
+        let mut errors = context.into_errors();
+
+        match root {
+            Ok(root) if errors.is_empty() => Ok(DataModelInstance { root, model }),
+            Ok(_) => Err(errors),
+            Err(e) => {
+                errors.push(e);
+                Err(errors)
+            }
+        }
+    }
 
-        DataModelInstance { root }
+    /// Serializes the instance to JSON as RFC 7951 describes, or with the
+    /// camelCase names it is usually written with.
+    pub fn to_json(&self, options: &SerializeOptions) -> Value {
+        self.root.to_json(options)
     }
 
     pub fn visit(&self, f: &dyn Fn(NodeToVisit)) {
         self.root.visit(f);
     }
+
+    /// Checks every leaf and leaf-list value against the restrictions of its
+    /// type and identityref values against the identities of the model, that
+    /// mandatory nodes exist and lists and leaf-lists have as many entries
+    /// as they allow, and that the `when` and `must` conditions of every node
+    /// hold, returning all of the violations found.
+    pub fn validate(&self) -> Result<(), Vec<InstanceError>> {
+        let mut errors = Vec::new();
+        self.root.validate(&mut errors);
+        self.check_identities(&mut errors);
+        check_constraints(&self.root, &self.model, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_identities(&self, errors: &mut Vec<InstanceError>) {
+        let found = RefCell::new(Vec::new());
+
+        self.visit(&|node| {
+            let (values, leaf_type, module, path) = match node {
+                NodeToVisit::LeafInstance(x) => (
+                    vec![&x.value],
+                    &x.model.leaf_type,
+                    x.model.module.as_deref(),
+                    x.get_path(),
+                ),
+                NodeToVisit::LeafListInstance(x) => (
+                    x.children.iter().map(|c| &c.value).collect(),
+                    &x.model.leaf_type,
+                    x.model.module.as_deref(),
+                    x.get_path(),
+                ),
+            };
+
+            for value in values {
+                let error = value.validate_identity(leaf_type, module, &self.model, &path);
+                found.borrow_mut().extend(error);
+            }
+        });
+
+        errors.extend(found.into_inner());
+    }
+
+    /// Adds the default values in effect to the instance, as leaves and
+    /// leaf-lists whose `is_default` is set.
+    pub fn apply_defaults(&self) {
+        self.root.apply_defaults();
+    }
+
+    /// Returns the value of the leaf at `path`, given as the camelCase names
+    /// of the containers down to it, or the default in effect for it if it
+    /// has no data. The instance is left as it is.
+    pub fn get_effective_value(&self, path: &[&str]) -> Option<LeafValue> {
+        self.root.get_effective_value(path)
+    }
+
+    /// Returns the list at `path`, given as the camelCase names of the
+    /// containers down to it and of the list itself.
+    pub fn get_list(&self, path: &[&str]) -> Option<ListInstance> {
+        self.root.get_list(path)
+    }
+
+    /// Removes the state data from the instance, leaving its configuration.
+    pub fn strip_state(&self) {
+        self.root.retain_data(DataKind::Config);
+    }
+
+    /// Reduces the instance to its state data, along with the keys of the
+    /// list entries that have any.
+    pub fn extract_state(&self) {
+        self.root.retain_data(DataKind::State);
+    }
+
+    pub fn visit_unknown(&self, f: &dyn Fn(&UnknownInstance)) {
+        self.root.visit_unknown(f);
+    }
+
+    /// Returns the sorted paths of the members that match no schema node.
+    pub fn get_unknown_paths(&self) -> Vec<String> {
+        let paths = RefCell::new(Vec::new());
+
+        self.visit_unknown(&|x| paths.borrow_mut().push(x.get_path()));
+
+        let mut paths = paths.into_inner();
+        paths.sort();
+        paths
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use serde_json::*;
-    use std::cell::Cell;
+    use std::cell::{Cell, RefCell};
     use std::sync::Arc;
 
+    use crate::instance::error::{InstanceError, JsonKind};
+    use crate::instance::leafvalue::LeafValue;
+    use crate::instance::listinstance::Position;
     use crate::instance::util::*;
     use crate::model::datamodel::DataModel;
+    use crate::model::leaftype::Number;
     use crate::model::util::*;
 
     const DATA_MODEL: &str = r#"<?xml version="1.0"?>
-    <yin:container name="root" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
-        <yin:leaf name="foo">
-            <yin:type name="string"/>
-        </yin:leaf>
-        <yin:container name="a-container">
-            <yin:leaf name="leaf-in-container">
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:container name="root">
+            <yin:leaf name="foo">
                 <yin:type name="string"/>
             </yin:leaf>
-            <yin:leaf-list name="leaf-list-in-container">
-                <yin:type name="string"/>
-            </yin:leaf-list>
+            <yin:container name="a-container">
+                <yin:leaf name="leaf-in-container">
+                    <yin:type name="string"/>
+                </yin:leaf>
+                <yin:leaf-list name="leaf-list-in-container">
+                    <yin:type name="string"/>
+                </yin:leaf-list>
+            </yin:container>
+            <yin:list name="a-list">
+                <yin:key value="leaf-in-list"/>
+                <yin:leaf name="leaf-in-list">
+                    <yin:type name="string"/>
+                </yin:leaf>
+                <yin:leaf-list name="leaf-list-in-list">
+                    <yin:type name="string"/>
+                </yin:leaf-list>
+            </yin:list>
         </yin:container>
-        <yin:list name="a-list">
-            <yin:key value="leaf-in-list"/>
-            <yin:leaf name="leaf-in-list">
-                <yin:type name="string"/>
-            </yin:leaf>
-            <yin:leaf-list name="leaf-list-in-list">
-                <yin:type name="string"/>
-            </yin:leaf-list>
-        </yin:list>
-    </yin:container>"#;
+    </yin:module>"#;
+
+    const TYPED_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <module-set>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:prefix value="t"/>
+            <yin:container name="root">
+                <yin:leaf name="small-number">
+                    <yin:type name="uint8"/>
+                </yin:leaf>
+                <yin:leaf name="big-number">
+                    <yin:type name="uint64"/>
+                </yin:leaf>
+                <yin:leaf name="price">
+                    <yin:type name="decimal64">
+                        <yin:fraction-digits value="2"/>
+                    </yin:type>
+                </yin:leaf>
+                <yin:leaf name="enabled">
+                    <yin:type name="boolean"/>
+                </yin:leaf>
+                <yin:leaf name="flag">
+                    <yin:type name="empty"/>
+                </yin:leaf>
+                <yin:leaf name="mixed">
+                    <yin:type name="union">
+                        <yin:type name="int32"/>
+                        <yin:type name="string"/>
+                    </yin:type>
+                </yin:leaf>
+                <yin:leaf-list name="ports">
+                    <yin:type name="uint16"/>
+                </yin:leaf-list>
+                <yin:list name="peer">
+                    <yin:key value="name"/>
+                    <yin:leaf name="name">
+                        <yin:type name="string"/>
+                    </yin:leaf>
+                </yin:list>
+            </yin:container>
+        </yin:module>
+        <yin:module name="ext" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:prefix value="e"/>
+            <yin:import module="test">
+                <yin:prefix value="t"/>
+            </yin:import>
+            <yin:augment target-node="/t:root">
+                <yin:leaf name="extra">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:augment>
+        </yin:module>
+    </module-set>"#;
 
     const INSTANCE: &str = r#"
     {
@@ -74,12 +257,21 @@ mod tests {
         }
     }"#;
 
+    const INSTANCE_INVALID: &str = r#"
+    {
+        "root": {
+            "foo": { "bar": "baz" },
+            "a-container": { "leaf-in-container": "fizz", "leaf-list-in-container": "buzz" },
+            "a-list": [{ "leaf-list-in-list": ["buzz", null] }]
+        }
+    }"#;
+
     #[test]
     fn it_visits_all_nodes() {
-        let pkg = get_package(DATA_MODEL);
-        let data_model = Arc::new(DataModel::new(get_root_el(&pkg)));
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
         let v: Value = from_str(INSTANCE).unwrap();
-        let instance = super::DataModelInstance::new(data_model, v);
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
 
         let count = Cell::new(0);
         let visitor = |node: NodeToVisit| match node {
@@ -95,10 +287,10 @@ mod tests {
 
     #[test]
     fn it_ignores_unknown_data() {
-        let pkg = get_package(DATA_MODEL);
-        let data_model = Arc::new(DataModel::new(get_root_el(&pkg)));
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
         let v: Value = from_str(INSTANCE_UNKNOWN).unwrap();
-        let instance = super::DataModelInstance::new(data_model, v);
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
 
         let count = Cell::new(0);
         let visitor = |node: NodeToVisit| match node {
@@ -111,4 +303,1589 @@ mod tests {
 
         assert_eq!(count.get(), 5);
     }
+
+    #[test]
+    fn it_keeps_unknown_data() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v: Value = from_str(INSTANCE_UNKNOWN).unwrap();
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+
+        assert_eq!(
+            instance.get_unknown_paths(),
+            vec!["/root/aContainer/unknown", "/root/aList=fizz/unknown"]
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_data() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v: Value = from_str(INSTANCE_UNKNOWN).unwrap();
+        let options = ParseOptions {
+            collect_errors: true,
+            unknown_members: UnknownMembers::Reject,
+            ..Default::default()
+        };
+        let errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+
+        assert_eq!(errors.len(), 2);
+        assert!(errors.contains(&InstanceError::UnknownMember {
+            path: "/root/aList=fizz/unknown".to_string()
+        }));
+    }
+
+    #[test]
+    fn it_builds_paths_from_the_top_of_the_tree() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v: Value = from_str(INSTANCE).unwrap();
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+
+        let paths = std::cell::RefCell::new(Vec::new());
+        let visitor = |node: NodeToVisit| {
+            if let NodeToVisit::LeafInstance(x) = node {
+                paths.borrow_mut().push(x.get_path());
+            }
+        };
+
+        instance.visit(&visitor);
+
+        let mut paths = paths.into_inner();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "/root/aContainer/leafInContainer",
+                "/root/aList=fizz/leafInList",
+                "/root/foo"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_stops_at_the_first_error() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v: Value = from_str(INSTANCE_INVALID).unwrap();
+        let errors = super::DataModelInstance::new(data_model, v).err().unwrap();
+
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn it_collects_all_errors() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v: Value = from_str(INSTANCE_INVALID).unwrap();
+        let options = ParseOptions {
+            collect_errors: true,
+            ..Default::default()
+        };
+        let mut errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+        errors.sort_by(|a, b| a.path().cmp(b.path()));
+
+        assert_eq!(
+            errors,
+            vec![
+                InstanceError::UnexpectedKind {
+                    path: "/root/aContainer/leafListInContainer".to_string(),
+                    expected: vec![JsonKind::Array],
+                    actual: JsonKind::String,
+                },
+                InstanceError::MissingKey {
+                    path: "/root/aList".to_string(),
+                    key: "leafInList".to_string(),
+                },
+                InstanceError::UnexpectedKind {
+                    path: "/root/foo".to_string(),
+                    expected: vec![JsonKind::String, JsonKind::Number, JsonKind::Boolean],
+                    actual: JsonKind::Object,
+                },
+            ]
+        );
+        assert_eq!(
+            errors[2].to_string(),
+            "Expected a string, a number or a boolean at /root/foo but found an object."
+        );
+    }
+
+    #[test]
+    fn it_types_leaf_values() {
+        let pkg = get_package(TYPED_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({ "root": { "small-number": "7", "enabled": "false", "ports": [80] } });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+
+        let values = RefCell::new(Vec::new());
+        instance.visit(&|node| match node {
+            NodeToVisit::LeafInstance(x) => values.borrow_mut().push(x.value.clone()),
+            NodeToVisit::LeafListInstance(x) => values
+                .borrow_mut()
+                .extend(x.children.iter().map(|c| c.value.clone())),
+        });
+        let values = values.into_inner();
+
+        assert!(values.contains(&LeafValue::Uint8(7)));
+        assert!(values.contains(&LeafValue::Boolean(false)));
+        assert!(values.iter().any(|x| x.as_u16() == Some(80)));
+
+        let v = json!({ "root": { "small-number": 300 } });
+        let errors = super::DataModelInstance::new(data_model.clone(), v)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "Invalid uint8 value 300 at /root/smallNumber."
+        );
+
+        // Strictly, values have to be of the kind of JSON value RFC 7951
+        // encodes them as.
+        let options = ParseOptions {
+            strict_kinds: true,
+            ..Default::default()
+        };
+        let v = json!({ "root": { "small-number": "7" } });
+        let errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "Expected a number at /root/smallNumber but found a string."
+        );
+    }
+
+    #[test]
+    fn it_validates_restrictions() {
+        let xml = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:prefix value="t"/>
+            <yin:identity name="if-type"/>
+            <yin:identity name="ethernet">
+                <yin:base name="t:if-type"/>
+            </yin:identity>
+            <yin:identity name="loopback"/>
+            <yin:container name="root">
+                <yin:leaf name="port">
+                    <yin:type name="uint16">
+                        <yin:range value="1..1024"/>
+                    </yin:type>
+                </yin:leaf>
+                <yin:leaf-list name="kind">
+                    <yin:type name="identityref">
+                        <yin:base name="t:if-type"/>
+                    </yin:type>
+                </yin:leaf-list>
+                <yin:leaf name="price">
+                    <yin:type name="decimal64">
+                        <yin:fraction-digits value="2"/>
+                    </yin:type>
+                </yin:leaf>
+                <yin:leaf-list name="code">
+                    <yin:type name="string">
+                        <yin:length value="2"/>
+                        <yin:pattern value="[A-Z]+"/>
+                    </yin:type>
+                </yin:leaf-list>
+                <yin:list name="peer">
+                    <yin:key value="name"/>
+                    <yin:leaf name="name">
+                        <yin:type name="string">
+                            <yin:pattern value="x.*">
+                                <yin:modifier value="invert-match"/>
+                            </yin:pattern>
+                        </yin:type>
+                    </yin:leaf>
+                </yin:list>
+            </yin:container>
+        </yin:module>"#;
+        let pkg = get_package(xml).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+
+        let v = json!({
+            "root": {
+                "port": 80,
+                "price": "1.50",
+                "code": ["GB"],
+                "kind": ["ethernet", "test:ethernet"]
+            }
+        });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+        assert_eq!(instance.validate(), Ok(()));
+
+        let v = json!({
+            "root": {
+                "port": 8080,
+                "price": "1.505",
+                "code": ["GB", "usa"],
+                "kind": ["loopback", "test:token-ring"],
+                "peer": [{ "name": "xyz" }]
+            }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let mut errors: Vec<String> = instance
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        errors.sort();
+
+        assert_eq!(
+            errors,
+            vec![
+                "Invalid identityref value loopback at /root/kind.",
+                "Invalid identityref value test:token-ring at /root/kind.",
+                "The length 3 of the value at /root/code is outside the length 2.",
+                "The value 1.505 at /root/price has more than 2 fraction digits.",
+                "The value 8080 at /root/port is outside the range 1..1024.",
+                "The value usa at /root/code does not match the pattern [A-Z]+.",
+                "The value xyz at /root/peer=xyz/name matches the inverted pattern x.*.",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_validates_mandatory_nodes_and_cardinality() {
+        let xml = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:container name="root">
+                <yin:container name="settings">
+                    <yin:leaf name="name">
+                        <yin:type name="string"/>
+                        <yin:mandatory value="true"/>
+                    </yin:leaf>
+                </yin:container>
+                <yin:container name="tls">
+                    <yin:presence value="enables TLS"/>
+                    <yin:leaf name="certificate">
+                        <yin:type name="string"/>
+                        <yin:mandatory value="true"/>
+                    </yin:leaf>
+                </yin:container>
+                <yin:choice name="transport">
+                    <yin:mandatory value="true"/>
+                    <yin:case name="tcp">
+                        <yin:leaf name="tcp-port">
+                            <yin:type name="uint16"/>
+                        </yin:leaf>
+                        <yin:leaf name="tcp-address">
+                            <yin:type name="string"/>
+                            <yin:mandatory value="true"/>
+                        </yin:leaf>
+                    </yin:case>
+                    <yin:leaf name="socket">
+                        <yin:type name="string"/>
+                    </yin:leaf>
+                </yin:choice>
+                <yin:leaf-list name="server">
+                    <yin:type name="string"/>
+                    <yin:min-elements value="1"/>
+                    <yin:max-elements value="2"/>
+                </yin:leaf-list>
+            </yin:container>
+        </yin:module>"#;
+        let pkg = get_package(xml).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let validate = |v: Value| {
+            let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+            let mut errors: Vec<String> = match instance.validate() {
+                Ok(()) => Vec::new(),
+                Err(x) => x.iter().map(|e| e.to_string()).collect(),
+            };
+            errors.sort();
+            errors
+        };
+
+        let v = json!({
+            "root": { "settings": { "name": "a" }, "socket": "/run/a", "server": ["x"] }
+        });
+        assert!(validate(v).is_empty());
+
+        let v = json!({ "root": { "tls": {}, "tcp-port": 80, "server": ["x", "y", "z"] } });
+        assert_eq!(
+            validate(v),
+            vec![
+                "The mandatory node /root/settings/name is missing.",
+                "The mandatory node /root/tcpAddress is missing.",
+                "The mandatory node /root/tls/certificate is missing.",
+                "There are 3 entries at /root/server but at most 2 are allowed.",
+            ]
+        );
+
+        let v = json!({ "root": { "settings": { "name": "a" } } });
+        assert_eq!(
+            validate(v),
+            vec![
+                "No case of the mandatory choice transport at /root is present.",
+                "There are 0 entries at /root/server but at least 1 are required.",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_checks_list_keys_and_unique_leaves() {
+        let xml = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:list name="server">
+                <yin:key value="name"/>
+                <yin:unique tag="endpoint/ip endpoint/port"/>
+                <yin:leaf name="name">
+                    <yin:type name="string"/>
+                </yin:leaf>
+                <yin:container name="endpoint">
+                    <yin:leaf name="ip">
+                        <yin:type name="string"/>
+                    </yin:leaf>
+                    <yin:leaf name="port">
+                        <yin:type name="uint16"/>
+                    </yin:leaf>
+                </yin:container>
+            </yin:list>
+        </yin:module>"#;
+        let pkg = get_package(xml).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+
+        let v = json!({ "server": [{ "name": "a" }, { "name": "a" }] });
+        let errors = super::DataModelInstance::new(data_model.clone(), v)
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors[0].to_string(),
+            "The list entry /server=a is duplicated."
+        );
+
+        let v = json!({
+            "server": [
+                { "name": "a", "endpoint": { "ip": "10.0.0.1", "port": 80 } },
+                { "name": "b", "endpoint": { "ip": "10.0.0.1", "port": 443 } },
+                { "name": "c", "endpoint": { "ip": "10.0.0.1", "port": 80 } },
+                { "name": "d", "endpoint": { "ip": "10.0.0.1" } },
+                { "name": "e", "endpoint": { "ip": "10.0.0.1" } }
+            ]
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let errors = instance.validate().unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![InstanceError::NotUnique {
+                path: "/server".to_string(),
+                leaves: vec!["endpoint/ip".to_string(), "endpoint/port".to_string()],
+                entries: vec!["/server=a".to_string(), "/server=c".to_string()],
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "The entries /server=a, /server=c of the list at /server have the same values for endpoint/ip endpoint/port."
+        );
+    }
+
+    #[test]
+    fn it_compares_list_keys_by_value() {
+        let xml = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:list name="pair">
+                <yin:key value="a b"/>
+                <yin:leaf name="a">
+                    <yin:type name="string"/>
+                </yin:leaf>
+                <yin:leaf name="b">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:list>
+            <yin:list name="rate">
+                <yin:key value="value"/>
+                <yin:leaf name="value">
+                    <yin:type name="decimal64">
+                        <yin:fraction-digits value="2"/>
+                    </yin:type>
+                </yin:leaf>
+            </yin:list>
+        </yin:module>"#;
+        let pkg = get_package(xml).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+
+        let v = json!({ "pair": [{ "a": "x,y", "b": "z" }, { "a": "x", "b": "y,z" }] });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+        assert_eq!(instance.get_list(&["pair"]).unwrap().get_entries().len(), 2);
+
+        let v = json!({ "rate": [{ "value": "1.5" }, { "value": "1.50" }] });
+        let errors = super::DataModelInstance::new(data_model, v).err().unwrap();
+        assert_eq!(
+            errors,
+            vec![InstanceError::DuplicateKey {
+                path: "/rate=1.5".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn it_parses_state_lists_without_keys() {
+        let xml = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:container name="stats">
+                <yin:config value="false"/>
+                <yin:list name="sample">
+                    <yin:leaf name="value">
+                        <yin:type name="uint32"/>
+                    </yin:leaf>
+                </yin:list>
+            </yin:container>
+        </yin:module>"#;
+        let pkg = get_package(xml).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+
+        let v = json!({ "stats": { "sample": [{ "value": 1 }, { "value": 1 }, { "value": 2 }] } });
+        let instance = super::DataModelInstance::new(data_model, v.clone()).unwrap();
+        assert!(instance.validate().is_ok());
+
+        let samples = instance.get_list(&["stats", "sample"]).unwrap();
+        assert_eq!(samples.get_entries().len(), 3);
+        assert_eq!(samples.get_entries()[0].get_path(), "/stats/sample");
+        assert_eq!(
+            instance.to_json(&SerializeOptions {
+                names: NameStyle::CamelCase,
+                ..Default::default()
+            }),
+            v
+        );
+    }
+
+    const LEAFREF_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:prefix value="t"/>
+        <yin:list name="tenant">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+        </yin:list>
+        <yin:list name="router">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+            <yin:leaf name="tenant">
+                <yin:type name="leafref">
+                    <yin:path value="/t:tenant/t:name"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:list name="interface">
+                <yin:key value="name"/>
+                <yin:leaf name="name">
+                    <yin:type name="string"/>
+                </yin:leaf>
+                <yin:leaf name="speed">
+                    <yin:type name="uint32"/>
+                </yin:leaf>
+            </yin:list>
+        </yin:list>
+        <yin:container name="service">
+            <yin:leaf name="router">
+                <yin:type name="leafref">
+                    <yin:path value="/t:router/t:name"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf name="interface">
+                <yin:type name="leafref">
+                    <yin:path value="/t:router[t:name = current()/../router]/t:interface/t:name"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf name="speed">
+                <yin:type name="leafref">
+                    <yin:path value="deref(../interface)/../speed"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf-list name="backup">
+                <yin:type name="leafref">
+                    <yin:path value="../../router/name"/>
+                    <yin:require-instance value="false"/>
+                </yin:type>
+            </yin:leaf-list>
+        </yin:container>
+    </yin:module>"#;
+
+    #[test]
+    fn it_follows_leafrefs() {
+        let pkg = get_package(LEAFREF_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "tenant": [{ "name": "blue" }],
+            "router": [
+                { "name": "r1", "tenant": "blue", "interface": [{ "name": "eth0", "speed": 10 }] },
+                { "name": "r2", "interface": [{ "name": "eth0", "speed": 100 }] }
+            ],
+            "service": { "router": "r2", "interface": "eth0", "speed": 100, "backup": ["r1"] }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+
+        let targets = RefCell::new(Vec::new());
+        instance.visit(&|node| {
+            if let NodeToVisit::LeafInstance(x) = node {
+                for target in x.follow() {
+                    targets.borrow_mut().push((x.get_path(), target.get_path()));
+                }
+            }
+        });
+        let mut targets = targets.into_inner();
+        targets.sort();
+
+        assert_eq!(
+            targets,
+            vec![
+                (
+                    "/router=r1/tenant".to_string(),
+                    "/tenant=blue/name".to_string()
+                ),
+                (
+                    "/service/interface".to_string(),
+                    "/router=r2/interface=eth0/name".to_string()
+                ),
+                ("/service/router".to_string(), "/router=r2/name".to_string()),
+                (
+                    "/service/speed".to_string(),
+                    "/router=r2/interface=eth0/speed".to_string()
+                ),
+            ]
+        );
+        assert_eq!(instance.validate(), Ok(()));
+    }
+
+    #[test]
+    fn it_types_leafrefs_by_their_targets() {
+        let pkg = get_package(LEAFREF_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "router": [{ "name": "r1", "interface": [{ "name": "eth0", "speed": 5 }] }],
+            "service": { "router": "r1", "interface": "eth0", "speed": 5 }
+        });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+        let json = instance.to_json(&Default::default());
+
+        assert_eq!(json["test:service"]["speed"], json!(5));
+        assert_eq!(instance.validate(), Ok(()));
+
+        // A leafref to a uint32 is encoded as a JSON number, like its target.
+        let options = ParseOptions {
+            strict_kinds: true,
+            ..Default::default()
+        };
+        let v = json!({ "service": { "speed": "5" } });
+        let errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            errors[0].to_string(),
+            "Expected a number at /service/speed but found a string."
+        );
+    }
+
+    #[test]
+    fn it_keys_lists_by_leafref_targets() {
+        let xml = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:prefix value="t"/>
+            <yin:leaf-list name="price">
+                <yin:type name="decimal64">
+                    <yin:fraction-digits value="2"/>
+                </yin:type>
+            </yin:leaf-list>
+            <yin:list name="offer">
+                <yin:key value="price"/>
+                <yin:ordered-by value="user"/>
+                <yin:leaf name="price">
+                    <yin:type name="leafref">
+                        <yin:path value="/t:price"/>
+                    </yin:type>
+                </yin:leaf>
+            </yin:list>
+        </yin:module>"#;
+        let pkg = get_package(xml).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+
+        let v = json!({
+            "price": ["1.5", "2"],
+            "offer": [{ "price": "1.5" }, { "price": "1.50" }]
+        });
+        let errors = super::DataModelInstance::new(data_model.clone(), v)
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors,
+            vec![InstanceError::DuplicateKey {
+                path: "/offer=1.5".to_string()
+            }]
+        );
+
+        let v = json!({
+            "price": ["1.5", "2"],
+            "offer": [{ "price": "1.5" }, { "price": "2" }]
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let offers = instance.get_list(&["offer"]).unwrap();
+        let key = |text: &str| {
+            let n = Number::parse(text).unwrap();
+            vec![LeafValue::Leafref(Box::new(LeafValue::Decimal64(n)))]
+        };
+
+        offers
+            .move_entry(&key("2.00"), &Position::Before(key("1.50")))
+            .unwrap();
+        let keys: Vec<String> = offers.get_entries().iter().map(|e| e.get_key()).collect();
+        assert_eq!(keys, vec!["2", "1.5"]);
+        assert_eq!(instance.validate(), Ok(()));
+    }
+
+    #[test]
+    fn it_reports_dangling_leafrefs() {
+        let pkg = get_package(LEAFREF_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "router": [{ "name": "r1", "tenant": "red", "interface": [{ "name": "eth0" }] }],
+            "service": { "router": "r1", "interface": "eth1", "backup": ["r9"] }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let mut errors: Vec<String> = instance
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|e| e.to_string())
+            .collect();
+        errors.sort();
+
+        assert_eq!(
+            errors,
+            vec![
+                "The leafref at /router=r1/tenant refers to red, which no node at /t:tenant/t:name has.",
+                "The leafref at /service/interface refers to eth1, which no node at /t:router[t:name = current()/../router]/t:interface/t:name has.",
+            ]
+        );
+    }
+
+    const CONSTRAINT_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:prefix value="t"/>
+        <yin:identity name="if-type"/>
+        <yin:identity name="ethernet">
+            <yin:base name="t:if-type"/>
+        </yin:identity>
+        <yin:identity name="fast-ethernet">
+            <yin:base name="t:ethernet"/>
+        </yin:identity>
+        <yin:identity name="loopback">
+            <yin:base name="t:if-type"/>
+        </yin:identity>
+        <yin:list name="interface">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+            <yin:leaf name="type">
+                <yin:type name="identityref">
+                    <yin:base name="t:if-type"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf name="mtu">
+                <yin:type name="uint16"/>
+                <yin:when condition="derived-from-or-self(../t:type, 't:ethernet')"/>
+                <yin:must condition=". &gt;= 64">
+                    <yin:error-message>
+                        <yin:value>The MTU must be at least 64.</yin:value>
+                    </yin:error-message>
+                    <yin:error-app-tag value="mtu-too-small"/>
+                </yin:must>
+            </yin:leaf>
+            <yin:leaf name="state">
+                <yin:type name="enumeration">
+                    <yin:enum name="up">
+                        <yin:value value="1"/>
+                    </yin:enum>
+                    <yin:enum name="down">
+                        <yin:value value="2"/>
+                    </yin:enum>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf name="flags">
+                <yin:type name="bits">
+                    <yin:bit name="exclusive"/>
+                    <yin:bit name="managed"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf name="description">
+                <yin:type name="string"/>
+                <yin:must condition="re-match(., '[a-z ]*')"/>
+            </yin:leaf>
+        </yin:list>
+        <yin:container name="service">
+            <yin:must condition="deref(t:interface)/../t:state = 'up'">
+                <yin:error-message>
+                    <yin:value>The service interface must be up.</yin:value>
+                </yin:error-message>
+            </yin:must>
+            <yin:leaf name="interface">
+                <yin:type name="leafref">
+                    <yin:path value="/t:interface/t:name"/>
+                </yin:type>
+            </yin:leaf>
+            <yin:leaf name="priority">
+                <yin:type name="uint8"/>
+                <yin:when condition="enum-value(deref(../t:interface)/../t:state) = 1"/>
+            </yin:leaf>
+            <yin:leaf name="shared">
+                <yin:type name="boolean"/>
+                <yin:must condition="current() = 'false' or not(bit-is-set(deref(../interface)/../flags, 'exclusive'))"/>
+            </yin:leaf>
+        </yin:container>
+        <yin:augment target-node="/t:service">
+            <yin:when condition="t:interface = 'eth0'"/>
+            <yin:leaf name="note">
+                <yin:type name="string"/>
+            </yin:leaf>
+        </yin:augment>
+    </yin:module>"#;
+
+    #[test]
+    fn it_evaluates_must_and_when() {
+        let pkg = get_package(CONSTRAINT_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "interface": [
+                { "name": "lo", "type": "test:loopback", "description": "local" },
+                {
+                    "name": "eth0",
+                    "type": "test:fast-ethernet",
+                    "mtu": 1500,
+                    "state": "up",
+                    "flags": "exclusive"
+                }
+            ],
+            "service": { "interface": "eth0", "priority": 5, "shared": false, "note": "x" }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        assert_eq!(instance.validate(), Ok(()));
+    }
+
+    #[test]
+    fn it_reports_must_and_when_violations() {
+        let pkg = get_package(CONSTRAINT_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "interface": [
+                { "name": "lo", "type": "loopback", "mtu": 1500 },
+                {
+                    "name": "eth0",
+                    "type": "fast-ethernet",
+                    "mtu": 32,
+                    "state": "down",
+                    "flags": "exclusive",
+                    "description": "Uplink"
+                }
+            ],
+            "service": { "interface": "eth0", "priority": 5, "shared": true }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let errors = instance.validate().unwrap_err();
+
+        assert!(errors.contains(&InstanceError::MustViolation {
+            path: "/interface=eth0/mtu".to_string(),
+            condition: ". >= 64".to_string(),
+            error_message: Some("The MTU must be at least 64.".to_string()),
+            error_app_tag: Some("mtu-too-small".to_string()),
+        }));
+
+        let mut errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        errors.sort();
+
+        assert_eq!(
+            errors,
+            vec![
+                "The MTU must be at least 64. (/interface=eth0/mtu)",
+                "The must condition current() = 'false' or not(bit-is-set(deref(../interface)/../flags, 'exclusive')) at /service/shared is false.",
+                "The must condition re-match(., '[a-z ]*') at /interface=eth0/description is false.",
+                "The node /interface=lo/mtu exists but its when condition derived-from-or-self(../t:type, 't:ethernet') is false.",
+                "The node /service/priority exists but its when condition enum-value(deref(../t:interface)/../t:state) = 1 is false.",
+                "The service interface must be up. (/service)",
+            ]
+        );
+    }
+
+    const DEFAULTS_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:typedef name="port-number">
+            <yin:type name="uint16"/>
+            <yin:default value="8080"/>
+        </yin:typedef>
+        <yin:container name="settings">
+            <yin:leaf name="port">
+                <yin:type name="port-number"/>
+            </yin:leaf>
+            <yin:leaf name="host">
+                <yin:type name="string"/>
+                <yin:default value="localhost"/>
+            </yin:leaf>
+            <yin:leaf-list name="dns">
+                <yin:type name="string"/>
+                <yin:default value="1.1.1.1"/>
+                <yin:default value="8.8.8.8"/>
+            </yin:leaf-list>
+            <yin:container name="logging">
+                <yin:leaf name="level">
+                    <yin:type name="enumeration">
+                        <yin:enum name="info"/>
+                        <yin:enum name="debug"/>
+                    </yin:type>
+                    <yin:default value="info"/>
+                </yin:leaf>
+            </yin:container>
+            <yin:container name="tls">
+                <yin:presence value="TLS is enabled."/>
+                <yin:leaf name="version">
+                    <yin:type name="string"/>
+                    <yin:default value="1.3"/>
+                </yin:leaf>
+            </yin:container>
+            <yin:choice name="transport">
+                <yin:default value="tcp"/>
+                <yin:case name="tcp">
+                    <yin:leaf name="tcp-port">
+                        <yin:type name="uint16"/>
+                        <yin:default value="80"/>
+                    </yin:leaf>
+                </yin:case>
+                <yin:case name="udp">
+                    <yin:leaf name="udp-port">
+                        <yin:type name="uint16"/>
+                        <yin:default value="53"/>
+                    </yin:leaf>
+                    <yin:leaf name="udp-only">
+                        <yin:type name="boolean"/>
+                    </yin:leaf>
+                </yin:case>
+            </yin:choice>
+            <yin:leaf name="proxy">
+                <yin:type name="string"/>
+                <yin:when condition="../host != 'localhost'"/>
+                <yin:default value="none"/>
+            </yin:leaf>
+        </yin:container>
+    </yin:module>"#;
+
+    #[test]
+    fn it_gets_effective_values() {
+        let pkg = get_package(DEFAULTS_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({ "settings": { "host": "example.com" } });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+        let get = |path: &str| {
+            instance
+                .get_effective_value(&path.split('/').collect::<Vec<_>>())
+                .map(|v| v.to_string())
+        };
+
+        assert_eq!(get("settings/port"), Some("8080".to_string()));
+        assert_eq!(get("settings/host"), Some("example.com".to_string()));
+        assert_eq!(get("settings/logging/level"), Some("info".to_string()));
+        assert_eq!(get("settings/tls/version"), None);
+        assert_eq!(get("settings/tcpPort"), Some("80".to_string()));
+        assert_eq!(get("settings/udpPort"), None);
+        assert_eq!(get("settings/proxy"), None);
+        assert_eq!(
+            instance.to_json(&SerializeOptions::default()),
+            json!({ "test:settings": { "host": "example.com" } })
+        );
+
+        let v = json!({ "settings": { "udp-only": true } });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        assert_eq!(
+            instance
+                .get_effective_value(&["settings", "udpPort"])
+                .map(|v| v.to_string()),
+            Some("53".to_string())
+        );
+        assert_eq!(instance.get_effective_value(&["settings", "tcpPort"]), None);
+    }
+
+    #[test]
+    fn it_applies_defaults() {
+        let pkg = get_package(DEFAULTS_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({ "settings": { "host": "example.com", "tls": {} } });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        instance.apply_defaults();
+
+        let report_all = SerializeOptions {
+            with_defaults: WithDefaults::ReportAll,
+            ..Default::default()
+        };
+        assert_eq!(
+            instance.to_json(&report_all),
+            json!({
+                "test:settings": {
+                    "port": 8080,
+                    "host": "example.com",
+                    "dns": ["1.1.1.1", "8.8.8.8"],
+                    "logging": { "level": "info" },
+                    "tls": { "version": "1.3" },
+                    "tcp-port": 80
+                }
+            })
+        );
+
+        let defaults = RefCell::new(Vec::new());
+        instance.visit(&|node| match node {
+            NodeToVisit::LeafInstance(x) if x.is_default => {
+                defaults.borrow_mut().push(x.get_path())
+            }
+            NodeToVisit::LeafListInstance(x) if x.is_default => {
+                defaults.borrow_mut().push(x.get_path())
+            }
+            _ => (),
+        });
+        let mut defaults = defaults.into_inner();
+        defaults.sort();
+
+        assert_eq!(
+            defaults,
+            vec![
+                "/settings/dns",
+                "/settings/logging/level",
+                "/settings/port",
+                "/settings/tcpPort",
+                "/settings/tls/version",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_serializes_with_defaults() {
+        let pkg = get_package(DEFAULTS_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "settings": { "host": "localhost", "port": 9000, "logging": { "level": "info" } }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let to_json = |with_defaults| {
+            instance.to_json(&SerializeOptions {
+                with_defaults,
+                ..Default::default()
+            })
+        };
+
+        let explicit = json!({
+            "test:settings": { "host": "localhost", "port": 9000, "logging": { "level": "info" } }
+        });
+        assert_eq!(to_json(WithDefaults::Explicit), explicit);
+        assert_eq!(
+            to_json(WithDefaults::Trim),
+            json!({ "test:settings": { "port": 9000 } })
+        );
+        assert_eq!(
+            to_json(WithDefaults::ReportAll),
+            json!({
+                "test:settings": {
+                    "host": "localhost",
+                    "port": 9000,
+                    "dns": ["1.1.1.1", "8.8.8.8"],
+                    "logging": { "level": "info" },
+                    "tcp-port": 80
+                }
+            })
+        );
+
+        let tag = json!({ "ietf-netconf-with-defaults:default": true });
+        assert_eq!(
+            to_json(WithDefaults::ReportAllTagged),
+            json!({
+                "test:settings": {
+                    "host": "localhost",
+                    "@host": tag,
+                    "port": 9000,
+                    "dns": ["1.1.1.1", "8.8.8.8"],
+                    "@dns": [tag, tag],
+                    "logging": { "level": "info", "@level": tag },
+                    "tcp-port": 80,
+                    "@tcp-port": tag
+                }
+            })
+        );
+
+        instance.apply_defaults();
+        assert_eq!(to_json(WithDefaults::Explicit), explicit);
+    }
+
+    #[test]
+    fn it_serializes_to_rfc_7951() {
+        let pkg = get_package(TYPED_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "root": {
+                "small-number": 5,
+                "big-number": "18446744073709551615",
+                "price": "1.50",
+                "enabled": true,
+                "flag": [null],
+                "mixed": "12",
+                "ports": [80, 443],
+                "peer": [{ "name": "a" }],
+                "extra": "x"
+            }
+        });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+
+        assert_eq!(
+            instance.to_json(&SerializeOptions::default()),
+            json!({
+                "test:root": {
+                    "small-number": 5,
+                    "big-number": "18446744073709551615",
+                    "price": "1.5",
+                    "enabled": true,
+                    "flag": [null],
+                    "mixed": 12,
+                    "ports": [80, 443],
+                    "peer": [{ "name": "a" }],
+                    "ext:extra": "x"
+                }
+            })
+        );
+
+        let camel_case = SerializeOptions {
+            names: NameStyle::CamelCase,
+            ..Default::default()
+        };
+        let output = instance.to_json(&camel_case);
+        assert_eq!(output["root"]["smallNumber"], json!(5));
+        assert_eq!(output["root"]["ext:extra"], json!("x"));
+
+        let reparsed = super::DataModelInstance::new(data_model, output.clone()).unwrap();
+        assert_eq!(reparsed.to_json(&camel_case), output);
+    }
+
+    const STATE_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:list name="interface">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+            <yin:leaf name="mtu">
+                <yin:type name="uint16"/>
+            </yin:leaf>
+            <yin:container name="statistics">
+                <yin:config value="false"/>
+                <yin:leaf name="in-octets">
+                    <yin:type name="uint64"/>
+                </yin:leaf>
+            </yin:container>
+            <yin:leaf name="oper-status">
+                <yin:type name="string"/>
+                <yin:config value="false"/>
+            </yin:leaf>
+        </yin:list>
+        <yin:container name="system">
+            <yin:leaf name="hostname">
+                <yin:type name="string"/>
+            </yin:leaf>
+        </yin:container>
+    </yin:module>"#;
+
+    #[test]
+    fn it_separates_config_and_state() {
+        let pkg = get_package(STATE_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+
+        match data_model.root.get_child("interface") {
+            Some(Model::List(x)) => {
+                assert!(x.config);
+                assert!(!x.get_child("statistics").unwrap().is_config());
+                assert!(!x.get_child("operStatus").unwrap().is_config());
+                match x.get_child("statistics") {
+                    Some(Model::Container(c)) => {
+                        assert!(!c.get_child("inOctets").unwrap().is_config())
+                    }
+                    _ => panic!("Expected the statistics container."),
+                }
+            }
+            _ => panic!("Expected the interface list."),
+        }
+
+        let v = json!({
+            "interface": [
+                { "name": "eth0", "mtu": 1500, "oper-status": "up" },
+                { "name": "eth1", "statistics": { "in-octets": 10 } },
+                { "name": "eth2", "mtu": 9000 }
+            ],
+            "system": { "hostname": "router" }
+        });
+        let to_json = |instance: &super::DataModelInstance| {
+            let mut output = instance.to_json(&SerializeOptions::default());
+            output["test:interface"]
+                .as_array_mut()
+                .unwrap()
+                .sort_by_key(|e| e["name"].as_str().unwrap().to_string());
+            output
+        };
+
+        let instance = super::DataModelInstance::new(data_model.clone(), v.clone()).unwrap();
+        instance.strip_state();
+        assert_eq!(
+            to_json(&instance),
+            json!({
+                "test:interface": [
+                    { "name": "eth0", "mtu": 1500 },
+                    { "name": "eth1" },
+                    { "name": "eth2", "mtu": 9000 }
+                ],
+                "test:system": { "hostname": "router" }
+            })
+        );
+
+        let instance = super::DataModelInstance::new(data_model.clone(), v.clone()).unwrap();
+        instance.extract_state();
+        assert_eq!(
+            to_json(&instance),
+            json!({
+                "test:interface": [
+                    { "name": "eth0", "oper-status": "up" },
+                    { "name": "eth1", "statistics": { "in-octets": "10" } }
+                ]
+            })
+        );
+
+        let options = ParseOptions {
+            config_only: true,
+            collect_errors: true,
+            ..Default::default()
+        };
+        let errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+        let mut messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        messages.sort();
+        assert_eq!(
+            messages,
+            vec![
+                "The node at /interface=eth0/operStatus is state data, which is not allowed \
+                 in configuration.",
+                "The node at /interface=eth1/statistics is state data, which is not allowed \
+                 in configuration.",
+            ]
+        );
+    }
+
+    const CHOICE_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:choice name="transport">
+            <yin:case name="tcp">
+                <yin:leaf name="tcp-port">
+                    <yin:type name="uint16"/>
+                </yin:leaf>
+            </yin:case>
+            <yin:case name="udp">
+                <yin:leaf name="udp-port">
+                    <yin:type name="uint16"/>
+                </yin:leaf>
+                <yin:choice name="checksum">
+                    <yin:leaf name="crc">
+                        <yin:type name="empty"/>
+                    </yin:leaf>
+                    <yin:leaf name="none">
+                        <yin:type name="empty"/>
+                    </yin:leaf>
+                </yin:choice>
+            </yin:case>
+        </yin:choice>
+    </yin:module>"#;
+
+    #[test]
+    fn it_checks_choice_cases() {
+        let pkg = get_package(CHOICE_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let choice = data_model.root.get_choice("checksum").unwrap();
+        assert_eq!(choice.get_case("crc").unwrap().name, "crc");
+        assert_eq!(
+            data_model
+                .root
+                .get_choice("transport")
+                .unwrap()
+                .get_case("crc")
+                .unwrap()
+                .name,
+            "udp"
+        );
+
+        let v = json!({ "udp-port": 53, "none": [null] });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+        assert_eq!(instance.validate(), Ok(()));
+        assert_eq!(
+            instance.root.get_active_case("transport"),
+            Some("udp".to_string())
+        );
+        assert_eq!(
+            instance.root.get_active_case("checksum"),
+            Some("none".to_string())
+        );
+
+        let v = json!({ "tcp-port": 80 });
+        let instance = super::DataModelInstance::new(data_model.clone(), v).unwrap();
+        assert_eq!(
+            instance.root.get_active_case("transport"),
+            Some("tcp".to_string())
+        );
+        assert_eq!(instance.root.get_active_case("checksum"), None);
+        assert_eq!(instance.root.get_active_case("unknown"), None);
+
+        let v = json!({ "tcp-port": 80, "udp-port": 53, "crc": [null], "none": [null] });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let errors = instance.validate().unwrap_err();
+        assert_eq!(
+            errors,
+            vec![InstanceError::ConflictingCases {
+                path: "".to_string(),
+                choice: "transport".to_string(),
+                cases: vec!["tcp".to_string(), "udp".to_string()],
+            }]
+        );
+        assert_eq!(
+            errors[0].to_string(),
+            "The cases tcp, udp of the choice transport at / all have data, but only one may."
+        );
+    }
+
+    const NAMES_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:list name="access-rule">
+            <yin:key value="rule-name"/>
+            <yin:leaf name="rule-name">
+                <yin:type name="string"/>
+            </yin:leaf>
+            <yin:leaf name="max-hits">
+                <yin:type name="uint32"/>
+            </yin:leaf>
+        </yin:list>
+    </yin:module>"#;
+
+    #[test]
+    fn it_parses_member_names_by_naming_convention() {
+        let pkg = get_package(NAMES_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let parse = |v: Value, names| {
+            let options = ParseOptions {
+                collect_errors: true,
+                unknown_members: UnknownMembers::Reject,
+                names,
+                ..Default::default()
+            };
+            super::DataModelInstance::with_options(data_model.clone(), v, options)
+        };
+
+        let yang = json!({ "access-rule": [{ "rule-name": "a", "max-hits": 1 }] });
+        let camel_case = json!({ "accessRule": [{ "ruleName": "a", "maxHits": 1 }] });
+        let mixed = json!({ "access-rule": [{ "ruleName": "a", "max-hits": 1 }] });
+
+        for names in [None, Some(NameStyle::Yang)] {
+            let instance = parse(yang.clone(), names).ok().unwrap();
+            assert_eq!(
+                instance.to_json(&SerializeOptions::default()),
+                json!({ "test:access-rule": [{ "rule-name": "a", "max-hits": 1 }] })
+            );
+        }
+
+        for names in [None, Some(NameStyle::CamelCase)] {
+            let instance = parse(camel_case.clone(), names).ok().unwrap();
+            let options = SerializeOptions {
+                names: NameStyle::CamelCase,
+                ..Default::default()
+            };
+            assert_eq!(instance.to_json(&options), camel_case);
+        }
+
+        assert!(parse(mixed.clone(), None).is_ok());
+        assert_eq!(
+            parse(mixed, Some(NameStyle::Yang)).err().unwrap(),
+            vec![InstanceError::MissingKey {
+                path: "/accessRule".to_string(),
+                key: "ruleName".to_string(),
+            }]
+        );
+        assert_eq!(
+            parse(camel_case, Some(NameStyle::Yang)).err().unwrap(),
+            vec![InstanceError::UnknownMember {
+                path: "/accessRule".to_string(),
+            }]
+        );
+        assert_eq!(
+            parse(yang, Some(NameStyle::CamelCase)).err().unwrap(),
+            vec![InstanceError::UnknownMember {
+                path: "/access-rule".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn it_keeps_apart_names_that_share_a_camel_case_name() {
+        let pkg = get_package(
+            r#"<?xml version="1.0"?>
+            <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                <yin:container name="root">
+                    <yin:leaf name="foo-bar">
+                        <yin:type name="string"/>
+                    </yin:leaf>
+                    <yin:leaf name="foo_bar">
+                        <yin:type name="string"/>
+                    </yin:leaf>
+                </yin:container>
+            </yin:module>"#,
+        )
+        .unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let options = ParseOptions {
+            unknown_members: UnknownMembers::Reject,
+            ..Default::default()
+        };
+
+        let v = json!({ "root": { "foo-bar": "a", "foo_bar": "b" } });
+        let instance = super::DataModelInstance::with_options(data_model.clone(), v, options)
+            .ok()
+            .unwrap();
+        assert_eq!(
+            instance.get_effective_value(&["root", "foo_bar"]),
+            Some(LeafValue::String("b".to_string()))
+        );
+        assert_eq!(
+            instance.to_json(&SerializeOptions::default()),
+            json!({ "test:root": { "foo-bar": "a", "foo_bar": "b" } })
+        );
+
+        let v = json!({ "root": { "fooBar": "a" } });
+        let errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors,
+            vec![InstanceError::UnknownMember {
+                path: "/root/fooBar".to_string()
+            }]
+        );
+    }
+
+    const MODULES_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <module-set>
+        <yin:module name="a" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:prefix value="a"/>
+            <yin:container name="system">
+                <yin:leaf name="enabled">
+                    <yin:type name="boolean"/>
+                </yin:leaf>
+            </yin:container>
+        </yin:module>
+        <yin:module name="b" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:prefix value="b"/>
+            <yin:import module="a">
+                <yin:prefix value="a"/>
+            </yin:import>
+            <yin:container name="system">
+                <yin:leaf name="hostname">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:container>
+            <yin:augment target-node="/a:system">
+                <yin:leaf name="enabled">
+                    <yin:type name="string"/>
+                </yin:leaf>
+                <yin:leaf name="log-level">
+                    <yin:type name="uint8"/>
+                </yin:leaf>
+            </yin:augment>
+        </yin:module>
+    </module-set>"#;
+
+    #[test]
+    fn it_parses_qualified_member_names() {
+        let pkg = get_package(MODULES_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let options = ParseOptions {
+            unknown_members: UnknownMembers::Reject,
+            ..Default::default()
+        };
+        let v = json!({
+            "a:system": { "enabled": true, "b:enabled": "yes", "log-level": 3 },
+            "b:system": { "hostname": "router" }
+        });
+        let instance =
+            super::DataModelInstance::with_options(data_model.clone(), v.clone(), options)
+                .ok()
+                .unwrap();
+
+        assert_eq!(
+            instance.get_effective_value(&["a:system", "b:enabled"]),
+            Some(LeafValue::String("yes".to_string()))
+        );
+        assert_eq!(
+            instance.get_effective_value(&["a:system", "enabled"]),
+            Some(LeafValue::Boolean(true))
+        );
+        assert_eq!(
+            instance.to_json(&SerializeOptions::default()),
+            json!({
+                "a:system": { "enabled": true, "b:enabled": "yes", "b:log-level": 3 },
+                "b:system": { "hostname": "router" }
+            })
+        );
+
+        let camel_case = SerializeOptions {
+            names: NameStyle::CamelCase,
+            ..Default::default()
+        };
+        let output = instance.to_json(&camel_case);
+        assert_eq!(
+            output,
+            json!({
+                "a:system": { "enabled": true, "b:enabled": "yes", "b:logLevel": 3 },
+                "b:system": { "hostname": "router" }
+            })
+        );
+        let reparsed = super::DataModelInstance::new(data_model.clone(), output.clone()).unwrap();
+        assert_eq!(reparsed.to_json(&camel_case), output);
+
+        let v = json!({ "system": {} });
+        let errors = super::DataModelInstance::with_options(data_model, v, options)
+            .err()
+            .unwrap();
+        assert_eq!(
+            errors,
+            vec![InstanceError::UnknownMember {
+                path: "/system".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_document_order() {
+        let pkg = get_package(STATE_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "system": { "hostname": "router" },
+            "interface": [
+                { "oper-status": "up", "name": "eth2", "mtu": 1500 },
+                { "name": "eth0" },
+                { "name": "eth1", "mtu": 9000 }
+            ]
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+
+        assert_eq!(
+            to_string(&instance.to_json(&SerializeOptions::default())).unwrap(),
+            concat!(
+                r#"{"test:system":{"hostname":"router"},"#,
+                r#""test:interface":[{"oper-status":"up","name":"eth2","mtu":1500},"#,
+                r#"{"name":"eth0"},{"name":"eth1","mtu":9000}]}"#
+            )
+        );
+    }
+
+    const ORDERED_DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:container name="acl">
+            <yin:list name="rule">
+                <yin:key value="name"/>
+                <yin:ordered-by value="user"/>
+                <yin:leaf name="name">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:list>
+            <yin:list name="group">
+                <yin:key value="name"/>
+                <yin:leaf name="name">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:list>
+        </yin:container>
+    </yin:module>"#;
+
+    #[test]
+    fn it_inserts_and_moves_list_entries() {
+        let pkg = get_package(ORDERED_DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "acl": {
+                "rule": [{ "name": "b" }, { "name": "d" }],
+                "group": [{ "name": "x" }]
+            }
+        });
+        let instance = super::DataModelInstance::new(data_model, v).unwrap();
+        let rules = instance.get_list(&["acl", "rule"]).unwrap();
+        let key = |name: &str| vec![LeafValue::String(name.to_string())];
+        let keys = || -> Vec<String> { rules.get_entries().iter().map(|e| e.get_key()).collect() };
+
+        let insert = |name: &str, position: Position| {
+            rules.insert(json!({ "name": name }), &position, ParseOptions::default())
+        };
+        insert("a", Position::First).unwrap();
+        insert("e", Position::Last).unwrap();
+        insert("c", Position::Before(key("d"))).unwrap();
+        assert_eq!(keys(), vec!["a", "b", "c", "d", "e"]);
+
+        assert_eq!(
+            insert("c", Position::First).err().unwrap(),
+            vec![InstanceError::DuplicateKey {
+                path: "/acl/rule=c".to_string()
+            }]
+        );
+        assert_eq!(
+            insert("f", Position::After(key("z"))).err().unwrap()[0].to_string(),
+            "The list entry /acl/rule=z does not exist."
+        );
+
+        rules
+            .move_entry(&key("a"), &Position::After(key("c")))
+            .unwrap();
+        assert_eq!(keys(), vec!["b", "c", "a", "d", "e"]);
+        rules
+            .move_entry(&key("e"), &Position::Before(key("c")))
+            .unwrap();
+        assert_eq!(keys(), vec!["b", "e", "c", "a", "d"]);
+        rules.move_entry(&key("b"), &Position::Last).unwrap();
+        rules.move_entry(&key("d"), &Position::First).unwrap();
+        rules
+            .move_entry(&key("c"), &Position::After(key("c")))
+            .unwrap();
+        assert_eq!(keys(), vec!["d", "e", "c", "a", "b"]);
+        assert!(rules.move_entry(&key("z"), &Position::First).is_err());
+
+        let output = to_string(&instance.to_json(&SerializeOptions::default())).unwrap();
+        assert!(output.contains(
+            r#""rule":[{"name":"d"},{"name":"e"},{"name":"c"},{"name":"a"},{"name":"b"}]"#
+        ));
+
+        let groups = instance.get_list(&["acl", "group"]).unwrap();
+        groups
+            .insert(
+                json!({ "name": "y" }),
+                &Position::Last,
+                ParseOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            groups
+                .insert(
+                    json!({ "name": "w" }),
+                    &Position::First,
+                    ParseOptions::default()
+                )
+                .err()
+                .unwrap()[0]
+                .to_string(),
+            "The list at /acl/group is not ordered-by user, so entries can only be added last."
+        );
+        assert!(groups.move_entry(&key("y"), &Position::First).is_err());
+    }
 }
@@ -0,0 +1,400 @@
+use serde_json::Value;
+use std::error::Error;
+use std::fmt;
+
+/// The kinds of JSON value, for describing what a schema node expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonKind {
+    Null,
+    Boolean,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl JsonKind {
+    pub fn of(value: &Value) -> JsonKind {
+        match value {
+            Value::Null => JsonKind::Null,
+            Value::Bool(_) => JsonKind::Boolean,
+            Value::Number(_) => JsonKind::Number,
+            Value::String(_) => JsonKind::String,
+            Value::Array(_) => JsonKind::Array,
+            Value::Object(_) => JsonKind::Object,
+        }
+    }
+}
+
+impl fmt::Display for JsonKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            JsonKind::Null => "null",
+            JsonKind::Boolean => "a boolean",
+            JsonKind::Number => "a number",
+            JsonKind::String => "a string",
+            JsonKind::Array => "an array",
+            JsonKind::Object => "an object",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// An error in the data of an instance. The `path` is that of the data node
+/// the error was found at, as returned by its `get_path`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstanceError {
+    /// A value is not of a kind of JSON value its schema node accepts.
+    UnexpectedKind {
+        path: String,
+        expected: Vec<JsonKind>,
+        actual: JsonKind,
+    },
+    /// An entry of the list at `path` lacks one of the list's keys.
+    MissingKey { path: String, key: String },
+    /// A list has more than one entry with the key of the entry at `path`.
+    DuplicateKey { path: String },
+    /// An entry to insert or move relative to, at `path`, does not exist.
+    MissingEntry { path: String },
+    /// An entry was placed anywhere but last in the list at `path`, which
+    /// is not `ordered-by user`.
+    NotOrderedByUser { path: String },
+    /// Entries of the list at `path` have the same values for the leaves of
+    /// one of its `unique` statements.
+    NotUnique {
+        path: String,
+        leaves: Vec<String>,
+        entries: Vec<String>,
+    },
+    /// A leafref requiring an instance has a `value` that no node selected by
+    /// its `target` path has.
+    DanglingReference {
+        path: String,
+        value: String,
+        target: String,
+    },
+    /// A member matches no schema node, which is only an error when unknown
+    /// members are rejected.
+    UnknownMember { path: String },
+    /// A node is state data, which is only an error when parsing
+    /// configuration alone.
+    StateData { path: String },
+    /// A scalar is not in the value space of the built-in type named
+    /// `type_name`.
+    InvalidValue {
+        path: String,
+        type_name: String,
+        value: String,
+    },
+    /// A number is outside of the `range` of its type.
+    OutOfRange {
+        path: String,
+        value: String,
+        range: String,
+    },
+    /// A string or binary value is outside of the `length` of its type.
+    InvalidLength {
+        path: String,
+        length: usize,
+        allowed: String,
+    },
+    /// A string is not allowed by one of the `pattern`s of its type.
+    PatternMismatch {
+        path: String,
+        value: String,
+        pattern: String,
+        invert_match: bool,
+    },
+    /// A `mandatory` leaf is missing.
+    MissingMandatory { path: String },
+    /// None of the cases of a `mandatory` choice in the node at `path` has
+    /// any data.
+    MissingChoice { path: String, choice: String },
+    /// More than one of the `cases` of a choice in the node at `path` has
+    /// data, where only one may.
+    ConflictingCases {
+        path: String,
+        choice: String,
+        cases: Vec<String>,
+    },
+    /// A list or leaf-list has fewer entries than its `min-elements`.
+    TooFewElements {
+        path: String,
+        count: usize,
+        min: u64,
+    },
+    /// A list or leaf-list has more entries than its `max-elements`.
+    TooManyElements {
+        path: String,
+        count: usize,
+        max: u64,
+    },
+    /// A decimal64 has more fraction digits than its type allows.
+    TooManyFractionDigits {
+        path: String,
+        value: String,
+        fraction_digits: u8,
+    },
+    /// A node exists although a `when` condition it depends on is false.
+    WhenFalse { path: String, condition: String },
+    /// A `must` condition of a node is false, with the `error-message` and
+    /// `error-app-tag` of the `must` statement, if any.
+    MustViolation {
+        path: String,
+        condition: String,
+        error_message: Option<String>,
+        error_app_tag: Option<String>,
+    },
+    /// A `must` or `when` condition could not be evaluated, such as when it
+    /// calls an unknown function.
+    InvalidCondition {
+        path: String,
+        condition: String,
+        message: String,
+    },
+}
+
+impl InstanceError {
+    pub fn unexpected_kind(path: &str, expected: &[JsonKind], actual: &Value) -> InstanceError {
+        InstanceError::UnexpectedKind {
+            path: path.to_string(),
+            expected: expected.to_vec(),
+            actual: JsonKind::of(actual),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        match self {
+            InstanceError::UnexpectedKind { path, .. }
+            | InstanceError::MissingKey { path, .. }
+            | InstanceError::DuplicateKey { path }
+            | InstanceError::MissingEntry { path }
+            | InstanceError::NotOrderedByUser { path }
+            | InstanceError::NotUnique { path, .. }
+            | InstanceError::DanglingReference { path, .. }
+            | InstanceError::UnknownMember { path }
+            | InstanceError::StateData { path }
+            | InstanceError::InvalidValue { path, .. }
+            | InstanceError::OutOfRange { path, .. }
+            | InstanceError::InvalidLength { path, .. }
+            | InstanceError::PatternMismatch { path, .. }
+            | InstanceError::MissingMandatory { path }
+            | InstanceError::MissingChoice { path, .. }
+            | InstanceError::ConflictingCases { path, .. }
+            | InstanceError::TooFewElements { path, .. }
+            | InstanceError::TooManyElements { path, .. }
+            | InstanceError::TooManyFractionDigits { path, .. }
+            | InstanceError::WhenFalse { path, .. }
+            | InstanceError::MustViolation { path, .. }
+            | InstanceError::InvalidCondition { path, .. } => path,
+        }
+    }
+}
+
+fn display_path(path: &str) -> &str {
+    // The root of the data tree has an empty path.
+    if path.is_empty() {
+        "/"
+    } else {
+        path
+    }
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstanceError::UnexpectedKind {
+                path,
+                expected,
+                actual,
+            } => {
+                let mut kinds: Vec<String> = expected.iter().map(|k| k.to_string()).collect();
+                let last = kinds.pop().unwrap_or_default();
+                let expected = if kinds.is_empty() {
+                    last
+                } else {
+                    format!("{} or {}", kinds.join(", "), last)
+                };
+
+                write!(
+                    f,
+                    "Expected {} at {} but found {}.",
+                    expected,
+                    display_path(path),
+                    actual
+                )
+            }
+            InstanceError::MissingKey { path, key } => write!(
+                f,
+                "An entry of the list at {} has no value for the key {}.",
+                display_path(path),
+                key
+            ),
+            InstanceError::DuplicateKey { path } => {
+                write!(f, "The list entry {} is duplicated.", display_path(path))
+            }
+            InstanceError::MissingEntry { path } => {
+                write!(f, "The list entry {} does not exist.", display_path(path))
+            }
+            InstanceError::NotOrderedByUser { path } => write!(
+                f,
+                "The list at {} is not ordered-by user, so entries can only be added last.",
+                display_path(path)
+            ),
+            InstanceError::NotUnique {
+                path,
+                leaves,
+                entries,
+            } => write!(
+                f,
+                "The entries {} of the list at {} have the same values for {}.",
+                entries.join(", "),
+                display_path(path),
+                leaves.join(" ")
+            ),
+            InstanceError::DanglingReference {
+                path,
+                value,
+                target,
+            } => write!(
+                f,
+                "The leafref at {} refers to {}, which no node at {} has.",
+                display_path(path),
+                value,
+                target
+            ),
+            InstanceError::UnknownMember { path } => {
+                write!(f, "Unknown member at {}.", display_path(path))
+            }
+            InstanceError::StateData { path } => write!(
+                f,
+                "The node at {} is state data, which is not allowed in configuration.",
+                display_path(path)
+            ),
+            InstanceError::InvalidValue {
+                path,
+                type_name,
+                value,
+            } => write!(
+                f,
+                "Invalid {} value {} at {}.",
+                type_name,
+                value,
+                display_path(path)
+            ),
+            InstanceError::OutOfRange { path, value, range } => write!(
+                f,
+                "The value {} at {} is outside the range {}.",
+                value,
+                display_path(path),
+                range
+            ),
+            InstanceError::InvalidLength {
+                path,
+                length,
+                allowed,
+            } => write!(
+                f,
+                "The length {} of the value at {} is outside the length {}.",
+                length,
+                display_path(path),
+                allowed
+            ),
+            InstanceError::PatternMismatch {
+                path,
+                value,
+                pattern,
+                invert_match,
+            } => write!(
+                f,
+                "The value {} at {} {} {}.",
+                value,
+                display_path(path),
+                if *invert_match {
+                    "matches the inverted pattern"
+                } else {
+                    "does not match the pattern"
+                },
+                pattern
+            ),
+            InstanceError::MissingMandatory { path } => {
+                write!(f, "The mandatory node {} is missing.", display_path(path))
+            }
+            InstanceError::MissingChoice { path, choice } => write!(
+                f,
+                "No case of the mandatory choice {} at {} is present.",
+                choice,
+                display_path(path)
+            ),
+            InstanceError::ConflictingCases {
+                path,
+                choice,
+                cases,
+            } => write!(
+                f,
+                "The cases {} of the choice {} at {} all have data, but only one may.",
+                cases.join(", "),
+                choice,
+                display_path(path)
+            ),
+            InstanceError::TooFewElements { path, count, min } => write!(
+                f,
+                "There are {} entries at {} but at least {} are required.",
+                count,
+                display_path(path),
+                min
+            ),
+            InstanceError::TooManyElements { path, count, max } => write!(
+                f,
+                "There are {} entries at {} but at most {} are allowed.",
+                count,
+                display_path(path),
+                max
+            ),
+            InstanceError::TooManyFractionDigits {
+                path,
+                value,
+                fraction_digits,
+            } => write!(
+                f,
+                "The value {} at {} has more than {} fraction digits.",
+                value,
+                display_path(path),
+                fraction_digits
+            ),
+            InstanceError::WhenFalse { path, condition } => write!(
+                f,
+                "The node {} exists but its when condition {} is false.",
+                display_path(path),
+                condition
+            ),
+            InstanceError::MustViolation {
+                path,
+                error_message: Some(message),
+                ..
+            } => write!(f, "{} ({})", message, display_path(path)),
+            InstanceError::MustViolation {
+                path, condition, ..
+            } => write!(
+                f,
+                "The must condition {} at {} is false.",
+                condition,
+                display_path(path)
+            ),
+            InstanceError::InvalidCondition {
+                path,
+                condition,
+                message,
+            } => write!(
+                f,
+                "The condition {} at {} cannot be evaluated: {}.",
+                condition,
+                display_path(path),
+                message
+            ),
+        }
+    }
+}
+
+impl Error for InstanceError {}
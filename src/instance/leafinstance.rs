@@ -1,38 +1,89 @@
 use serde_json::Value;
 use std::sync::Arc;
 
+use super::error::InstanceError;
+use super::leafref::{check_references, LeafNode};
+use super::leafvalue::LeafValue;
 use super::util::*;
 use crate::model::leaf::Leaf;
 
 pub struct LeafInstance {
     pub parent: Parent,
     pub model: Arc<Leaf>,
-    pub value: String,
+    pub value: LeafValue,
+    /// Whether the leaf holds its default value, having been added by
+    /// `apply_defaults` rather than parsed.
+    pub is_default: bool,
 }
 
 impl LeafInstance {
-    pub fn new(model: Arc<Leaf>, value: Value, parent: Parent) -> LeafInstance {
-        let value_str = match value {
-            Value::String(x) => x,
-            Value::Number(x) => x.to_string(),
-            Value::Bool(x) => x.to_string(),
-            _ => panic!("Leaf must have a string value!"),
-        };
-
-        LeafInstance {
+    pub fn new(
+        model: Arc<Leaf>,
+        value: Value,
+        parent: Parent,
+        strict: bool,
+    ) -> Result<LeafInstance, InstanceError> {
+        let path = format!("{}/{}", parent.get_path(), model.name);
+        let value = parse_value(&model.leaf_type, &value, &path, strict)?;
+
+        Ok(LeafInstance {
             model,
-            value: value_str,
+            value,
             parent,
-        }
+            is_default: false,
+        })
+    }
+
+    /// Returns the leaf holding the default value of `model`, if it has one
+    /// in the value space of its type.
+    pub fn from_default(model: Arc<Leaf>, parent: Parent) -> Option<LeafInstance> {
+        let value = LeafValue::from_text(&model.leaf_type, model.default.as_ref()?)?;
+
+        Some(LeafInstance {
+            model,
+            value,
+            parent,
+            is_default: true,
+        })
+    }
+
+    /// Whether the leaf has the default value of its schema node.
+    pub fn has_default_value(&self) -> bool {
+        self.is_default
+            || LeafInstance::from_default(self.model.clone(), self.parent.clone())
+                .is_some_and(|d| d.value == self.value)
     }
 
     pub fn get_path(&self) -> String {
-        let parent_path = match &self.parent {
-            Parent::ContainerData(x) => x.upgrade().unwrap().borrow().get_path(),
-            Parent::ListChildData(x) => x.upgrade().unwrap().borrow().get_path(),
-        };
+        format!("{}/{}", self.parent.get_path(), self.model.name)
+    }
+
+    pub fn to_json(&self) -> Value {
+        self.value.to_json()
+    }
+
+    pub fn as_node(&self) -> LeafNode {
+        LeafNode {
+            parent: self.parent.clone(),
+            name: self.model.yang_name.clone(),
+        }
+    }
+
+    /// Follows a leafref to the leaves or leaf-lists it refers to.
+    pub fn follow(&self) -> Vec<LeafNode> {
+        self.as_node().follow()
+    }
+
+    pub fn validate(&self, errors: &mut Vec<InstanceError>) {
+        let leaf_type = &self.model.leaf_type;
 
-        format!("{}/{}", parent_path, self.model.name)
+        errors.extend(self.value.validate(leaf_type, &self.get_path()));
+        check_references(
+            &self.as_node(),
+            leaf_type,
+            std::slice::from_ref(&self.value),
+            errors,
+        );
     }
 
     pub fn visit(&self, f: &dyn Fn(NodeToVisit)) {
@@ -1,25 +1,26 @@
 use serde_json::Value;
 use std::sync::Arc;
 
+use super::error::InstanceError;
+use super::leafvalue::LeafValue;
+use super::util::*;
 use crate::model::leaflist::LeafList;
 
 pub struct LeafListChildInstance {
     pub model: Arc<LeafList>,
-    pub value: String,
+    pub value: LeafValue,
 }
 
-impl<'a> LeafListChildInstance {
-    pub fn new(model: Arc<LeafList>, value: Value) -> LeafListChildInstance {
-        let value_str = match value {
-            Value::String(x) => x,
-            Value::Number(x) => x.to_string(),
-            Value::Bool(x) => x.to_string(),
-            _ => panic!("Leaf list value must have a string value!"),
-        };
+impl LeafListChildInstance {
+    /// Parses one value of the leaf-list at `path`.
+    pub fn new(
+        model: Arc<LeafList>,
+        value: Value,
+        path: &str,
+        strict: bool,
+    ) -> Result<LeafListChildInstance, InstanceError> {
+        let value = parse_value(&model.leaf_type, &value, path, strict)?;
 
-        LeafListChildInstance {
-            model,
-            value: value_str,
-        }
+        Ok(LeafListChildInstance { model, value })
     }
 }
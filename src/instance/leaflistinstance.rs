@@ -1,7 +1,10 @@
 use serde_json::Value;
 use std::sync::Arc;
 
+use super::error::{InstanceError, JsonKind};
 use super::leaflistchildinstance::LeafListChildInstance;
+use super::leafref::{check_references, LeafNode};
+use super::leafvalue::LeafValue;
 use super::util::*;
 use crate::model::leaflist::LeafList;
 
@@ -9,35 +12,123 @@ pub struct LeafListInstance {
     pub parent: Parent,
     pub model: Arc<LeafList>,
     pub children: Vec<LeafListChildInstance>,
+    /// Whether the entries are the default values of the leaf-list, having
+    /// been added by `apply_defaults` rather than parsed.
+    pub is_default: bool,
 }
 
 impl LeafListInstance {
-    pub fn new(model: Arc<LeafList>, value: Value, parent: Parent) -> LeafListInstance {
+    pub fn new(
+        model: Arc<LeafList>,
+        value: Value,
+        parent: Parent,
+        context: &mut ParseContext,
+    ) -> Result<LeafListInstance, InstanceError> {
+        let path = format!("{}/{}", parent.get_path(), model.name);
         let value_arr = match value {
             Value::Array(x) => x,
-            _ => panic!("Leaf list must have an array value!"),
+            x => {
+                return Err(InstanceError::unexpected_kind(
+                    &path,
+                    &[JsonKind::Array],
+                    &x,
+                ))
+            }
         };
 
         let mut children: Vec<LeafListChildInstance> = Vec::new();
 
         for leaf_list_value in value_arr {
-            children.push(LeafListChildInstance::new(model.clone(), leaf_list_value));
+            if context.is_done() {
+                break;
+            }
+
+            let strict = context.options.strict_kinds;
+
+            match LeafListChildInstance::new(model.clone(), leaf_list_value, &path, strict) {
+                Ok(c) => children.push(c),
+                Err(e) => context.report(e),
+            }
         }
 
-        LeafListInstance {
+        Ok(LeafListInstance {
             model,
             children,
             parent,
-        }
+            is_default: false,
+        })
     }
 
-    pub fn get_path(&self) -> String {
-        let parent_path = match &self.parent {
-            Parent::ContainerData(x) => x.upgrade().unwrap().borrow().get_path(),
-            Parent::ListChildData(x) => x.upgrade().unwrap().borrow().get_path(),
+    /// Returns the leaf-list holding the default values of `model`, if it
+    /// has any in the value space of its type.
+    pub fn from_defaults(model: Arc<LeafList>, parent: Parent) -> Option<LeafListInstance> {
+        let children = model
+            .defaults
+            .iter()
+            .map(|d| {
+                let value = LeafValue::from_text(&model.leaf_type, d)?;
+
+                Some(LeafListChildInstance {
+                    model: model.clone(),
+                    value,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|c| !c.is_empty())?;
+
+        Some(LeafListInstance {
+            model,
+            children,
+            parent,
+            is_default: true,
+        })
+    }
+
+    /// Whether the leaf-list has the default values of its schema node.
+    pub fn has_default_values(&self) -> bool {
+        let values = |x: &LeafListInstance| -> Vec<LeafValue> {
+            x.children.iter().map(|c| c.value.clone()).collect()
         };
 
-        format!("{}/{}", parent_path, self.model.name)
+        self.is_default
+            || LeafListInstance::from_defaults(self.model.clone(), self.parent.clone())
+                .is_some_and(|d| values(&d) == values(self))
+    }
+
+    pub fn get_path(&self) -> String {
+        format!("{}/{}", self.parent.get_path(), self.model.name)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let values = self.children.iter().map(|c| c.value.to_json()).collect();
+
+        Value::Array(values)
+    }
+
+    pub fn as_node(&self) -> LeafNode {
+        LeafNode {
+            parent: self.parent.clone(),
+            name: self.model.yang_name.clone(),
+        }
+    }
+
+    pub fn validate(&self, errors: &mut Vec<InstanceError>) {
+        let path = self.get_path();
+        let values: Vec<LeafValue> = self.children.iter().map(|c| c.value.clone()).collect();
+
+        for value in &values {
+            errors.extend(value.validate(&self.model.leaf_type, &path));
+        }
+
+        check_references(&self.as_node(), &self.model.leaf_type, &values, errors);
+
+        check_elements(
+            self.children.len(),
+            self.model.min_elements,
+            self.model.max_elements,
+            &path,
+            errors,
+        );
     }
 
     pub fn visit(&self, f: &dyn Fn(NodeToVisit)) {
@@ -0,0 +1,243 @@
+use super::error::InstanceError;
+use super::leafvalue::LeafValue;
+use super::util::*;
+use crate::model::leafref::{LeafrefPath, PathPredicate, PathStart};
+use crate::model::leaftype::{BuiltinType, LeafType};
+
+/// A leaf or leaf-list of the instance tree, identified by the container or
+/// list entry it is in and its YANG name.
+#[derive(Clone, PartialEq)]
+pub struct LeafNode {
+    pub parent: Parent,
+    pub name: String,
+}
+
+impl LeafNode {
+    pub fn get_path(&self) -> String {
+        let name = self
+            .parent
+            .with_children(|children| find_child(children, &self.name)?.get_model())
+            .map_or_else(|| self.name.clone(), |m| m.get_name().to_string());
+
+        format!("{}/{}", self.parent.get_path(), name)
+    }
+
+    /// Returns the type and values of the node, or `None` if it has no data.
+    fn get_data(&self) -> Option<(LeafType, Vec<LeafValue>)> {
+        self.parent
+            .with_children(|children| match find_child(children, &self.name)? {
+                Child::LeafInstance(x) => Some((x.model.leaf_type.clone(), vec![x.value.clone()])),
+                Child::LeafListInstance(x) => Some((
+                    x.model.leaf_type.clone(),
+                    x.children.iter().map(|c| c.value.clone()).collect(),
+                )),
+                _ => None,
+            })
+    }
+
+    /// Whether the node is a key of the list entry it is in.
+    pub fn is_key(&self) -> bool {
+        match &self.parent {
+            Parent::ListChildData(x) => x
+                .upgrade()
+                .unwrap()
+                .borrow()
+                .model
+                .keys
+                .contains(&self.name),
+            Parent::ContainerData(_) => false,
+        }
+    }
+
+    /// The value of a leaf, or the values of a leaf-list.
+    pub fn get_values(&self) -> Vec<LeafValue> {
+        self.get_data().map(|(_, v)| v).unwrap_or_default()
+    }
+
+    /// Follows the leafref values of the node to the leaves and leaf-lists
+    /// that have those values.
+    pub fn follow(&self) -> Vec<LeafNode> {
+        let (leaf_type, values) = match self.get_data() {
+            Some(x) => x,
+            None => return Vec::new(),
+        };
+        let mut targets: Vec<LeafNode> = Vec::new();
+
+        for value in &values {
+            for target in follow_value(self, &leaf_type, value) {
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+        }
+
+        targets
+    }
+}
+
+/// Returns the leafref path that `value` of `leaf_type` was parsed with,
+/// which for a union is that of its first leafref member, and whether it
+/// requires an instance.
+fn get_leafref<'a>(leaf_type: &'a LeafType, value: &LeafValue) -> Option<(&'a LeafrefPath, bool)> {
+    match (&leaf_type.builtin, value) {
+        (
+            BuiltinType::Leafref {
+                path,
+                require_instance,
+                ..
+            },
+            LeafValue::Leafref(_),
+        ) => Some((path, *require_instance)),
+        (BuiltinType::Union(members), _) => members.iter().find_map(|m| get_leafref(m, value)),
+        _ => None,
+    }
+}
+
+fn go_up(node: Parent, steps: usize) -> Option<Parent> {
+    (0..steps).try_fold(node, |n, _| n.get_parent())
+}
+
+fn get_root(node: &Parent) -> Parent {
+    let mut root = node.clone();
+
+    while let Some(parent) = root.get_parent() {
+        root = parent;
+    }
+
+    root
+}
+
+/// Returns the container or the list entries named `name` in `node`, where
+/// the entries are those matching `predicates`.
+fn get_child_nodes(
+    node: &Parent,
+    name: &str,
+    predicates: &[PathPredicate],
+    current: &LeafNode,
+) -> Vec<Parent> {
+    node.with_children(|children| match find_child(children, name) {
+        Some(Child::ContainerInstance(x)) => vec![x.as_parent()],
+        Some(Child::ListInstance(x)) => x
+            .get_entries()
+            .iter()
+            .map(|e| e.as_parent())
+            .filter(|e| predicates.iter().all(|p| is_match(e, p, current)))
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// Whether the key of a list entry equals the value the predicate selects
+/// relative to `current`.
+fn is_match(entry: &Parent, predicate: &PathPredicate, current: &LeafNode) -> bool {
+    let (last, steps) = match predicate.steps.split_last() {
+        Some(x) => x,
+        None => return false,
+    };
+    let mut nodes: Vec<Parent> = go_up(current.parent.clone(), predicate.up - 1)
+        .into_iter()
+        .collect();
+
+    for step in steps {
+        nodes = nodes
+            .iter()
+            .flat_map(|n| get_child_nodes(n, step, &[], current))
+            .collect();
+    }
+
+    let expected: Vec<String> = nodes
+        .into_iter()
+        .flat_map(|parent| {
+            LeafNode {
+                parent,
+                name: last.clone(),
+            }
+            .get_values()
+        })
+        .map(|v| v.to_string())
+        .collect();
+
+    LeafNode {
+        parent: entry.clone(),
+        name: predicate.key.clone(),
+    }
+    .get_values()
+    .iter()
+    .any(|k| expected.contains(&k.to_string()))
+}
+
+/// Returns the leaves and leaf-lists with data that `path` selects from the
+/// leaf `current`, whatever their values.
+fn resolve(path: &LeafrefPath, current: &LeafNode) -> Vec<LeafNode> {
+    let mut nodes: Vec<Parent> = match &path.start {
+        PathStart::Root => vec![get_root(&current.parent)],
+        PathStart::Current { up } => go_up(current.parent.clone(), up - 1).into_iter().collect(),
+        PathStart::Deref { path, up } => resolve(path, current)
+            .iter()
+            .flat_map(|t| t.follow())
+            .filter_map(|t| go_up(t.parent, up - 1))
+            .collect(),
+    };
+
+    let (last, steps) = match path.steps.split_last() {
+        Some(x) => x,
+        None => return Vec::new(),
+    };
+
+    for step in steps {
+        nodes = nodes
+            .iter()
+            .flat_map(|n| get_child_nodes(n, &step.name, &step.predicates, current))
+            .collect();
+    }
+
+    nodes
+        .into_iter()
+        .map(|parent| LeafNode {
+            parent,
+            name: last.name.clone(),
+        })
+        .filter(|n| n.get_data().is_some())
+        .collect()
+}
+
+/// Returns the nodes that the leafref `value` of the node `current` refers
+/// to, i.e. those its path selects that have the value.
+fn follow_value(current: &LeafNode, leaf_type: &LeafType, value: &LeafValue) -> Vec<LeafNode> {
+    let path = match get_leafref(leaf_type, value) {
+        Some((path, _)) => path,
+        None => return Vec::new(),
+    };
+    let text = value.to_string();
+
+    resolve(path, current)
+        .into_iter()
+        .filter(|t| {
+            t.get_data().is_some_and(|(target_type, values)| {
+                let parsed = LeafValue::from_text(&target_type, &text);
+                values.iter().any(|v| Some(v) == parsed.as_ref())
+            })
+        })
+        .collect()
+}
+
+/// Reports the values of the node `current` that are leafrefs requiring an
+/// instance but refer to none.
+pub fn check_references(
+    current: &LeafNode,
+    leaf_type: &LeafType,
+    values: &[LeafValue],
+    errors: &mut Vec<InstanceError>,
+) {
+    for value in values {
+        if let Some((path, true)) = get_leafref(leaf_type, value) {
+            if follow_value(current, leaf_type, value).is_empty() {
+                errors.push(InstanceError::DanglingReference {
+                    path: current.get_path(),
+                    value: value.to_string(),
+                    target: path.to_string(),
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,672 @@
+use serde_json::Value;
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::error::{InstanceError, JsonKind};
+use crate::model::datamodel::DataModel;
+use crate::model::leaftype::{
+    decode_base64, Bit, BuiltinType, Enum, LeafType, Number, BASE64_ALPHABET,
+};
+use crate::model::util::split_prefix;
+
+/// The value of a leaf or leaf-list entry, typed by the built-in type of its
+/// schema node. The values of a union take the type of the first member type
+/// they are valid for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LeafValue {
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    Uint8(u8),
+    Uint16(u16),
+    Uint32(u32),
+    Uint64(u64),
+    /// An exact decimal, which keeps the fraction digits it was written with
+    /// so that they can be checked against those of its type.
+    Decimal64(Number),
+    String(String),
+    Boolean(bool),
+    Enumeration(Enum),
+    /// The bits that are set, ordered by position.
+    Bits(Vec<Bit>),
+    Binary(Vec<u8>),
+    Empty,
+    Identityref(String),
+    InstanceIdentifier(String),
+    /// The value of a leafref, typed by the leaf it refers to, or a string if
+    /// that could not be found.
+    Leafref(Box<LeafValue>),
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |acc, (i, b)| acc | u32::from(*b) << (16 - 8 * i));
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (group >> (18 - 6 * i)) & 0x3f;
+                encoded.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+/// Returns the text of a JSON scalar, which for numbers and booleans is their
+/// JSON form.
+fn get_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(x) => Some(x.clone()),
+        Value::Number(x) => Some(x.to_string()),
+        Value::Bool(x) => Some(x.to_string()),
+        _ => None,
+    }
+}
+
+/// Returns the kinds of JSON value RFC 7951 section 6 encodes values of
+/// `leaf_type` as.
+pub fn get_kinds(leaf_type: &LeafType) -> Vec<JsonKind> {
+    match &leaf_type.builtin {
+        BuiltinType::Int8
+        | BuiltinType::Int16
+        | BuiltinType::Int32
+        | BuiltinType::Uint8
+        | BuiltinType::Uint16
+        | BuiltinType::Uint32 => vec![JsonKind::Number],
+        BuiltinType::Boolean => vec![JsonKind::Boolean],
+        BuiltinType::Empty => vec![JsonKind::Array],
+        BuiltinType::Union(members) => {
+            let mut kinds = Vec::new();
+
+            for kind in members.iter().flat_map(get_kinds) {
+                if !kinds.contains(&kind) {
+                    kinds.push(kind);
+                }
+            }
+
+            kinds
+        }
+        BuiltinType::Leafref {
+            target: Some(t), ..
+        } => get_kinds(t),
+        BuiltinType::Leafref { .. } => vec![JsonKind::String, JsonKind::Number, JsonKind::Boolean],
+        _ => vec![JsonKind::String],
+    }
+}
+
+impl LeafValue {
+    /// Parses a JSON value of `leaf_type`, returning `None` if it is not in
+    /// the value space of the type's built-in type. Any scalar is taken as
+    /// the lexical form of a value, unless `strict`, where values have to be
+    /// of the kind of JSON value RFC 7951 section 6 encodes them as, so that
+    /// the members of a union are told apart by it.
+    pub fn parse(leaf_type: &LeafType, value: &Value, strict: bool) -> Option<LeafValue> {
+        match (&leaf_type.builtin, value) {
+            // An empty leaf is written as `[null]` (RFC 7951 section 6.9).
+            (BuiltinType::Empty, Value::Array(x)) if x.len() == 1 && x[0].is_null() => {
+                Some(LeafValue::Empty)
+            }
+            (_, x) if !strict => LeafValue::from_text(leaf_type, &get_text(x)?),
+            (BuiltinType::Union(members), _) => members
+                .iter()
+                .find_map(|m| LeafValue::parse(m, value, strict).filter(|v| v.is_valid(m))),
+            (
+                BuiltinType::Int8
+                | BuiltinType::Int16
+                | BuiltinType::Int32
+                | BuiltinType::Uint8
+                | BuiltinType::Uint16
+                | BuiltinType::Uint32,
+                Value::Number(x),
+            ) => LeafValue::from_text(leaf_type, &x.to_string()),
+            (BuiltinType::Boolean, Value::Bool(x)) => Some(LeafValue::Boolean(*x)),
+            (
+                BuiltinType::Leafref {
+                    target: Some(t), ..
+                },
+                x,
+            ) => Some(LeafValue::Leafref(Box::new(LeafValue::parse(
+                t, x, strict,
+            )?))),
+            (BuiltinType::Leafref { .. }, x) => Some(LeafValue::Leafref(Box::new(
+                LeafValue::String(get_text(x)?),
+            ))),
+            (
+                BuiltinType::Int8
+                | BuiltinType::Int16
+                | BuiltinType::Int32
+                | BuiltinType::Uint8
+                | BuiltinType::Uint16
+                | BuiltinType::Uint32
+                | BuiltinType::Boolean
+                | BuiltinType::Empty,
+                _,
+            ) => None,
+            (_, Value::String(x)) => LeafValue::from_text(leaf_type, x),
+            _ => None,
+        }
+    }
+
+    /// Parses a value of `leaf_type` from its lexical form (RFC 7950 section
+    /// 9), such as the argument of a `default` statement.
+    pub fn from_text(leaf_type: &LeafType, text: &str) -> Option<LeafValue> {
+        let integer = || Some(leaf_type.parse_number(text)?.value());
+
+        let parsed = match &leaf_type.builtin {
+            BuiltinType::Int8 => LeafValue::Int8(i8::try_from(integer()?).ok()?),
+            BuiltinType::Int16 => LeafValue::Int16(i16::try_from(integer()?).ok()?),
+            BuiltinType::Int32 => LeafValue::Int32(i32::try_from(integer()?).ok()?),
+            BuiltinType::Int64 => LeafValue::Int64(i64::try_from(integer()?).ok()?),
+            BuiltinType::Uint8 => LeafValue::Uint8(u8::try_from(integer()?).ok()?),
+            BuiltinType::Uint16 => LeafValue::Uint16(u16::try_from(integer()?).ok()?),
+            BuiltinType::Uint32 => LeafValue::Uint32(u32::try_from(integer()?).ok()?),
+            BuiltinType::Uint64 => LeafValue::Uint64(u64::try_from(integer()?).ok()?),
+            BuiltinType::Decimal64 { .. } => LeafValue::Decimal64(leaf_type.parse_number(text)?),
+            BuiltinType::String => LeafValue::String(text.to_string()),
+            BuiltinType::Boolean => match text {
+                "true" => LeafValue::Boolean(true),
+                "false" => LeafValue::Boolean(false),
+                _ => return None,
+            },
+            BuiltinType::Enumeration(_) => {
+                LeafValue::Enumeration(leaf_type.find_enum(text)?.clone())
+            }
+            BuiltinType::Bits(_) => LeafValue::Bits(leaf_type.find_bits(text)?),
+            BuiltinType::Binary => LeafValue::Binary(decode_base64(text)?),
+            BuiltinType::Empty if text.is_empty() => LeafValue::Empty,
+            BuiltinType::Empty => return None,
+            BuiltinType::Union(members) => {
+                let member = members.iter().find(|m| m.accepts(text))?;
+                return LeafValue::from_text(member, text);
+            }
+            BuiltinType::Identityref(_) => LeafValue::Identityref(text.to_string()),
+            BuiltinType::InstanceIdentifier { .. } => {
+                LeafValue::InstanceIdentifier(text.to_string())
+            }
+            BuiltinType::Leafref {
+                target: Some(t), ..
+            } => LeafValue::Leafref(Box::new(LeafValue::from_text(t, text)?)),
+            BuiltinType::Leafref { .. } => {
+                LeafValue::Leafref(Box::new(LeafValue::String(text.to_string())))
+            }
+        };
+
+        Some(parsed)
+    }
+
+    /// Whether the value is of the built-in type `builtin`, or of one of its
+    /// members if it is a union.
+    fn is_of_type(&self, builtin: &BuiltinType) -> bool {
+        match (self, builtin) {
+            (x, BuiltinType::Union(members)) => members.iter().any(|m| x.is_of_type(&m.builtin)),
+            (LeafValue::Int8(_), BuiltinType::Int8)
+            | (LeafValue::Int16(_), BuiltinType::Int16)
+            | (LeafValue::Int32(_), BuiltinType::Int32)
+            | (LeafValue::Int64(_), BuiltinType::Int64)
+            | (LeafValue::Uint8(_), BuiltinType::Uint8)
+            | (LeafValue::Uint16(_), BuiltinType::Uint16)
+            | (LeafValue::Uint32(_), BuiltinType::Uint32)
+            | (LeafValue::Uint64(_), BuiltinType::Uint64)
+            | (LeafValue::Decimal64(_), BuiltinType::Decimal64 { .. })
+            | (LeafValue::String(_), BuiltinType::String)
+            | (LeafValue::Boolean(_), BuiltinType::Boolean)
+            | (LeafValue::Enumeration(_), BuiltinType::Enumeration(_))
+            | (LeafValue::Bits(_), BuiltinType::Bits(_))
+            | (LeafValue::Binary(_), BuiltinType::Binary)
+            | (LeafValue::Empty, BuiltinType::Empty)
+            | (LeafValue::Identityref(_), BuiltinType::Identityref(_))
+            | (LeafValue::InstanceIdentifier(_), BuiltinType::InstanceIdentifier { .. })
+            | (LeafValue::Leafref(_), BuiltinType::Leafref { .. }) => true,
+            _ => false,
+        }
+    }
+
+    fn invalid(&self, leaf_type: &LeafType, path: &str) -> InstanceError {
+        InstanceError::InvalidValue {
+            path: path.to_string(),
+            type_name: leaf_type.name().to_string(),
+            value: self.to_string(),
+        }
+    }
+
+    fn is_valid(&self, leaf_type: &LeafType) -> bool {
+        self.validate(leaf_type, "").is_empty()
+    }
+
+    /// Checks the value against the restrictions of `leaf_type`, i.e. its
+    /// `range`, `length`, `pattern`s and `fraction-digits`, returning the
+    /// violations found at `path`. A union value need only be valid for one
+    /// of the members of its type.
+    pub fn validate(&self, leaf_type: &LeafType, path: &str) -> Vec<InstanceError> {
+        let mut errors = Vec::new();
+
+        match (&leaf_type.builtin, self) {
+            (BuiltinType::Union(members), _) => {
+                let mut candidates = members.iter().filter(|m| self.is_of_type(&m.builtin));
+
+                if let Some(first) = candidates.next() {
+                    let first_errors = self.validate(first, path);
+
+                    if !first_errors.is_empty() && !candidates.any(|m| self.is_valid(m)) {
+                        errors = first_errors;
+                    }
+                }
+
+                return errors;
+            }
+            (
+                BuiltinType::Leafref {
+                    target: Some(t), ..
+                },
+                LeafValue::Leafref(x),
+            ) => return x.validate(t, path),
+            (BuiltinType::Decimal64 { fraction_digits }, LeafValue::Decimal64(n))
+                if n.rescale(*fraction_digits).is_none() =>
+            {
+                errors.push(InstanceError::TooManyFractionDigits {
+                    path: path.to_string(),
+                    value: self.to_string(),
+                    fraction_digits: *fraction_digits,
+                })
+            }
+            (BuiltinType::Enumeration(enums), LeafValue::Enumeration(x)) if !enums.contains(x) => {
+                errors.push(self.invalid(leaf_type, path))
+            }
+            (BuiltinType::Bits(bits), LeafValue::Bits(x))
+                if !x.iter().all(|b| bits.contains(b)) =>
+            {
+                errors.push(self.invalid(leaf_type, path))
+            }
+            _ => (),
+        }
+
+        if let (Some(range), Some(n)) = (&leaf_type.range, self.as_number()) {
+            if !leaf_type.allows_number(n) {
+                errors.push(InstanceError::OutOfRange {
+                    path: path.to_string(),
+                    value: self.to_string(),
+                    range: range.to_string(),
+                });
+            }
+        }
+
+        let length = match self {
+            LeafValue::String(x) => Some(x.chars().count()),
+            LeafValue::Binary(x) => Some(x.len()),
+            _ => None,
+        };
+
+        if let (Some(allowed), Some(length)) = (&leaf_type.length, length) {
+            if !leaf_type.allows_length(length) {
+                errors.push(InstanceError::InvalidLength {
+                    path: path.to_string(),
+                    length,
+                    allowed: allowed.to_string(),
+                });
+            }
+        }
+
+        if let LeafValue::String(x) = self {
+            for pattern in &leaf_type.patterns {
+                if !pattern.allows(x) {
+                    errors.push(InstanceError::PatternMismatch {
+                        path: path.to_string(),
+                        value: x.clone(),
+                        pattern: pattern.regex.clone(),
+                        invert_match: pattern.invert_match,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Checks that an identityref value names an identity of `model` that is
+    /// derived from every base of `leaf_type`, where an identity without a
+    /// module is one of `module`, the module of the node.
+    pub fn validate_identity(
+        &self,
+        leaf_type: &LeafType,
+        module: Option<&str>,
+        model: &DataModel,
+        path: &str,
+    ) -> Option<InstanceError> {
+        match (&leaf_type.builtin, self) {
+            (BuiltinType::Union(members), _) => {
+                let errors: Vec<Option<InstanceError>> = members
+                    .iter()
+                    .filter(|m| self.is_of_type(&m.builtin))
+                    .map(|m| self.validate_identity(m, module, model, path))
+                    .collect();
+
+                if errors.iter().any(|e| e.is_none()) {
+                    None
+                } else {
+                    errors.into_iter().flatten().next()
+                }
+            }
+            (
+                BuiltinType::Leafref {
+                    target: Some(t), ..
+                },
+                LeafValue::Leafref(x),
+            ) => x.validate_identity(t, module, model, path),
+            (BuiltinType::Identityref(bases), LeafValue::Identityref(x)) => {
+                let identity = match (split_prefix(x), module) {
+                    ((None, name), Some(m)) => format!("{}:{}", m, name),
+                    _ => x.clone(),
+                };
+                let derived = model.get_bases(&identity);
+
+                if model.identities.contains_key(&identity)
+                    && bases.iter().all(|b| derived.contains(b))
+                {
+                    None
+                } else {
+                    Some(self.invalid(leaf_type, path))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Encodes the value as RFC 7951 section 6 describes, where 64-bit and
+    /// decimal numbers are strings so as not to lose precision.
+    pub fn to_json(&self) -> Value {
+        match self {
+            LeafValue::Int8(x) => Value::from(*x),
+            LeafValue::Int16(x) => Value::from(*x),
+            LeafValue::Int32(x) => Value::from(*x),
+            LeafValue::Uint8(x) => Value::from(*x),
+            LeafValue::Uint16(x) => Value::from(*x),
+            LeafValue::Uint32(x) => Value::from(*x),
+            LeafValue::Boolean(x) => Value::Bool(*x),
+            LeafValue::Empty => Value::Array(vec![Value::Null]),
+            LeafValue::Leafref(x) => x.to_json(),
+            x => Value::String(x.to_string()),
+        }
+    }
+
+    fn as_integer(&self) -> Option<i128> {
+        match self {
+            LeafValue::Int8(x) => Some(i128::from(*x)),
+            LeafValue::Int16(x) => Some(i128::from(*x)),
+            LeafValue::Int32(x) => Some(i128::from(*x)),
+            LeafValue::Int64(x) => Some(i128::from(*x)),
+            LeafValue::Uint8(x) => Some(i128::from(*x)),
+            LeafValue::Uint16(x) => Some(i128::from(*x)),
+            LeafValue::Uint32(x) => Some(i128::from(*x)),
+            LeafValue::Uint64(x) => Some(i128::from(*x)),
+            LeafValue::Leafref(x) => x.as_integer(),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer or decimal64 as an exact number.
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            LeafValue::Decimal64(x) => Some(*x),
+            LeafValue::Leafref(x) => x.as_number(),
+            x => x.as_integer().map(|i| Number::new(i, 0)),
+        }
+    }
+
+    pub fn as_i8(&self) -> Option<i8> {
+        i8::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_i16(&self) -> Option<i16> {
+        i16::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        i32::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        i64::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_u8(&self) -> Option<u8> {
+        u8::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_u16(&self) -> Option<u16> {
+        u16::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_u32(&self) -> Option<u32> {
+        u32::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        u64::try_from(self.as_integer()?).ok()
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            LeafValue::Boolean(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    /// The text of a string, identity, instance-identifier or leafref value.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            LeafValue::String(x) | LeafValue::Identityref(x) | LeafValue::InstanceIdentifier(x) => {
+                Some(x)
+            }
+            LeafValue::Leafref(x) => x.as_str(),
+            _ => None,
+        }
+    }
+
+    pub fn as_enum(&self) -> Option<&Enum> {
+        match self {
+            LeafValue::Enumeration(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_bits(&self) -> Option<&[Bit]> {
+        match self {
+            LeafValue::Bits(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            LeafValue::Binary(x) => Some(x),
+            _ => None,
+        }
+    }
+}
+
+/// Formats the value in its canonical form (RFC 7950 section 9).
+impl fmt::Display for LeafValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeafValue::Decimal64(x) => write!(f, "{}", x),
+            LeafValue::String(x) | LeafValue::Identityref(x) | LeafValue::InstanceIdentifier(x) => {
+                write!(f, "{}", x)
+            }
+            LeafValue::Leafref(x) => write!(f, "{}", x),
+            LeafValue::Boolean(x) => write!(f, "{}", x),
+            LeafValue::Enumeration(x) => write!(f, "{}", x.name),
+            LeafValue::Bits(x) => {
+                let names: Vec<&str> = x.iter().map(|b| b.name.as_str()).collect();
+                write!(f, "{}", names.join(" "))
+            }
+            LeafValue::Binary(x) => write!(f, "{}", encode_base64(x)),
+            LeafValue::Empty => Ok(()),
+            x => write!(f, "{}", x.as_integer().unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::model::util::*;
+
+    fn parse_type(xml: &str) -> LeafType {
+        let xml = format!(
+            r#"<?xml version="1.0"?><yin:type xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1" {}"#,
+            xml
+        );
+        let pkg = get_package(&xml).unwrap();
+        LeafType::new(get_root_el(&pkg).unwrap(), "/foo").unwrap()
+    }
+
+    #[test]
+    fn it_parses_integers() {
+        let leaf_type = parse_type(r#"name="uint32"/>"#);
+
+        let value = LeafValue::parse(&leaf_type, &json!(4000000000u32), true).unwrap();
+        assert_eq!(value, LeafValue::Uint32(4000000000));
+        assert_eq!(value.as_u32(), Some(4000000000));
+        assert_eq!(value.as_i32(), None);
+
+        assert_eq!(LeafValue::parse(&leaf_type, &json!("42"), true), None);
+        assert_eq!(
+            LeafValue::from_text(&leaf_type, "42"),
+            Some(LeafValue::Uint32(42))
+        );
+        assert_eq!(LeafValue::parse(&leaf_type, &json!(-1), true), None);
+
+        let leaf_type = parse_type(r#"name="int64"/>"#);
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!("-42"), true),
+            Some(LeafValue::Int64(-42))
+        );
+        assert_eq!(LeafValue::parse(&leaf_type, &json!(-42), true), None);
+        assert_eq!(LeafValue::parse(&leaf_type, &json!(1.5), true), None);
+    }
+
+    #[test]
+    fn it_parses_decimal64() {
+        let leaf_type =
+            parse_type(r#"name="decimal64"><yin:fraction-digits value="2"/></yin:type>"#);
+        let value = LeafValue::parse(&leaf_type, &json!("1.50"), true).unwrap();
+
+        assert_eq!(value, LeafValue::Decimal64(Number::new(150, 2)));
+        assert_eq!(value.to_json(), json!("1.5"));
+    }
+
+    #[test]
+    fn it_parses_enums_and_bits() {
+        let enum_type = parse_type(
+            r#"name="enumeration"><yin:enum name="up"/><yin:enum name="down"/></yin:type>"#,
+        );
+        let value = LeafValue::parse(&enum_type, &json!("down"), true).unwrap();
+        assert_eq!(value.as_enum().unwrap().value, 1);
+        assert_eq!(LeafValue::parse(&enum_type, &json!("sideways"), true), None);
+
+        let bits_type =
+            parse_type(r#"name="bits"><yin:bit name="read"/><yin:bit name="write"/></yin:type>"#);
+        let value = LeafValue::parse(&bits_type, &json!("write read"), true).unwrap();
+        assert_eq!(value.to_string(), "read write");
+        assert_eq!(value.as_bits().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn it_parses_binary() {
+        let leaf_type = parse_type(r#"name="binary"/>"#);
+        let value = LeafValue::parse(&leaf_type, &json!("aGVsbG8="), true).unwrap();
+
+        assert_eq!(value.as_bytes(), Some("hello".as_bytes()));
+        assert_eq!(value.to_json(), json!("aGVsbG8="));
+        assert_eq!(LeafValue::parse(&leaf_type, &json!("a*=="), true), None);
+    }
+
+    #[test]
+    fn it_picks_union_members() {
+        let leaf_type = parse_type(
+            r#"name="union">
+                <yin:type name="uint8"><yin:range value="1..10"/></yin:type>
+                <yin:type name="boolean"/>
+                <yin:type name="string"/>
+            </yin:type>"#,
+        );
+
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!(5), true),
+            Some(LeafValue::Uint8(5))
+        );
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!(true), true),
+            Some(LeafValue::Boolean(true))
+        );
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!("50"), true),
+            Some(LeafValue::String("50".to_string()))
+        );
+        assert_eq!(LeafValue::parse(&leaf_type, &json!(50), true), None);
+
+        let leaf_type = parse_type(
+            r#"name="union"><yin:type name="int32"/><yin:type name="string"/></yin:type>"#,
+        );
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!("007"), true),
+            Some(LeafValue::String("007".to_string()))
+        );
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!(7), true),
+            Some(LeafValue::Int32(7))
+        );
+    }
+
+    #[test]
+    fn it_parses_strings_and_booleans_strictly() {
+        let leaf_type = parse_type(r#"name="string"/>"#);
+        assert_eq!(LeafValue::parse(&leaf_type, &json!(12), true), None);
+        assert_eq!(LeafValue::parse(&leaf_type, &json!(true), true), None);
+
+        let leaf_type = parse_type(r#"name="boolean"/>"#);
+        assert_eq!(LeafValue::parse(&leaf_type, &json!("true"), true), None);
+        assert_eq!(
+            LeafValue::from_text(&leaf_type, "true"),
+            Some(LeafValue::Boolean(true))
+        );
+    }
+
+    #[test]
+    fn it_parses_any_scalar_leniently() {
+        let leaf_type = parse_type(r#"name="uint8"/>"#);
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!("7"), false),
+            Some(LeafValue::Uint8(7))
+        );
+
+        let leaf_type = parse_type(r#"name="string"/>"#);
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!(12), false),
+            Some(LeafValue::String("12".to_string()))
+        );
+
+        let leaf_type = parse_type(
+            r#"name="union"><yin:type name="int32"/><yin:type name="string"/></yin:type>"#,
+        );
+        assert_eq!(
+            LeafValue::parse(&leaf_type, &json!("12"), false),
+            Some(LeafValue::Int32(12))
+        );
+        assert_eq!(
+            get_kinds(&leaf_type),
+            vec![JsonKind::Number, JsonKind::String]
+        );
+    }
+}
@@ -1,23 +1,23 @@
-use inflector::cases::kebabcase::to_kebab_case;
+use indexmap::IndexMap;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::rc::{Rc, Weak};
 use std::sync::Arc;
-use ustr::{ustr, UstrMap};
+use ustr::{ustr, Ustr};
 
-use super::containerinstance::ContainerInstance;
-use super::leafinstance::LeafInstance;
-use super::leaflistinstance::LeafListInstance;
-use super::listinstance::{ListData, ListInstance};
+use super::error::{InstanceError, JsonKind};
+use super::leafvalue::LeafValue;
+use super::listinstance::ListData;
+use super::unknowninstance::UnknownInstance;
 use super::util::*;
 use crate::model::list::List;
-use crate::model::util::{Model, WithChildren};
+use crate::model::util::Model;
 
 pub struct ListChildData {
     pub parent: Weak<RefCell<ListData>>,
     pub model: Arc<List>,
-    pub children: Option<Rc<RefCell<UstrMap<Child>>>>,
-    pub key_value: String,
+    pub children: Option<Rc<RefCell<IndexMap<Ustr, Child>>>>,
+    pub key_values: Vec<LeafValue>,
 }
 
 type Link = Rc<RefCell<ListChildData>>;
@@ -36,48 +36,33 @@ impl PartialEq for ListChildInstance {
     }
 }
 
-pub fn parse_children(model: Arc<List>, value: Value, parent: &Link) -> UstrMap<Child> {
-    let mut children: UstrMap<Child> = UstrMap::default();
+pub fn parse_children(
+    model: Arc<List>,
+    value: Value,
+    parent: &Link,
+    context: &mut ParseContext,
+) -> IndexMap<Ustr, Child> {
+    let mut children: IndexMap<Ustr, Child> = IndexMap::new();
 
     if let Value::Object(x) = value {
         for (k, v) in x.into_iter() {
+            if context.is_done() {
+                break;
+            }
+
             let children_parent = Parent::ListChildData(Rc::downgrade(parent));
 
-            if let Some(child_model) = model.get_child(&k) {
-                match child_model {
-                    Model::Leaf(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::LeafInstance(LeafInstance::new(m.clone(), v, children_parent)),
-                        );
-                    }
-                    Model::Container(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::ContainerInstance(ContainerInstance::new(
-                                m.clone(),
-                                v,
-                                Some(children_parent),
-                            )),
-                        );
-                    }
-                    Model::LeafList(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::LeafListInstance(LeafListInstance::new(
-                                m.clone(),
-                                v,
-                                children_parent,
-                            )),
-                        );
-                    }
-                    Model::List(m) => {
-                        children.insert(
-                            ustr(&k),
-                            Child::ListInstance(ListInstance::new(m.clone(), v, children_parent)),
-                        );
-                    }
+            // Children are keyed like their schema nodes, whatever their names.
+            let (key, child) = match find_model(&model.children, &k, context.options.names) {
+                Some((key, m)) => (key.as_str(), parse_child(m, v, children_parent, context)),
+                None => (k.as_str(), parse_unknown(&k, v, children_parent, context)),
+            };
+
+            match child {
+                Ok(c) => {
+                    children.insert(ustr(key), c);
                 }
+                Err(e) => context.report(e),
             }
         }
     }
@@ -85,25 +70,54 @@ pub fn parse_children(model: Arc<List>, value: Value, parent: &Link) -> UstrMap<
     children
 }
 
-pub fn get_key_value(model: Arc<List>, value: &Value) -> String {
-    let mut key_values: Vec<String> = Vec::new();
+/// Returns the values of the keys of a list entry, parsed with `options`.
+/// `path` is the path of the list.
+pub fn get_key_values(
+    model: Arc<List>,
+    value: &Value,
+    path: &str,
+    options: &ParseOptions,
+) -> Result<Vec<LeafValue>, InstanceError> {
+    if !value.is_object() {
+        return Err(InstanceError::unexpected_kind(
+            path,
+            &[JsonKind::Object],
+            value,
+        ));
+    }
+
+    let mut key_values: Vec<LeafValue> = Vec::new();
 
     for key in &model.keys {
-        let key_value = match value[key] {
-            Value::Null => &value[to_kebab_case(key)],
-            _ => &value[key],
-        };
-        let key_value_string = match key_value {
-            Value::String(x) => x.clone(),
-            Value::Number(x) => x.to_string(),
-            Value::Bool(x) => x.to_string(),
-            _ => panic!("Key value must be a string."),
+        let (child, leaf) = match model.children.get(key) {
+            Some(m @ Model::Leaf(x)) => (m, x),
+            _ => continue,
         };
+        let key_value = get_member_names(child, options.names)
+            .into_iter()
+            .map(|n| &value[n])
+            .find(|v| !v.is_null())
+            .ok_or_else(|| InstanceError::MissingKey {
+                path: path.to_string(),
+                key: child.get_name().to_string(),
+            })?;
 
-        key_values.push(key_value_string);
+        key_values.push(parse_value(
+            &leaf.leaf_type,
+            key_value,
+            &format!("{}/{}", path, child.get_name()),
+            options.strict_kinds,
+        )?);
     }
 
-    key_values.join(",")
+    Ok(key_values)
+}
+
+/// Formats the values of the keys of a list entry as they appear in its
+/// path, e.g. `eth0,100`.
+pub fn format_key(key_values: &[LeafValue]) -> String {
+    let values: Vec<String> = key_values.iter().map(|v| v.to_string()).collect();
+    values.join(",")
 }
 
 impl ListChildInstance {
@@ -111,27 +125,118 @@ impl ListChildInstance {
         model: Arc<List>,
         value: Value,
         parent: Weak<RefCell<ListData>>,
-    ) -> ListChildInstance {
-        let key_value = get_key_value(model.clone(), &value);
+        context: &mut ParseContext,
+    ) -> Result<ListChildInstance, InstanceError> {
+        let list_path = parent.upgrade().unwrap().borrow().get_path();
+        let key_values = get_key_values(model.clone(), &value, &list_path, &context.options)?;
 
         let instance = ListChildInstance(Rc::new(RefCell::new(ListChildData {
             model: model.clone(),
             children: None,
             parent,
-            key_value,
+            key_values,
         })));
 
-        instance.0.borrow_mut().children = Some(Rc::new(RefCell::new(parse_children(
-            model,
-            value,
-            &instance.0,
-        ))));
+        let children = parse_children(model, value, &instance.0, context);
+        instance.0.borrow_mut().children = Some(Rc::new(RefCell::new(children)));
 
-        instance
+        Ok(instance)
     }
 
+    /// Returns the key of the entry as it appears in its path.
     pub fn get_key(&self) -> String {
-        self.0.borrow().key_value.to_string()
+        format_key(&self.0.borrow().key_values)
+    }
+
+    /// Returns the values of the keys of the entry, in the order of the
+    /// list's `key` statement.
+    pub fn get_key_values(&self) -> Vec<LeafValue> {
+        self.0.borrow().key_values.clone()
+    }
+
+    pub fn get_path(&self) -> String {
+        self.0.borrow().get_path()
+    }
+
+    /// Returns this entry as the parent of its children.
+    pub fn as_parent(&self) -> Parent {
+        Parent::ListChildData(Rc::downgrade(&self.0))
+    }
+
+    /// Returns the value of the descendant leaf at `path`, a path of
+    /// camelCase names such as `endpoint/port`.
+    pub fn get_leaf_value(&self, path: &str) -> Option<LeafValue> {
+        let names: Vec<&str> = path.split('/').collect();
+
+        get_leaf_value(&self.0.borrow().children.as_ref().unwrap().borrow(), &names)
+    }
+
+    /// Returns the value of the descendant leaf at `path`, a path of
+    /// camelCase names such as `endpoint/port`, or the default in effect for
+    /// it if it has no data.
+    pub fn get_effective_value(&self, path: &str) -> Option<LeafValue> {
+        let names: Vec<&str> = path.split('/').collect();
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        get_effective_value(&children, &data.model.children, &data.model.choices, &names)
+    }
+
+    /// Returns the name of the case of the choice `name` that has data, if
+    /// any, where the choice may be nested in the case of another.
+    pub fn get_active_case(&self, name: &str) -> Option<String> {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        get_active_case(&children, data.model.as_ref(), name)
+    }
+
+    /// Adds the defaults in effect to this entry and its descendants.
+    pub fn apply_defaults(&self) {
+        let data = self.0.borrow();
+
+        apply_defaults(
+            &mut data.children.as_ref().unwrap().borrow_mut(),
+            &data.model.children,
+            &data.model.choices,
+            &self.as_parent(),
+        );
+    }
+
+    /// Removes the descendants that are not of the `kind` of data to keep,
+    /// except for the keys, returning whether any other data is left.
+    pub fn retain_data(&self, kind: DataKind) -> bool {
+        let data = self.0.borrow();
+        let mut children = data.children.as_ref().unwrap().borrow_mut();
+
+        retain_data(&mut children, kind, &data.model.keys)
+    }
+
+    pub fn to_json(&self, options: &SerializeOptions) -> Value {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        children_to_json(
+            &children,
+            &data.model.children,
+            &data.model.choices,
+            &self.as_parent(),
+            data.model.module.as_deref(),
+            options,
+        )
+    }
+
+    pub fn validate(&self, errors: &mut Vec<InstanceError>) {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        validate_children(
+            &children,
+            &data.model.children,
+            &data.model.choices,
+            &data.get_path(),
+            errors,
+        );
     }
 
     pub fn visit(&self, f: &dyn Fn(NodeToVisit)) {
@@ -149,6 +254,25 @@ impl ListChildInstance {
                 Child::LeafListInstance(c) => {
                     c.visit(f);
                 }
+                Child::UnknownInstance(_) => (),
+            }
+        }
+    }
+
+    /// Calls `f` with every unknown node below this one.
+    pub fn visit_unknown(&self, f: &dyn Fn(&UnknownInstance)) {
+        for child in self.0.borrow().children.as_ref().unwrap().borrow().values() {
+            match child {
+                Child::ContainerInstance(c) => {
+                    c.visit_unknown(f);
+                }
+                Child::ListInstance(c) => {
+                    c.visit_unknown(f);
+                }
+                Child::UnknownInstance(c) => {
+                    f(c);
+                }
+                _ => (),
             }
         }
     }
@@ -158,7 +282,7 @@ impl ListChildData {
     pub fn is_generated(&self) -> bool {
         for child in self.children.as_ref().unwrap().borrow().values() {
             if let Child::LeafInstance(c) = child {
-                if c.model.name == "generated" && c.value == "true" {
+                if c.model.name == "generated" && c.value.as_bool() == Some(true) {
                     return true;
                 }
             }
@@ -167,8 +291,14 @@ impl ListChildData {
         false
     }
 
+    /// Returns the path of the entry, which for a list without keys is that
+    /// of the list, since such entries cannot be told apart.
     pub fn get_path(&self) -> String {
-        let parent_path = &self.parent.upgrade().unwrap().borrow().get_path();
-        format!("{}={}", parent_path, self.key_value)
+        let parent_path = self.parent.upgrade().unwrap().borrow().get_path();
+
+        match self.key_values.is_empty() {
+            true => parent_path,
+            false => format!("{}={}", parent_path, format_key(&self.key_values)),
+        }
     }
 }
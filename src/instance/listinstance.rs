@@ -1,21 +1,35 @@
 use serde_json::Value;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
 
-use super::listchildinstance::ListChildInstance;
+use super::error::{InstanceError, JsonKind};
+use super::leafvalue::LeafValue;
+use super::listchildinstance::{format_key, ListChildInstance};
+use super::unknowninstance::UnknownInstance;
 use super::util::*;
 use crate::model::list::List;
 
 pub struct ListData {
     pub parent: Parent,
     pub model: Arc<List>,
-    pub children: Option<Rc<RefCell<HashMap<String, ListChildInstance>>>>,
+    pub children: Option<Rc<RefCell<Vec<ListChildInstance>>>>,
 }
 
 type Link = Rc<RefCell<ListData>>;
 
+/// Where to put a list entry, as with the `insert` attribute of NETCONF
+/// and query parameter of RESTCONF. Other entries are given by the values of
+/// their keys, as returned by `ListChildInstance::get_key_values`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    First,
+    Last,
+    Before(Vec<LeafValue>),
+    After(Vec<LeafValue>),
+}
+
 pub struct ListInstance(Link);
 
 impl Clone for ListInstance {
@@ -31,10 +45,22 @@ impl PartialEq for ListInstance {
 }
 
 impl ListInstance {
-    pub fn new(model: Arc<List>, value: Value, parent: Parent) -> ListInstance {
+    pub fn new(
+        model: Arc<List>,
+        value: Value,
+        parent: Parent,
+        context: &mut ParseContext,
+    ) -> Result<ListInstance, InstanceError> {
         let value_arr = match value {
             Value::Array(x) => x,
-            _ => panic!("List must have an array value!"),
+            x => {
+                let path = format!("{}/{}", parent.get_path(), model.name);
+                return Err(InstanceError::unexpected_kind(
+                    &path,
+                    &[JsonKind::Array],
+                    &x,
+                ));
+            }
         };
 
         let instance = ListInstance(Rc::new(RefCell::new(ListData {
@@ -43,33 +69,232 @@ impl ListInstance {
             parent,
         })));
 
-        let mut children: HashMap<String, ListChildInstance> = HashMap::new();
+        let mut children: Vec<ListChildInstance> = Vec::new();
+        // Keys are compared by value, so that `1.5` and `1.50` are the same,
+        // and entries of lists without keys are never duplicates.
+        let mut keys: HashSet<Vec<LeafValue>> = HashSet::new();
 
         for list_value in value_arr.into_iter() {
+            if context.is_done() {
+                break;
+            }
+
             let children_parent = Rc::downgrade(&instance.0);
-            let child_instance = ListChildInstance::new(model.clone(), list_value, children_parent);
-            children.insert(child_instance.get_key(), child_instance);
+
+            match ListChildInstance::new(model.clone(), list_value, children_parent, context) {
+                Ok(c) if !model.keys.is_empty() && !keys.insert(c.get_key_values()) => {
+                    context.report(InstanceError::DuplicateKey { path: c.get_path() })
+                }
+                Ok(c) => children.push(c),
+                Err(e) => context.report(e),
+            }
         }
 
         instance.0.borrow_mut().children = Some(Rc::new(RefCell::new(children)));
 
-        instance
+        Ok(instance)
+    }
+
+    pub fn get_entries(&self) -> Vec<ListChildInstance> {
+        self.0.borrow().children.as_ref().unwrap().borrow().clone()
+    }
+
+    pub fn get_model(&self) -> Arc<List> {
+        self.0.borrow().model.clone()
+    }
+
+    /// Parses `value` as a new entry and inserts it at `position`. Only
+    /// lists that are `ordered-by user` take entries anywhere but last.
+    pub fn insert(
+        &self,
+        value: Value,
+        position: &Position,
+        options: ParseOptions,
+    ) -> Result<ListChildInstance, Vec<InstanceError>> {
+        let mut context = ParseContext::new(options);
+        let entry = ListChildInstance::new(
+            self.get_model(),
+            value,
+            Rc::downgrade(&self.0),
+            &mut context,
+        );
+
+        let mut errors = context.into_errors();
+        let entry = match entry {
+            Ok(e) if errors.is_empty() => e,
+            Ok(_) => return Err(errors),
+            Err(e) => {
+                errors.push(e);
+                return Err(errors);
+            }
+        };
+
+        let data = self.0.borrow();
+        let mut children = data.children.as_ref().unwrap().borrow_mut();
+
+        let key_values = entry.get_key_values();
+
+        if !data.model.keys.is_empty() && children.iter().any(|c| c.get_key_values() == key_values)
+        {
+            return Err(vec![InstanceError::DuplicateKey {
+                path: entry.get_path(),
+            }]);
+        }
+
+        let index = get_index(&data, &children, position).map_err(|e| vec![e])?;
+        children.insert(index, entry.clone());
+
+        Ok(entry)
+    }
+
+    /// Moves the entry with the given key to `position`. Moving an entry
+    /// before or after itself leaves it where it is.
+    pub fn move_entry(&self, key: &[LeafValue], position: &Position) -> Result<(), InstanceError> {
+        let data = self.0.borrow();
+        let mut children = data.children.as_ref().unwrap().borrow_mut();
+        let from = index_of(&data, &children, key)?;
+
+        if let Position::Before(x) | Position::After(x) = position {
+            if x == key {
+                return Ok(());
+            }
+        }
+
+        let to = get_index(&data, &children, position)?;
+        // The entry is taken out before it is put back, which shifts the
+        // entries after it.
+        let entry = children.remove(from);
+        children.insert(if to > from { to - 1 } else { to }, entry);
+
+        Ok(())
+    }
+
+    pub fn to_json(&self, options: &SerializeOptions) -> Value {
+        let entries = self
+            .0
+            .borrow()
+            .children
+            .as_ref()
+            .unwrap()
+            .borrow()
+            .iter()
+            .map(|c| c.to_json(options))
+            .collect();
+
+        Value::Array(entries)
+    }
+
+    /// Removes the data that is not of the `kind` to keep from the entries,
+    /// returning whether the list should be kept. Entries are configuration
+    /// through their keys, and are kept with state data only if they have
+    /// any.
+    pub fn retain_data(&self, kind: DataKind) -> bool {
+        let data = self.0.borrow();
+        let mut children = data.children.as_ref().unwrap().borrow_mut();
+
+        children.retain(|c| c.retain_data(kind) || kind == DataKind::Config);
+        !children.is_empty()
+    }
+
+    pub fn validate(&self, errors: &mut Vec<InstanceError>) {
+        let data = self.0.borrow();
+        let children = data.children.as_ref().unwrap().borrow();
+
+        for child in children.iter() {
+            child.validate(errors);
+        }
+
+        let path = data.get_path();
+        check_elements(
+            children.len(),
+            data.model.min_elements,
+            data.model.max_elements,
+            &path,
+            errors,
+        );
+
+        for leaves in &data.model.unique {
+            // Entries lacking any of the leaves are exempt from the constraint.
+            let mut entries: HashMap<Vec<String>, Vec<String>> = HashMap::new();
+
+            for child in children.iter() {
+                let values: Option<Vec<String>> = leaves
+                    .iter()
+                    .map(|l| child.get_leaf_value(l).map(|v| v.to_string()))
+                    .collect();
+
+                if let Some(values) = values {
+                    entries.entry(values).or_default().push(child.get_path());
+                }
+            }
+
+            let mut collisions: Vec<Vec<String>> =
+                entries.into_values().filter(|e| e.len() > 1).collect();
+            collisions.sort();
+
+            for mut collision in collisions {
+                collision.sort();
+                errors.push(InstanceError::NotUnique {
+                    path: path.clone(),
+                    leaves: leaves.clone(),
+                    entries: collision,
+                });
+            }
+        }
     }
 
     pub fn visit(&self, f: &dyn Fn(NodeToVisit)) {
-        for child in self.0.borrow().children.as_ref().unwrap().borrow().values() {
+        for child in self.0.borrow().children.as_ref().unwrap().borrow().iter() {
             child.visit(f);
         }
     }
+
+    /// Calls `f` with every unknown node below this one.
+    pub fn visit_unknown(&self, f: &dyn Fn(&UnknownInstance)) {
+        for child in self.0.borrow().children.as_ref().unwrap().borrow().iter() {
+            child.visit_unknown(f);
+        }
+    }
+}
+
+/// Returns the index an entry is inserted at to be at `position` among the
+/// `children` of a list.
+fn get_index(
+    data: &ListData,
+    children: &[ListChildInstance],
+    position: &Position,
+) -> Result<usize, InstanceError> {
+    if !data.model.ordered_by_user && *position != Position::Last {
+        return Err(InstanceError::NotOrderedByUser {
+            path: data.get_path(),
+        });
+    }
+
+    match position {
+        Position::First => Ok(0),
+        Position::Last => Ok(children.len()),
+        Position::Before(x) => index_of(data, children, x),
+        Position::After(x) => Ok(index_of(data, children, x)? + 1),
+    }
+}
+
+/// Returns the index of the entry with the given key among the `children`
+/// of a list.
+fn index_of(
+    data: &ListData,
+    children: &[ListChildInstance],
+    key: &[LeafValue],
+) -> Result<usize, InstanceError> {
+    children
+        .iter()
+        .position(|c| c.get_key_values() == key)
+        .ok_or_else(|| InstanceError::MissingEntry {
+            path: format!("{}={}", data.get_path(), format_key(key)),
+        })
 }
 
 impl ListData {
     pub fn get_path(&self) -> String {
-        let parent_path = match &self.parent {
-            Parent::ContainerData(x) => x.upgrade().unwrap().borrow().get_path(),
-            Parent::ListChildData(x) => x.upgrade().unwrap().borrow().get_path(),
-        };
-
-        format!("{}/{}", parent_path, self.model.name)
+        format!("{}/{}", self.parent.get_path(), self.model.name)
     }
 }
@@ -1,10 +1,20 @@
+mod constraints;
 pub mod containerinstance;
 pub mod datamodelinstance;
+pub mod error;
 pub mod leafinstance;
 pub mod leaflistchildinstance;
 pub mod leaflistinstance;
+mod leafref;
+pub mod leafvalue;
 pub mod listchildinstance;
 pub mod listinstance;
+pub mod referenceindex;
+pub mod unknowninstance;
 mod util;
 
-pub use self::util::{Child, Generated, NodeToVisit, Parent};
+pub use self::leafref::LeafNode;
+pub use self::util::{
+    Child, DataKind, Generated, NameStyle, NodeToVisit, Parent, ParseContext, ParseOptions,
+    SerializeOptions, UnknownMembers, WithDefaults,
+};
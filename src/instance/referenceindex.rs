@@ -0,0 +1,195 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use super::datamodelinstance::DataModelInstance;
+use super::leafref::LeafNode;
+use super::util::*;
+
+/// The leafrefs of an instance indexed by what they refer to, to find the
+/// nodes that would be left dangling by deleting another. It reflects the
+/// instance when it was built.
+#[derive(Debug, Default)]
+pub struct ReferenceIndex {
+    referrers: HashMap<String, Vec<String>>,
+    /// The paths of each referrer and target, followed by those of their
+    /// ancestors, to tell which nodes are within another.
+    references: Vec<(Vec<String>, Vec<String>)>,
+}
+
+/// Returns the path of `node` and those of the containers and list entries
+/// it is in, up to the root.
+fn get_ancestor_paths(node: &LeafNode) -> Vec<String> {
+    let mut paths = vec![node.get_path()];
+    let mut parent = Some(node.parent.clone());
+
+    while let Some(p) = parent {
+        paths.push(p.get_path());
+        parent = p.get_parent();
+    }
+
+    paths
+}
+
+impl ReferenceIndex {
+    pub fn new(instance: &DataModelInstance) -> ReferenceIndex {
+        let index = RefCell::new(ReferenceIndex::default());
+
+        instance.visit(&|node| {
+            let node = match node {
+                NodeToVisit::LeafInstance(x) => x.as_node(),
+                NodeToVisit::LeafListInstance(x) => x.as_node(),
+            };
+
+            index.borrow_mut().add(&node);
+        });
+
+        index.into_inner()
+    }
+
+    fn add(&mut self, node: &LeafNode) {
+        let path = node.get_path();
+        let referrer_paths = get_ancestor_paths(node);
+
+        for target in node.follow() {
+            self.references
+                .push((referrer_paths.clone(), get_ancestor_paths(&target)));
+
+            let mut targets = vec![target.get_path()];
+
+            // Referring to the key of a list entry refers to the entry.
+            if target.is_key() {
+                targets.push(target.parent.get_path());
+            }
+
+            for t in targets {
+                let referrers = self.referrers.entry(t).or_default();
+
+                if !referrers.contains(&path) {
+                    referrers.push(path.clone());
+                }
+            }
+        }
+    }
+
+    /// Returns the sorted paths of the leaves and leaf-lists that refer to the
+    /// leaf, leaf-list or list entry at `path`.
+    pub fn get_referrers(&self, path: &str) -> Vec<String> {
+        let mut referrers = self.referrers.get(path).cloned().unwrap_or_default();
+
+        referrers.sort();
+        referrers
+    }
+
+    /// Returns the sorted paths of the leaves and leaf-lists outside of the
+    /// node at `path` that refer to it or to any node below it, which are
+    /// those that deleting it would leave dangling.
+    pub fn get_external_referrers(&self, path: &str) -> Vec<String> {
+        // Paths are compared whole, as key values may contain a `/`.
+        let is_within = |paths: &[String]| paths.iter().any(|p| p == path);
+        let mut referrers: Vec<String> = self
+            .references
+            .iter()
+            .filter(|(referrer, target)| is_within(target) && !is_within(referrer))
+            .map(|(referrer, _)| referrer[0].clone())
+            .collect();
+
+        referrers.sort();
+        referrers.dedup();
+        referrers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::model::datamodel::DataModel;
+    use crate::model::util::*;
+
+    const DATA_MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:list name="tenant">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+            <yin:leaf name="parent">
+                <yin:type name="leafref">
+                    <yin:path value="../../tenant/name"/>
+                </yin:type>
+            </yin:leaf>
+        </yin:list>
+        <yin:list name="router">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+            <yin:leaf-list name="tenant">
+                <yin:type name="leafref">
+                    <yin:path value="/tenant/name"/>
+                </yin:type>
+            </yin:leaf-list>
+        </yin:list>
+    </yin:module>"#;
+
+    #[test]
+    fn it_indexes_referrers() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "tenant": [{ "name": "blue" }, { "name": "red", "parent": "blue" }],
+            "router": [{ "name": "r1", "tenant": ["blue", "red"] }, { "name": "r2", "tenant": ["red"] }]
+        });
+        let instance = DataModelInstance::new(data_model, v).unwrap();
+        let index = ReferenceIndex::new(&instance);
+
+        assert_eq!(
+            index.get_referrers("/tenant=blue"),
+            vec!["/router=r1/tenant", "/tenant=red/parent"]
+        );
+        assert_eq!(
+            index.get_referrers("/tenant=red/name"),
+            vec!["/router=r1/tenant", "/router=r2/tenant"]
+        );
+        assert!(index.get_referrers("/router=r1").is_empty());
+    }
+
+    #[test]
+    fn it_finds_referrers_outside_of_a_subtree() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "tenant": [{ "name": "blue", "parent": "blue" }, { "name": "blue2" }],
+            "router": [{ "name": "r1", "tenant": ["blue2"] }]
+        });
+        let instance = DataModelInstance::new(data_model, v).unwrap();
+        let index = ReferenceIndex::new(&instance);
+
+        assert!(index.get_external_referrers("/tenant=blue").is_empty());
+        assert_eq!(
+            index.get_external_referrers("/tenant=blue2"),
+            vec!["/router=r1/tenant"]
+        );
+        assert!(index.get_external_referrers("").is_empty());
+    }
+
+    #[test]
+    fn it_compares_paths_whole() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let data_model = Arc::new(DataModel::new(get_root_el(&pkg).unwrap()).unwrap());
+        let v = json!({
+            "tenant": [{ "name": "10.0.0.0" }, { "name": "10.0.0.0/8" }],
+            "router": [{ "name": "r1", "tenant": ["10.0.0.0/8"] }]
+        });
+        let instance = DataModelInstance::new(data_model, v).unwrap();
+        let index = ReferenceIndex::new(&instance);
+
+        assert!(index.get_external_referrers("/tenant=10.0.0.0").is_empty());
+        assert_eq!(
+            index.get_external_referrers("/tenant=10.0.0.0/8"),
+            vec!["/router=r1/tenant"]
+        );
+    }
+}
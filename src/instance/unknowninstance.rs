@@ -0,0 +1,25 @@
+use serde_json::Value;
+
+use super::util::*;
+
+/// A member of the JSON that matches no schema node, kept as it was found so
+/// that it can be reported.
+pub struct UnknownInstance {
+    pub parent: Parent,
+    pub name: String,
+    pub value: Value,
+}
+
+impl UnknownInstance {
+    pub fn new(name: &str, value: Value, parent: Parent) -> UnknownInstance {
+        UnknownInstance {
+            parent,
+            name: name.to_string(),
+            value,
+        }
+    }
+
+    pub fn get_path(&self) -> String {
+        format!("{}/{}", self.parent.get_path(), self.name)
+    }
+}
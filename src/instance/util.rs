@@ -1,19 +1,33 @@
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
 use std::cell::RefCell;
+use std::collections::HashSet;
 use std::rc::Weak;
+use std::sync::Arc;
+use ustr::{ustr, Ustr};
 
 use super::containerinstance::{ContainerData, ContainerInstance};
+use super::error::{InstanceError, JsonKind};
 use super::leafinstance::LeafInstance;
 use super::leaflistinstance::LeafListInstance;
+use super::leafvalue::{get_kinds, LeafValue};
 use super::listchildinstance::ListChildData;
 use super::listinstance::ListInstance;
+use super::unknowninstance::UnknownInstance;
+use crate::model::case::Case;
+use crate::model::choice::Choice;
+use crate::model::leaftype::{BuiltinType, LeafType};
+use crate::model::util::{find_child_entry, find_unique, split_prefix, Model, WithChildren};
 
 pub enum Child {
     ContainerInstance(ContainerInstance),
     LeafInstance(LeafInstance),
     LeafListInstance(LeafListInstance),
     ListInstance(ListInstance),
+    UnknownInstance(UnknownInstance),
 }
 
+#[derive(Clone)]
 pub enum Parent {
     ContainerData(Weak<RefCell<ContainerData>>),
     ListChildData(Weak<RefCell<ListChildData>>),
@@ -34,3 +48,817 @@ pub trait Generated {
         }
     }
 }
+
+impl Child {
+    /// The schema node of this child, which unknown nodes do not have.
+    pub fn get_model(&self) -> Option<Model> {
+        match self {
+            Child::ContainerInstance(x) => Some(Model::Container(x.get_model())),
+            Child::LeafInstance(x) => Some(Model::Leaf(x.model.clone())),
+            Child::LeafListInstance(x) => Some(Model::LeafList(x.model.clone())),
+            Child::ListInstance(x) => Some(Model::List(x.get_model())),
+            Child::UnknownInstance(_) => None,
+        }
+    }
+
+    /// Whether this is a leaf or leaf-list added by `apply_defaults`.
+    pub fn is_default(&self) -> bool {
+        match self {
+            Child::LeafInstance(x) => x.is_default,
+            Child::LeafListInstance(x) => x.is_default,
+            _ => false,
+        }
+    }
+
+    /// Whether this is a leaf or leaf-list that has its default value,
+    /// whether it was set or added by `apply_defaults`.
+    pub fn has_default_value(&self) -> bool {
+        match self {
+            Child::LeafInstance(x) => x.has_default_value(),
+            Child::LeafListInstance(x) => x.has_default_values(),
+            _ => false,
+        }
+    }
+
+    pub fn to_json(&self, options: &SerializeOptions) -> Value {
+        match self {
+            Child::ContainerInstance(x) => x.to_json(options),
+            Child::LeafInstance(x) => x.to_json(),
+            Child::LeafListInstance(x) => x.to_json(),
+            Child::ListInstance(x) => x.to_json(options),
+            Child::UnknownInstance(x) => x.value.clone(),
+        }
+    }
+}
+
+impl PartialEq for Parent {
+    fn eq(&self, other: &Parent) -> bool {
+        match (self, other) {
+            (Parent::ContainerData(a), Parent::ContainerData(b)) => Weak::ptr_eq(a, b),
+            (Parent::ListChildData(a), Parent::ListChildData(b)) => Weak::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Parent {
+    pub fn get_path(&self) -> String {
+        match self {
+            Parent::ContainerData(x) => x.upgrade().unwrap().borrow().get_path(),
+            Parent::ListChildData(x) => x.upgrade().unwrap().borrow().get_path(),
+        }
+    }
+
+    /// The container or list entry this node is in, which is `None` for the
+    /// root of the data tree.
+    pub fn get_parent(&self) -> Option<Parent> {
+        match self {
+            Parent::ContainerData(x) => x.upgrade().unwrap().borrow().parent.clone(),
+            Parent::ListChildData(x) => {
+                let list = x.upgrade().unwrap().borrow().parent.upgrade().unwrap();
+                let parent = list.borrow().parent.clone();
+                Some(parent)
+            }
+        }
+    }
+
+    /// The schema node of this container or list entry.
+    pub fn get_model(&self) -> Model {
+        match self {
+            Parent::ContainerData(x) => {
+                Model::Container(x.upgrade().unwrap().borrow().model.clone())
+            }
+            Parent::ListChildData(x) => Model::List(x.upgrade().unwrap().borrow().model.clone()),
+        }
+    }
+
+    /// Calls `f` with the children of this node.
+    pub fn with_children<R>(&self, f: impl FnOnce(&IndexMap<Ustr, Child>) -> R) -> R {
+        match self {
+            Parent::ContainerData(x) => f(&x
+                .upgrade()
+                .unwrap()
+                .borrow()
+                .children
+                .as_ref()
+                .unwrap()
+                .borrow()),
+            Parent::ListChildData(x) => f(&x
+                .upgrade()
+                .unwrap()
+                .borrow()
+                .children
+                .as_ref()
+                .unwrap()
+                .borrow()),
+        }
+    }
+}
+
+/// What to do with JSON members that match no schema node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownMembers {
+    /// Keep them in the instance as `UnknownInstance` nodes.
+    #[default]
+    Keep,
+    /// Report them as errors.
+    Reject,
+}
+
+/// Options for parsing the JSON of an instance.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParseOptions {
+    /// Keep parsing after an error to report every error in the data, rather
+    /// than stopping at the first one.
+    pub collect_errors: bool,
+    pub unknown_members: UnknownMembers,
+    /// The naming convention of the member names, where either is accepted
+    /// if none is given.
+    pub names: Option<NameStyle>,
+    /// Report `config false` nodes as errors, as when the data is the
+    /// contents of a configuration datastore or an edit of one.
+    pub config_only: bool,
+    /// Only accept values of the kind of JSON value RFC 7951 encodes them
+    /// as, rather than the lexical form of a value in any scalar.
+    pub strict_kinds: bool,
+}
+
+/// The state of parsing an instance, which gathers the errors found in the
+/// nodes below the one being parsed.
+#[derive(Debug)]
+pub struct ParseContext {
+    pub options: ParseOptions,
+    errors: Vec<InstanceError>,
+}
+
+impl ParseContext {
+    pub fn new(options: ParseOptions) -> ParseContext {
+        ParseContext {
+            options,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Records an error in a node, which is then left out of the instance.
+    pub fn report(&mut self, error: InstanceError) {
+        self.errors.push(error);
+    }
+
+    /// Whether parsing should stop, which it does after the first error
+    /// unless all errors are being collected.
+    pub fn is_done(&self) -> bool {
+        !self.options.collect_errors && !self.errors.is_empty()
+    }
+
+    pub fn into_errors(self) -> Vec<InstanceError> {
+        self.errors
+    }
+}
+
+/// How member names are written when serializing an instance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameStyle {
+    /// The YANG names, qualified with their module wherever RFC 7951 requires
+    /// it, i.e. at the top level and where the module changes.
+    #[default]
+    Yang,
+    /// The camelCase names, qualified with their module where it differs
+    /// from that of their parent, as the children of the schema nodes are
+    /// keyed. Top-level names are only qualified where they are ambiguous.
+    CamelCase,
+}
+
+/// Which nodes with default values are written when serializing an
+/// instance, following the with-defaults modes of RFC 6243.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WithDefaults {
+    /// Every node with data, along with the defaults in effect for those
+    /// without.
+    ReportAll,
+    /// Like `ReportAll`, with every leaf and leaf-list that has its default
+    /// value annotated as `ietf-netconf-with-defaults:default`.
+    ReportAllTagged,
+    /// The nodes with data, except for the leaves and leaf-lists that have
+    /// their default value.
+    Trim,
+    /// The nodes with data that was set rather than added by
+    /// `apply_defaults`, whatever their value.
+    #[default]
+    Explicit,
+}
+
+/// The part of an instance to keep when separating configuration from state
+/// data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    Config,
+    State,
+}
+
+/// Options for serializing an instance to JSON.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerializeOptions {
+    pub names: NameStyle,
+    pub with_defaults: WithDefaults,
+}
+
+/// The metadata annotation (RFC 7952) of the nodes that have their default
+/// value in the `report-all-tagged` mode.
+const DEFAULT_ANNOTATION: &str = "ietf-netconf-with-defaults:default";
+
+/// Returns the member name of a node in the JSON object of its parent, whose
+/// module is `parent_module` and whose schema children are `models`. In
+/// camelCase, top-level nodes go without their module where no other one has
+/// the name.
+pub fn get_member_name(
+    model: &Model,
+    models: &IndexMap<String, Model>,
+    parent_module: Option<&str>,
+    options: &SerializeOptions,
+) -> String {
+    let is_unique = || {
+        models
+            .values()
+            .filter(|m| m.get_name() == model.get_name())
+            .count()
+            == 1
+    };
+
+    match options.names {
+        NameStyle::CamelCase if parent_module.is_none() && is_unique() => {
+            model.get_name().to_string()
+        }
+        NameStyle::CamelCase if model.get_module() != parent_module => model.get_qualified_name(),
+        NameStyle::CamelCase => model.get_name().to_string(),
+        NameStyle::Yang => model.get_key(parent_module),
+    }
+}
+
+/// Adds the member of `child` named `name` to `members`, unless the
+/// with-defaults mode leaves it out.
+fn add_member(
+    name: String,
+    child: &Child,
+    options: &SerializeOptions,
+    members: &mut Map<String, Value>,
+) {
+    let has_default_value = child.has_default_value();
+
+    let is_left_out = match options.with_defaults {
+        WithDefaults::Explicit => child.is_default(),
+        WithDefaults::Trim => has_default_value,
+        WithDefaults::ReportAll | WithDefaults::ReportAllTagged => false,
+    };
+    if is_left_out {
+        return;
+    }
+
+    let value = child.to_json(options);
+
+    // A container whose children were all left out is left out too, unless
+    // its existence means something.
+    if let Child::ContainerInstance(x) = child {
+        if !x.get_model().presence && !x.is_empty() && value == Value::Object(Map::new()) {
+            return;
+        }
+    }
+
+    if options.with_defaults == WithDefaults::ReportAllTagged && has_default_value {
+        let mut annotation = Map::new();
+        annotation.insert(DEFAULT_ANNOTATION.to_string(), Value::Bool(true));
+        let annotation = Value::Object(annotation);
+
+        let metadata = match child {
+            Child::LeafListInstance(x) => Value::Array(vec![annotation; x.children.len()]),
+            _ => annotation,
+        };
+
+        members.insert(format!("@{}", name), metadata);
+    }
+
+    members.insert(name, value);
+}
+
+/// Serializes the children of a container or list entry, which is in the
+/// namespace of `module` and has the schema children `models` and
+/// `choices`.
+pub fn children_to_json(
+    children: &IndexMap<Ustr, Child>,
+    models: &IndexMap<String, Model>,
+    choices: &[Arc<Choice>],
+    parent: &Parent,
+    module: Option<&str>,
+    options: &SerializeOptions,
+) -> Value {
+    let mut members = Map::new();
+
+    for child in children.values() {
+        let name = match (child, child.get_model()) {
+            (_, Some(m)) => get_member_name(&m, models, module, options),
+            (Child::UnknownInstance(x), None) => x.name.clone(),
+            _ => continue,
+        };
+
+        add_member(name, child, options, &mut members);
+    }
+
+    if let WithDefaults::ReportAll | WithDefaults::ReportAllTagged = options.with_defaults {
+        for (_, child) in get_defaults(children, models, choices, parent) {
+            if let Some(m) = child.get_model() {
+                add_member(
+                    get_member_name(&m, models, module, options),
+                    &child,
+                    options,
+                    &mut members,
+                );
+            }
+        }
+    }
+
+    Value::Object(members)
+}
+
+/// Returns the key and schema node of the member `name` among `models`,
+/// where the name is the YANG or camelCase name of the node as `names`
+/// requires. It may be qualified with the module of the node, as in
+/// `module:name`, which can only be left out for a node from another module
+/// than its parent's if no other node has the name. YANG names are matched
+/// before camelCase ones, and a camelCase name shared by several nodes, such
+/// as `fooBar` for `foo-bar` and `foo_bar`, matches none of them.
+pub fn find_model<'a>(
+    models: &'a IndexMap<String, Model>,
+    name: &str,
+    names: Option<NameStyle>,
+) -> Option<(&'a String, &'a Model)> {
+    let (module, local_name) = split_prefix(name);
+    let find = |is_named: &dyn Fn(&Model) -> bool| {
+        let candidates: Vec<(&String, &Model)> = models
+            .iter()
+            .filter(|(_, m)| is_named(m) && (module.is_none() || m.get_module() == module))
+            .collect();
+
+        match (module, candidates.len()) {
+            (None, n) if n > 1 => find_unique(candidates.into_iter(), |(k, _)| !k.contains(':')),
+            _ => find_unique(candidates.into_iter(), |_| true),
+        }
+    };
+
+    let by_yang_name = || match names {
+        Some(NameStyle::CamelCase) => None,
+        _ => find(&|m| m.get_yang_name() == local_name),
+    };
+    let by_name = || match names {
+        Some(NameStyle::Yang) => None,
+        _ => find(&|m| m.get_name() == local_name),
+    };
+
+    by_yang_name().or_else(by_name)
+}
+
+/// Returns the member name of `model` as `names` requires, or both of its
+/// names if either is accepted.
+pub fn get_member_names(model: &Model, names: Option<NameStyle>) -> Vec<&str> {
+    match names {
+        Some(NameStyle::Yang) => vec![model.get_yang_name()],
+        Some(NameStyle::CamelCase) => vec![model.get_name()],
+        None => vec![model.get_name(), model.get_yang_name()],
+    }
+}
+
+/// Returns the child of a container or list entry whose schema node is
+/// keyed `name`, or else the only one with that YANG or camelCase name.
+pub fn find_child<'a>(children: &'a IndexMap<Ustr, Child>, name: &str) -> Option<&'a Child> {
+    let is_named = |is_named: fn(&Model, &str) -> bool| {
+        find_unique(children.values(), |c| {
+            c.get_model().is_some_and(|m| is_named(&m, name))
+        })
+    };
+
+    children
+        .get(&ustr(name))
+        .filter(|c| c.get_model().is_some())
+        .or_else(|| is_named(|m, n| m.get_yang_name() == n))
+        .or_else(|| is_named(|m, n| m.get_name() == n))
+}
+
+/// Returns the value of the leaf at `path` below a container or list entry,
+/// where the path is made of the camelCase names of the nodes.
+pub fn get_leaf_value(children: &IndexMap<Ustr, Child>, path: &[&str]) -> Option<LeafValue> {
+    let (name, rest) = path.split_first()?;
+
+    match find_child(children, name)? {
+        Child::LeafInstance(x) if rest.is_empty() => Some(x.value.clone()),
+        Child::ContainerInstance(x) => x.get_leaf_value(rest),
+        _ => None,
+    }
+}
+
+/// Returns the list at `path` below a container or list entry, where the
+/// path is made of the camelCase names of the containers down to it.
+pub fn get_list(children: &IndexMap<Ustr, Child>, path: &[&str]) -> Option<ListInstance> {
+    let (name, rest) = path.split_first()?;
+
+    match find_child(children, name)? {
+        Child::ListInstance(x) if rest.is_empty() => Some(x.clone()),
+        Child::ContainerInstance(x) => x.get_list(rest),
+        _ => None,
+    }
+}
+
+/// Returns the keys of the children that have a schema node, which are those
+/// of their schema nodes.
+fn get_present(children: &IndexMap<Ustr, Child>) -> HashSet<String> {
+    children
+        .iter()
+        .filter(|(_, c)| c.get_model().is_some())
+        .map(|(k, _)| k.to_string())
+        .collect()
+}
+
+/// Returns the cases of `choice` that have data, where `present` holds the
+/// names of the nodes that do. The first one is the active case, and any
+/// other is an error.
+fn get_active_cases<'a>(choice: &'a Choice, present: &HashSet<String>) -> Vec<&'a Case> {
+    choice
+        .cases
+        .iter()
+        .filter(|c| c.children.keys().any(|k| present.contains(k)))
+        .collect()
+}
+
+/// Returns the name of the active case of the choice `name` among the
+/// children of a container or list entry, if any case has data.
+pub fn get_active_case(
+    children: &IndexMap<Ustr, Child>,
+    model: &dyn WithChildren,
+    name: &str,
+) -> Option<String> {
+    let choice = model.get_choice(name)?;
+
+    get_active_cases(choice, &get_present(children))
+        .first()
+        .map(|c| c.name.clone())
+}
+
+/// Whether the defaults of the node `name` are in effect when it has no
+/// data, where `present` holds the names of its siblings that do. Within a
+/// choice, that is when its case is the active one, or when no case is
+/// active and its case is the default one, and so on for nested choices.
+fn is_default_in_effect(choices: &[Arc<Choice>], present: &HashSet<String>, name: &str) -> bool {
+    choices
+        .iter()
+        .filter(|c| c.children.contains_key(name))
+        .all(|choice| {
+            let case = match choice.get_case(name) {
+                Some(x) => x,
+                None => return false,
+            };
+            let is_selected = match get_active_cases(choice, present).first() {
+                Some(x) => x.name == case.name,
+                None => choice.default.as_ref() == Some(&case.name),
+            };
+
+            is_selected && is_default_in_effect(&case.choices, present, name)
+        })
+}
+
+/// Returns the value of the leaf at `path` below a container or list entry,
+/// or the default in effect for it if it has no data. Nodes with a `when`
+/// condition have no default here, as they may not be allowed to exist.
+pub fn get_effective_value(
+    children: &IndexMap<Ustr, Child>,
+    models: &IndexMap<String, Model>,
+    choices: &[Arc<Choice>],
+    path: &[&str],
+) -> Option<LeafValue> {
+    let (name, rest) = path.split_first()?;
+
+    match find_child(children, name) {
+        Some(Child::LeafInstance(x)) if rest.is_empty() => Some(x.value.clone()),
+        Some(Child::ContainerInstance(x)) => x.get_effective_value(rest),
+        Some(_) => None,
+        None => {
+            let (key, model) = find_child_entry(models, name)?;
+
+            if !model.get_when().is_empty()
+                || !is_default_in_effect(choices, &get_present(children), key)
+            {
+                return None;
+            }
+
+            match model {
+                Model::Leaf(x) if rest.is_empty() => {
+                    LeafValue::from_text(&x.leaf_type, x.default.as_ref()?)
+                }
+                Model::Container(x) if !x.presence => {
+                    get_effective_value(&IndexMap::new(), &x.children, &x.choices, rest)
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Returns the nodes holding the defaults in effect for the children of a
+/// container or list entry that have no data, keyed like their schema
+/// nodes. These are leaves and leaf-lists marked as defaults, and the
+/// non-presence containers that have any defaults. Nodes with a `when`
+/// condition are left out, as they may not be allowed to exist.
+fn get_defaults(
+    children: &IndexMap<Ustr, Child>,
+    models: &IndexMap<String, Model>,
+    choices: &[Arc<Choice>],
+    parent: &Parent,
+) -> Vec<(String, Child)> {
+    let present = get_present(children);
+    let mut defaults = Vec::new();
+
+    for (name, model) in models {
+        if present.contains(name)
+            || !model.get_when().is_empty()
+            || !is_default_in_effect(choices, &present, name)
+        {
+            continue;
+        }
+
+        let child = match model {
+            Model::Leaf(x) => {
+                LeafInstance::from_default(x.clone(), parent.clone()).map(Child::LeafInstance)
+            }
+            Model::LeafList(x) => LeafListInstance::from_defaults(x.clone(), parent.clone())
+                .map(Child::LeafListInstance),
+            Model::Container(x) if !x.presence => {
+                ContainerInstance::from_defaults(x.clone(), parent.clone())
+                    .map(Child::ContainerInstance)
+            }
+            _ => None,
+        };
+
+        defaults.extend(child.map(|c| (name.clone(), c)));
+    }
+
+    defaults
+}
+
+/// Adds the defaults in effect to the children of a container or list entry
+/// and of its descendants.
+pub fn apply_defaults(
+    children: &mut IndexMap<Ustr, Child>,
+    models: &IndexMap<String, Model>,
+    choices: &[Arc<Choice>],
+    parent: &Parent,
+) {
+    for child in children.values() {
+        match child {
+            Child::ContainerInstance(x) => x.apply_defaults(),
+            Child::ListInstance(x) => {
+                for entry in x.get_entries() {
+                    entry.apply_defaults();
+                }
+            }
+            _ => (),
+        }
+    }
+
+    for (name, child) in get_defaults(children, models, choices, parent) {
+        children.insert(ustr(&name), child);
+    }
+}
+
+/// Checks the number of entries of the list or leaf-list at `path`.
+pub fn check_elements(
+    count: usize,
+    min: u64,
+    max: Option<u64>,
+    path: &str,
+    errors: &mut Vec<InstanceError>,
+) {
+    if (count as u64) < min {
+        errors.push(InstanceError::TooFewElements {
+            path: path.to_string(),
+            count,
+            min,
+        });
+    }
+
+    if let Some(max) = max.filter(|m| count as u64 > *m) {
+        errors.push(InstanceError::TooManyElements {
+            path: path.to_string(),
+            count,
+            max,
+        });
+    }
+}
+
+/// Reports the nodes among `models` that must exist but have no data, where
+/// `present` holds the names of the children of the node at `path` that do.
+/// Only the active case of a choice is checked, and the descendants of a
+/// missing container only if it is not a presence container. Nodes with a
+/// `when` condition are not required, as they may not be allowed to exist.
+fn check_missing(
+    models: &IndexMap<String, Model>,
+    choices: &[Arc<Choice>],
+    present: &HashSet<String>,
+    path: &str,
+    errors: &mut Vec<InstanceError>,
+) {
+    let in_choices: HashSet<&String> = choices.iter().flat_map(|c| c.children.keys()).collect();
+
+    for (name, model) in models {
+        if present.contains(name) || in_choices.contains(name) {
+            continue;
+        }
+
+        let child_path = format!("{}/{}", path, model.get_name());
+
+        match model {
+            Model::Leaf(x) if x.mandatory && x.when.is_empty() => {
+                errors.push(InstanceError::MissingMandatory { path: child_path })
+            }
+            Model::LeafList(x) if x.when.is_empty() => {
+                check_elements(0, x.min_elements, None, &child_path, errors)
+            }
+            Model::List(x) if x.when.is_empty() => {
+                check_elements(0, x.min_elements, None, &child_path, errors)
+            }
+            Model::Container(x) if !x.presence && x.when.is_empty() => check_missing(
+                &x.children,
+                &x.choices,
+                &HashSet::new(),
+                &child_path,
+                errors,
+            ),
+            _ => (),
+        }
+    }
+
+    for choice in choices {
+        let active_cases = get_active_cases(choice, present);
+
+        if active_cases.len() > 1 {
+            errors.push(InstanceError::ConflictingCases {
+                path: path.to_string(),
+                choice: choice.name.clone(),
+                cases: active_cases.iter().map(|c| c.name.clone()).collect(),
+            });
+        }
+
+        match active_cases.first() {
+            Some(case) => check_missing(&case.children, &case.choices, present, path, errors),
+            None if choice.mandatory && choice.when.is_empty() => {
+                errors.push(InstanceError::MissingChoice {
+                    path: path.to_string(),
+                    choice: choice.name.clone(),
+                })
+            }
+            None => (),
+        }
+    }
+}
+
+/// Validates the children of the container or list entry at `path`, whose
+/// schema node has the children `models` and `choices`.
+pub fn validate_children(
+    children: &IndexMap<Ustr, Child>,
+    models: &IndexMap<String, Model>,
+    choices: &[Arc<Choice>],
+    path: &str,
+    errors: &mut Vec<InstanceError>,
+) {
+    for child in children.values() {
+        match child {
+            Child::ContainerInstance(x) => x.validate(errors),
+            Child::LeafInstance(x) => x.validate(errors),
+            Child::LeafListInstance(x) => x.validate(errors),
+            Child::ListInstance(x) => x.validate(errors),
+            Child::UnknownInstance(_) => (),
+        }
+    }
+
+    check_missing(models, choices, &get_present(children), path, errors);
+}
+
+/// Removes the children that are not of the `kind` of data to keep, along
+/// with containers and lists left without data. The `keys` of a list entry
+/// are kept, but do not count as data when keeping state. Unknown nodes are
+/// considered to be configuration. Returns whether any data is left.
+pub fn retain_data(children: &mut IndexMap<Ustr, Child>, kind: DataKind, keys: &[String]) -> bool {
+    let mut has_data = false;
+
+    children.retain(|_, child| {
+        let model = child.get_model();
+        let keep = match &model {
+            None => kind == DataKind::Config,
+            // The descendants of state data are all state data.
+            Some(m) if !m.is_config() => kind == DataKind::State,
+            Some(_) => match child {
+                Child::ContainerInstance(x) => x.retain_data(kind),
+                Child::ListInstance(x) => x.retain_data(kind),
+                _ => kind == DataKind::Config,
+            },
+        };
+
+        has_data |= keep;
+        keep || model.is_some_and(|m| keys.iter().any(|k| k == m.get_yang_name()))
+    });
+
+    has_data
+}
+
+/// Parses the value of a leaf or leaf-list entry at `path`, which has to be
+/// of the kind of JSON value its type is encoded as if `strict`.
+pub fn parse_value(
+    leaf_type: &LeafType,
+    value: &Value,
+    path: &str,
+    strict: bool,
+) -> Result<LeafValue, InstanceError> {
+    if let Some(x) = LeafValue::parse(leaf_type, value, strict) {
+        return Ok(x);
+    }
+
+    let kinds = get_kinds(leaf_type);
+    if strict && !kinds.contains(&JsonKind::of(value)) {
+        return Err(InstanceError::unexpected_kind(path, &kinds, value));
+    }
+
+    // Only an empty leaf may have a value that is not a scalar.
+    let text = match &leaf_type.builtin {
+        BuiltinType::Empty => value.to_string(),
+        _ => get_scalar(value, path)?,
+    };
+
+    Err(InstanceError::InvalidValue {
+        path: path.to_string(),
+        type_name: leaf_type.name().to_string(),
+        value: text,
+    })
+}
+
+/// Returns the string form of a leaf or leaf-list value at `path`.
+pub fn get_scalar(value: &Value, path: &str) -> Result<String, InstanceError> {
+    match value {
+        Value::String(x) => Ok(x.clone()),
+        Value::Number(x) => Ok(x.to_string()),
+        Value::Bool(x) => Ok(x.to_string()),
+        x => Err(InstanceError::unexpected_kind(
+            path,
+            &[JsonKind::String, JsonKind::Number, JsonKind::Boolean],
+            x,
+        )),
+    }
+}
+
+/// Parses the value of a member that has no schema node.
+pub fn parse_unknown(
+    name: &str,
+    value: Value,
+    parent: Parent,
+    context: &ParseContext,
+) -> Result<Child, InstanceError> {
+    match context.options.unknown_members {
+        UnknownMembers::Keep => Ok(Child::UnknownInstance(UnknownInstance::new(
+            name, value, parent,
+        ))),
+        UnknownMembers::Reject => Err(InstanceError::UnknownMember {
+            path: format!("{}/{}", parent.get_path(), name),
+        }),
+    }
+}
+
+/// Parses the value of a member whose schema node is `model`.
+pub fn parse_child(
+    model: &Model,
+    value: Value,
+    parent: Parent,
+    context: &mut ParseContext,
+) -> Result<Child, InstanceError> {
+    if context.options.config_only && !model.is_config() {
+        return Err(InstanceError::StateData {
+            path: format!("{}/{}", parent.get_path(), model.get_name()),
+        });
+    }
+
+    let child = match model {
+        Model::Leaf(m) => Child::LeafInstance(LeafInstance::new(
+            m.clone(),
+            value,
+            parent,
+            context.options.strict_kinds,
+        )?),
+        Model::Container(m) => Child::ContainerInstance(ContainerInstance::new(
+            m.clone(),
+            value,
+            Some(parent),
+            context,
+        )?),
+        Model::LeafList(m) => {
+            Child::LeafListInstance(LeafListInstance::new(m.clone(), value, parent, context)?)
+        }
+        Model::List(m) => {
+            Child::ListInstance(ListInstance::new(m.clone(), value, parent, context)?)
+        }
+    };
+
+    Ok(child)
+}
@@ -1,63 +1,100 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use inflector::cases::camelcase::to_camel_case;
 use std::sync::Arc;
 use sxd_document::*;
 
+use super::choice::Choice;
+use super::constraint::{parse_when, When};
+use super::error::ModelError;
+use super::scope::Scope;
 use super::util::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Case {
     pub name: String,
-    pub children: HashMap<String, Model>,
+    pub yang_name: String,
+    /// The data nodes of the case, including those of its choices.
+    pub children: IndexMap<String, Model>,
+    pub choices: Vec<Arc<Choice>>,
 }
 
-fn parse_children_from_case(el: dom::Element) -> HashMap<String, Model> {
-    parse_children(el)
+fn parse_children_from_case<'d>(
+    el: dom::Element<'d>,
+    scope: &Scope<'d>,
+) -> Result<Children, ModelError> {
+    parse_children(el, scope)
 }
 
-fn parse_children_from_implicit_case(el: dom::Element) -> HashMap<String, Model> {
-    let child_option = parse_child(el);
-    let mut children: HashMap<String, Model> = HashMap::new();
+fn parse_children_from_implicit_case<'d>(
+    el: dom::Element<'d>,
+    scope: &Scope<'d>,
+) -> Result<Children, ModelError> {
+    let mut children: IndexMap<String, Model> = IndexMap::new();
+    let mut choices: Vec<Arc<Choice>> = Vec::new();
 
-    if let Some(c) = child_option {
-        match c {
-            Child::Choice(c) => {
-                children = c.children;
-            }
-            Child::Leaf(c) => {
-                children.insert(c.name.clone(), Model::Leaf(Arc::new(c)));
-            }
-            Child::LeafList(c) => {
-                children.insert(c.name.clone(), Model::LeafList(Arc::new(c)));
-            }
-            Child::Container(c) => {
-                children.insert(c.name.clone(), Model::Container(Arc::new(c)));
-            }
-            Child::List(c) => {
-                children.insert(c.name.clone(), Model::List(Arc::new(c)));
-            }
+    if let Some(c) = parse_child(el, scope)? {
+        if let Child::Choice(x) = &c {
+            choices.push(Arc::new(x.clone()));
         }
+
+        children.extend(c.into_models(scope.module().as_deref()));
     }
 
-    children
+    Ok((children, choices))
 }
 
 impl Case {
-    pub fn new(el: dom::Element) -> Case {
-        let name = get_name(el);
-
-        Case {
-            name,
-            children: if el.name().local_part() == "case" {
-                parse_children_from_case(el)
-            } else {
-                parse_children_from_implicit_case(el)
-            },
+    pub fn new<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Case, ModelError> {
+        let yang_name = get_raw_name(el, &scope.path())?;
+        let (children, choices) = if el.name().local_part() == "case" {
+            parse_children_from_case(el, scope)?
+        } else {
+            parse_children_from_implicit_case(el, scope)?
+        };
+
+        let mut case = Case {
+            name: to_camel_case(&yang_name),
+            yang_name,
+            children,
+            choices,
+        };
+
+        // The `when` of an implicit case is that of its data node.
+        if el.name().local_part() == "case" {
+            if let Some(w) = parse_when(el, true, &scope.path())? {
+                case.add_when(w);
+            }
+        }
+
+        Ok(case)
+    }
+
+    /// Keys the data nodes of the case as the children of a node in the
+    /// namespace of `parent_module`.
+    pub fn qualify(&mut self, parent_module: Option<&str>) {
+        qualify(&mut self.children, parent_module);
+
+        for choice in &mut self.choices {
+            Arc::make_mut(choice).qualify(parent_module);
+        }
+    }
+
+    /// Makes the data nodes of the case, including those of its choices,
+    /// conditional on `when`.
+    pub fn add_when(&mut self, when: When) {
+        for model in self.children.values_mut() {
+            model.add_when(when.clone());
+        }
+
+        for choice in &mut self.choices {
+            Arc::make_mut(choice).add_when(when.clone());
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::scope::Scope;
     use crate::model::util::*;
 
     const EXPLICT_MODEL: &str = r#"<?xml version="1.0"?>
@@ -74,29 +111,29 @@ mod tests {
 
     #[test]
     fn it_parses_name_explicit() {
-        let pkg = get_package(EXPLICT_MODEL);
-        let model = super::Case::new(get_root_el(&pkg));
+        let pkg = get_package(EXPLICT_MODEL).unwrap();
+        let model = super::Case::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "explicit");
     }
 
     #[test]
     fn it_parses_name_implicit() {
-        let pkg = get_package(IMPLICIT_MODEL);
-        let model = super::Case::new(get_root_el(&pkg));
+        let pkg = get_package(IMPLICIT_MODEL).unwrap();
+        let model = super::Case::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "bar");
     }
 
     #[test]
     fn it_parses_children_explicit() {
-        let pkg = get_package(EXPLICT_MODEL);
-        let model = super::Case::new(get_root_el(&pkg));
-        assert!(model.children.get("bar").is_some());
+        let pkg = get_package(EXPLICT_MODEL).unwrap();
+        let model = super::Case::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.children.contains_key("bar"));
     }
 
     #[test]
     fn it_parses_children_implicit() {
-        let pkg = get_package(IMPLICIT_MODEL);
-        let model = super::Case::new(get_root_el(&pkg));
-        assert!(model.children.get("bar").is_some());
+        let pkg = get_package(IMPLICIT_MODEL).unwrap();
+        let model = super::Case::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.children.contains_key("bar"));
     }
 }
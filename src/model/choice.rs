@@ -1,55 +1,190 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use inflector::cases::camelcase::to_camel_case;
 use sxd_document::*;
 
 use super::case::Case;
+use super::constraint::{parse_when, When};
+use super::error::ModelError;
+use super::scope::Scope;
 use super::util::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Choice {
     pub name: String,
-    pub children: HashMap<String, Model>,
-    cases: Vec<Case>,
+    pub yang_name: String,
+    pub module: Option<String>,
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    /// The data nodes of all of the cases.
+    pub children: IndexMap<String, Model>,
+    pub cases: Vec<Case>,
+    pub mandatory: bool,
+    pub config: bool,
+    /// The name of the case whose default values are in effect when no case
+    /// has any data, in camelCase like the names of the cases.
+    pub default: Option<String>,
+    pub augmented_by: Option<String>,
+    pub when: Vec<When>,
 }
 
-fn parse_cases(el: dom::Element) -> Vec<Case> {
+fn parse_cases<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Vec<Case>, ModelError> {
     let mut cases: Vec<Case> = Vec::new();
+    let path = scope.path();
 
-    for child in el.children() {
-        if let dom::ChildOfElement::Element(e) = child {
-            let model_type = e.name().local_part();
+    for e in el.children().into_iter().filter_map(|c| c.element()) {
+        check_statement(e, &path)?;
 
-            match model_type {
-                "case" | "leaf" | "container" | "list" | "leaf-list" => {
-                    let case = Case::new(e);
-                    cases.push(case);
-                }
-                _ => (),
+        match e.name().local_part() {
+            "case" | "leaf" | "container" | "list" | "leaf-list" | "choice" => {
+                let case = Case::new(e, &scope.descend(&get_raw_name(e, &path)?))?;
+                cases.push(case);
+            }
+            // Nodes of arbitrary data are not modeled, so neither is a case
+            // made of one.
+            statement @ ("anydata" | "anyxml") => {
+                return Err(ModelError::UnknownStatement {
+                    path,
+                    statement: statement.to_string(),
+                })
             }
+            _ => (),
         }
     }
 
-    cases
+    Ok(cases)
 }
 
 impl Choice {
-    pub fn new(el: dom::Element) -> Choice {
-        let cases = parse_cases(el);
-        let mut children = HashMap::new();
+    pub fn new<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Choice, ModelError> {
+        let mut cases = parse_cases(el, scope)?;
+
+        for augment_el in scope.augments() {
+            let augment_scope = scope.with_module(augment_el);
+            let when = parse_when(augment_el, true, &scope.path())?;
+
+            for mut case in parse_cases(augment_el, &augment_scope)? {
+                for model in case.children.values_mut() {
+                    model.set_augmented_by(augment_el);
+                }
+
+                if let Some(w) = &when {
+                    case.add_when(w.clone());
+                }
+
+                case.qualify(scope.module().as_deref());
+                cases.push(case);
+            }
+        }
+
+        let mut children = IndexMap::new();
 
         for case in &cases {
             children.extend(case.children.clone());
         }
 
-        Choice {
-            name: get_name(el),
+        let default = get_refinable_value(el, "default", scope)?.map(|x| to_camel_case(&x));
+
+        if let Some(name) = default
+            .as_ref()
+            .filter(|d| !cases.iter().any(|c| &c.name == *d))
+        {
+            return Err(ModelError::UnresolvedReference {
+                path: scope.path(),
+                statement: "default".to_string(),
+                name: name.clone(),
+            });
+        }
+
+        let yang_name = get_raw_name(el, &scope.path())?;
+        let mut choice = Choice {
+            name: to_camel_case(&yang_name),
+            yang_name,
+            module: scope.module(),
+            namespace: scope.namespace(),
+            prefix: scope.prefix(),
             cases,
             children,
+            mandatory: parse_mandatory(el, scope)?,
+            config: scope.config(),
+            default,
+            augmented_by: None,
+            when: Vec::new(),
+        };
+
+        if let Some(w) = parse_when(el, true, &scope.path())? {
+            choice.add_when(w);
+        }
+
+        Ok(choice)
+    }
+
+    /// Keys the data nodes of the cases as the children of a node in the
+    /// namespace of `parent_module`, which the choice is in.
+    pub fn qualify(&mut self, parent_module: Option<&str>) {
+        qualify(&mut self.children, parent_module);
+
+        for case in &mut self.cases {
+            case.qualify(parent_module);
+        }
+    }
+
+    /// Returns the case that the data node `name` is in, which may be in a
+    /// choice nested in the case. As with `get_child`, the node is given by
+    /// its key or else by its YANG or camelCase name.
+    pub fn get_case(&self, name: &str) -> Option<&Case> {
+        let (key, _) = find_child_entry(&self.children, name)?;
+
+        self.cases.iter().find(|c| c.children.contains_key(key))
+    }
+
+    /// Returns this choice if it is named `name`, or else the choice named
+    /// `name` that is nested in one of its cases.
+    pub fn find(&self, name: &str) -> Option<&Choice> {
+        if self.name == name || self.yang_name == name {
+            return Some(self);
+        }
+
+        self.cases
+            .iter()
+            .flat_map(|c| c.choices.iter())
+            .find_map(|c| c.find(name))
+    }
+
+    /// Makes the choice and the data nodes of its cases conditional on
+    /// `when`.
+    pub fn add_when(&mut self, when: When) {
+        for model in self.children.values_mut() {
+            model.add_when(when.clone());
+        }
+
+        for case in &mut self.cases {
+            case.add_when(when.clone());
+        }
+
+        self.when.push(when);
+    }
+
+    /// Records that this choice was added to its parent by `augment_el`,
+    /// along with the data nodes of its cases.
+    pub fn set_augmented_by(&mut self, augment_el: dom::Element) {
+        self.augmented_by = get_module_name(augment_el);
+
+        for model in self.children.values_mut() {
+            model.set_augmented_by(augment_el);
+        }
+
+        for case in &mut self.cases {
+            for model in case.children.values_mut() {
+                model.set_augmented_by(augment_el);
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::error::ModelError;
+    use crate::model::scope::Scope;
     use crate::model::util::*;
 
     const MODEL: &str = r#"<?xml version="1.0"?>
@@ -66,29 +201,129 @@ mod tests {
 
     #[test]
     fn it_parses_name() {
-        let pkg = get_package(MODEL);
-        let model = super::Choice::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "foo");
+        assert_eq!(model.yang_name, "foo");
     }
 
     #[test]
     fn it_parses_implicit_child() {
-        let pkg = get_package(MODEL);
-        let model = super::Choice::new(get_root_el(&pkg));
-        assert!(model.children.get("implicit").is_some());
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.children.contains_key("implicit"));
     }
 
     #[test]
     fn it_parses_explicit_child() {
-        let pkg = get_package(MODEL);
-        let model = super::Choice::new(get_root_el(&pkg));
-        assert!(model.children.get("bar").is_some());
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.children.contains_key("bar"));
     }
 
     #[test]
     fn it_parses_cases() {
-        let pkg = get_package(MODEL);
-        let model = super::Choice::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.cases.len(), 2);
+    }
+
+    #[test]
+    fn it_finds_the_case_of_a_node() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.get_case("bar").unwrap().name, "explicit");
+        assert_eq!(model.get_case("implicit").unwrap().name, "implicit");
+        assert!(model.get_case("foo").is_none());
+    }
+
+    #[test]
+    fn it_keeps_cases_that_share_a_camel_case_name() {
+        let pkg = get_package(
+            r#"<?xml version="1.0"?>
+            <yin:choice name="foo" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                <yin:leaf name="foo-bar"><yin:type name="string"/></yin:leaf>
+                <yin:leaf name="foo_bar"><yin:type name="string"/></yin:leaf>
+            </yin:choice>"#,
+        )
+        .unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        let names: Vec<&String> = model.children.keys().collect();
+        assert_eq!(names, vec!["foo-bar", "foo_bar"]);
+        assert_eq!(model.get_case("foo_bar").unwrap().yang_name, "foo_bar");
+        assert!(model.get_case("fooBar").is_none());
+    }
+
+    #[test]
+    fn it_parses_nested_choices_as_cases() {
+        let pkg = get_package(
+            r#"<?xml version="1.0"?>
+            <yin:choice name="foo" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                <yin:leaf name="bar"><yin:type name="string"/></yin:leaf>
+                <yin:choice name="baz">
+                    <yin:leaf name="qux"><yin:type name="string"/></yin:leaf>
+                </yin:choice>
+            </yin:choice>"#,
+        )
+        .unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.cases.len(), 2);
+        assert!(model.children.contains_key("qux"));
+        assert_eq!(model.get_case("qux").unwrap().name, "baz");
+        assert!(model.find("baz").is_some());
+
+        let pkg = get_package(
+            r#"<?xml version="1.0"?>
+            <yin:choice name="foo" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                <yin:anydata name="bar"/>
+            </yin:choice>"#,
+        )
+        .unwrap();
+        let error = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err();
+        assert_eq!(
+            error,
+            ModelError::UnknownStatement {
+                path: "/".to_string(),
+                statement: "anydata".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn it_parses_mandatory() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(!model.mandatory);
+
+        let xml = MODEL.replace(
+            r#"<yin:leaf name="implicit">"#,
+            r#"<yin:mandatory value="true"/><yin:leaf name="implicit">"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.mandatory);
+    }
+
+    #[test]
+    fn it_parses_default() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.default, None);
+
+        let xml = MODEL.replace(
+            r#"<yin:leaf name="implicit">"#,
+            r#"<yin:default value="explicit"/><yin:leaf name="implicit">"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.default, Some("explicit".to_string()));
+
+        let xml = xml.replace(r#"value="explicit""#, r#"value="other""#);
+        let pkg = get_package(&xml).unwrap();
+        let error = super::Choice::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Could not resolve the default other at /."
+        );
     }
 }
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fmt;
+use sxd_document::*;
+use sxd_xpath::Factory;
+
+use super::error::ModelError;
+use super::scope::Scope;
+use super::util::*;
+
+/// The XPath expression of a `must` or `when`, along with the form of it
+/// that is evaluated against the documents instances are validated as.
+#[derive(Clone)]
+pub struct Condition {
+    expression: String,
+    prepared: String,
+    /// The modules that the prefixes of the module the expression is in
+    /// refer to, where the empty prefix is that module itself.
+    modules: HashMap<String, String>,
+}
+
+impl Condition {
+    /// Prepares `expression`, returning `None` if it is not valid XPath.
+    pub fn new(expression: &str) -> Option<Condition> {
+        let prepared = prepare(expression);
+        Factory::new().build(&prepared).ok()??;
+
+        Some(Condition {
+            expression: expression.to_string(),
+            prepared,
+            modules: HashMap::new(),
+        })
+    }
+
+    /// The expression rewritten by `prepare`, which is what gets compiled.
+    pub fn prepared(&self) -> &str {
+        &self.prepared
+    }
+
+    /// The names of the modules that prefixes refer to in the expression,
+    /// where the empty prefix is the module of the expression itself.
+    pub fn modules(&self) -> &HashMap<String, String> {
+        &self.modules
+    }
+}
+
+impl PartialEq for Condition {
+    fn eq(&self, other: &Self) -> bool {
+        self.expression == other.expression
+    }
+}
+
+impl fmt::Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self.expression)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+/// A `must` statement, whose XPath `condition` every instance of the node it
+/// is on has to satisfy (RFC 7950 section 7.5.3).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Must {
+    pub condition: Condition,
+    pub error_message: Option<String>,
+    pub error_app_tag: Option<String>,
+}
+
+/// A `when` statement, whose XPath `condition` a node is only valid under
+/// (RFC 7950 section 7.21.5). It is evaluated with the node as the context
+/// node, or with its parent if `in_parent` is set, as for the `when` of an
+/// `augment`, `uses`, `choice` or `case`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct When {
+    pub condition: Condition,
+    pub in_parent: bool,
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.'
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_' || c == '*' || c == '@' || c == '.'
+}
+
+/// Rewrites an expression for the document built from an instance. Prefixes
+/// are removed, as its elements are in no namespace, and absolute paths
+/// start from the element holding the top-level nodes, as a document has a
+/// single root element. String literals and axes are kept.
+fn prepare(expression: &str) -> String {
+    let mut prepared = String::new();
+    let mut name = String::new();
+    // Whether the last token ends an operand, after which a `/` is a step
+    // rather than the start of an absolute path.
+    let mut after_operand = false;
+    let mut chars = expression.chars().peekable();
+
+    let flush = |prepared: &mut String, name: &mut String, after_operand: &mut bool| {
+        if !name.is_empty() {
+            let is_operator = ["and", "or", "div", "mod"].contains(&name.as_str());
+            *after_operand = !(is_operator && *after_operand);
+            prepared.push_str(name);
+            name.clear();
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                flush(&mut prepared, &mut name, &mut after_operand);
+                prepared.push(c);
+
+                for d in chars.by_ref() {
+                    prepared.push(d);
+
+                    if d == c {
+                        break;
+                    }
+                }
+
+                after_operand = true;
+            }
+            c if is_name_char(c) => name.push(c),
+            ':' if !name.is_empty() && chars.peek().is_some_and(|d| is_name_start(*d)) => {
+                name.clear();
+            }
+            '/' => {
+                flush(&mut prepared, &mut name, &mut after_operand);
+                let is_absolute = !after_operand;
+
+                if is_absolute {
+                    prepared.push_str("$yinz:root");
+                }
+
+                if chars.next_if_eq(&'/').is_some() {
+                    prepared.push_str("//");
+                } else if !is_absolute || chars.peek().is_some_and(|d| is_name_start(*d)) {
+                    prepared.push('/');
+                }
+
+                after_operand = false;
+            }
+            c => {
+                flush(&mut prepared, &mut name, &mut after_operand);
+                prepared.push(c);
+
+                if !c.is_whitespace() {
+                    after_operand = c == ')' || c == ']' || c == '*';
+                }
+            }
+        }
+    }
+
+    flush(&mut prepared, &mut name, &mut after_operand);
+    prepared
+}
+
+/// Returns the modules that the prefixes of the module `el` is in refer to,
+/// along with that module for the empty prefix.
+fn get_prefixed_modules(el: dom::Element) -> HashMap<String, String> {
+    let mut modules = HashMap::new();
+    let module_el = match get_module_el(el) {
+        Some(x) => x,
+        None => return modules,
+    };
+
+    for import_el in get_yin_children(module_el, "import") {
+        let prefix = get_yin_children(import_el, "prefix")
+            .first()
+            .and_then(|p| p.attribute_value("value"));
+
+        if let (Some(p), Some(m)) = (prefix, import_el.attribute_value("module")) {
+            modules.insert(p.to_string(), m.to_string());
+        }
+    }
+
+    if let Some(module) = get_module_name(el) {
+        if let Some(prefix) = get_module_prefix(module_el) {
+            modules.insert(prefix, module.clone());
+        }
+
+        modules.insert(String::new(), module);
+    }
+
+    modules
+}
+
+/// Returns the compiled `condition` of a `must` or `when`, which must be an
+/// XPath expression.
+fn get_condition(el: dom::Element, path: &str) -> Result<Condition, ModelError> {
+    let expression = get_attribute(el, "condition", path)?;
+    let mut condition = Condition::new(&expression).ok_or_else(|| ModelError::InvalidArgument {
+        path: path.to_string(),
+        statement: el.name().local_part().to_string(),
+        value: expression,
+    })?;
+
+    condition.modules = get_prefixed_modules(el);
+    Ok(condition)
+}
+
+/// Returns the text of the `value` of an `error-message`, which YIN writes
+/// as an element.
+fn get_error_message(el: dom::Element) -> Option<String> {
+    let message_el = get_yin_children(el, "error-message").into_iter().next()?;
+    let value_el = get_yin_children(message_el, "value").into_iter().next()?;
+
+    let text: String = value_el
+        .children()
+        .into_iter()
+        .filter_map(|c| c.text())
+        .map(|t| t.text())
+        .collect();
+
+    Some(text.trim().to_string())
+}
+
+/// Returns the `must` statements of a node, including those a `refine` adds
+/// to it.
+pub fn parse_musts<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Vec<Must>, ModelError> {
+    let path = scope.path();
+
+    std::iter::once(el)
+        .chain(scope.refines())
+        .flat_map(|e| get_yin_children(e, "must"))
+        .map(|e| {
+            Ok(Must {
+                condition: get_condition(e, &path)?,
+                error_message: get_error_message(e),
+                error_app_tag: get_yin_children(e, "error-app-tag")
+                    .first()
+                    .and_then(|t| t.attribute_value("value"))
+                    .map(|x| x.to_string()),
+            })
+        })
+        .collect()
+}
+
+/// Returns the `when` statement of `el`, which is evaluated in the parent of
+/// the nodes it applies to unless `el` is a data node itself.
+pub fn parse_when(
+    el: dom::Element,
+    in_parent: bool,
+    path: &str,
+) -> Result<Option<When>, ModelError> {
+    get_yin_children(el, "when")
+        .first()
+        .map(|w| {
+            Ok(When {
+                condition: get_condition(*w, path)?,
+                in_parent,
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::error::ModelError;
+    use crate::model::leaf::Leaf;
+    use crate::model::scope::Scope;
+
+    const MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:leaf name="mtu" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:type name="uint16"/>
+        <yin:when condition="../type = 'ethernet'"/>
+        <yin:must condition=". &gt;= 64">
+            <yin:error-message>
+                <yin:value>The MTU is too small.</yin:value>
+            </yin:error-message>
+            <yin:error-app-tag value="mtu-too-small"/>
+        </yin:must>
+        <yin:must condition=". &lt;= 9000"/>
+    </yin:leaf>"#;
+
+    #[test]
+    fn it_parses_musts() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(
+            model.musts,
+            vec![
+                Must {
+                    condition: Condition::new(". >= 64").unwrap(),
+                    error_message: Some("The MTU is too small.".to_string()),
+                    error_app_tag: Some("mtu-too-small".to_string()),
+                },
+                Must {
+                    condition: Condition::new(". <= 9000").unwrap(),
+                    error_message: None,
+                    error_app_tag: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_parses_when() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(
+            model.when,
+            vec![When {
+                condition: Condition::new("../type = 'ethernet'").unwrap(),
+                in_parent: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn it_prepares_expressions() {
+        assert_eq!(
+            prepare("../t:type = 't:x' and derived-from(if:type, \"if:eth\")"),
+            "../type = 't:x' and derived-from(type, \"if:eth\")"
+        );
+        assert_eq!(prepare("child::t:a/t:*[. > 1.5]"), "child::a/*[. > 1.5]");
+        assert_eq!(
+            prepare("count(/t:a//t:b) = 2 or //c or /"),
+            "count($yinz:root/a//b) = 2 or $yinz:root//c or $yinz:root"
+        );
+        assert_eq!(
+            prepare("../a / 2 and a/b[x = /c]"),
+            "../a / 2 and a/b[x = $yinz:root/c]"
+        );
+    }
+
+    #[test]
+    fn it_rejects_invalid_expressions() {
+        let xml = MODEL.replace(". &gt;= 64", "count(.");
+        let pkg = get_package(&xml).unwrap();
+        let error = Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err();
+        assert_eq!(
+            error,
+            ModelError::InvalidArgument {
+                path: "/".to_string(),
+                statement: "must".to_string(),
+                value: "count(.".to_string(),
+            }
+        );
+    }
+}
@@ -1,31 +1,67 @@
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use inflector::cases::camelcase::to_camel_case;
+use std::sync::Arc;
 use sxd_document::*;
 
+use super::choice::Choice;
+use super::constraint::{parse_musts, parse_when, Must, When};
+use super::error::ModelError;
+use super::scope::Scope;
 use super::util::*;
 
 #[derive(Debug, Clone)]
 pub struct Container {
     pub name: String,
-    pub children: HashMap<String, Model>,
+    pub yang_name: String,
+    pub module: Option<String>,
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub children: IndexMap<String, Model>,
+    pub choices: Vec<Arc<Choice>>,
+    /// Whether the container has a `presence` statement, without which it
+    /// only organizes its children and exists whenever they do.
+    pub presence: bool,
+    pub config: bool,
+    pub augmented_by: Option<String>,
+    pub when: Vec<When>,
+    pub musts: Vec<Must>,
 }
 
 impl Container {
-    pub fn new(el: dom::Element) -> Container {
-        Container {
-            name: get_name(el),
-            children: parse_children(el),
-        }
+    pub fn new<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Container, ModelError> {
+        let yang_name = get_raw_name(el, &scope.path())?;
+        let (children, choices) = parse_children(el, scope)?;
+
+        Ok(Container {
+            name: to_camel_case(&yang_name),
+            yang_name,
+            module: scope.module(),
+            namespace: scope.namespace(),
+            prefix: scope.prefix(),
+            children,
+            choices,
+            presence: scope.get_substatement(el, "presence").is_some(),
+            config: scope.config(),
+            augmented_by: None,
+            when: parse_when(el, false, &scope.path())?.into_iter().collect(),
+            musts: parse_musts(el, scope)?,
+        })
     }
 }
 
 impl WithChildren for Container {
-    fn get_children(&self) -> &HashMap<String, Model> {
+    fn get_children(&self) -> &IndexMap<String, Model> {
         &self.children
     }
+
+    fn get_choices(&self) -> &[Arc<Choice>] {
+        &self.choices
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::scope::Scope;
     use crate::model::util::*;
 
     const MODEL: &str = r#"<?xml version="1.0"?>
@@ -42,17 +78,135 @@ mod tests {
         </yin:leaf>
     </yin:container>"#;
 
+    const MODEL_WITH_USES: &str = r#"<?xml version="1.0"?>
+    <yin:container name="bgp" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:grouping name="timers">
+            <yin:grouping name="interval">
+                <yin:leaf name="interval">
+                    <yin:type name="uint32"/>
+                </yin:leaf>
+            </yin:grouping>
+            <yin:container name="timers">
+                <yin:uses name="interval"/>
+            </yin:container>
+            <yin:leaf name="hold-time">
+                <yin:type name="uint32"/>
+            </yin:leaf>
+        </yin:grouping>
+        <yin:uses name="timers">
+            <yin:augment target-node="timers">
+                <yin:leaf name="keepalive">
+                    <yin:type name="uint32"/>
+                </yin:leaf>
+            </yin:augment>
+        </yin:uses>
+    </yin:container>"#;
+
     #[test]
     fn it_parses_name() {
-        let pkg = get_package(MODEL);
-        let model = super::Container::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "bfd");
     }
 
     #[test]
     fn it_parses_children() {
-        let pkg = get_package(MODEL);
-        let model = super::Container::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.children.len(), 2);
+    }
+
+    #[test]
+    fn it_keeps_the_schema_order() {
+        let pkg = get_package(MODEL_WITH_USES).unwrap();
+        let model = super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        let names: Vec<&String> = model.children.keys().collect();
+        assert_eq!(names, vec!["timers", "hold-time"]);
+    }
+
+    #[test]
+    fn it_expands_uses() {
+        let pkg = get_package(MODEL_WITH_USES).unwrap();
+        let model = super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.children.len(), 2);
+        assert!(model.children.contains_key("hold-time"));
+
+        match model.get_child("timers") {
+            Some(Model::Container(c)) => {
+                assert!(c.children.contains_key("interval"));
+                assert!(c.children.contains_key("keepalive"));
+            }
+            _ => panic!("Expected the timers container."),
+        }
+    }
+
+    #[test]
+    fn it_reports_groupings_that_use_themselves() {
+        let parse_err = |groupings: &str| {
+            let xml = format!(
+                r#"<?xml version="1.0"?>
+                <yin:container name="foo" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                    {}
+                    <yin:uses name="a"/>
+                </yin:container>"#,
+                groupings
+            );
+            let pkg = get_package(&xml).unwrap();
+            super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err()
+        };
+
+        assert_eq!(
+            parse_err(r#"<yin:grouping name="a"><yin:uses name="a"/></yin:grouping>"#).to_string(),
+            "The grouping a refers to itself at /: a -> a."
+        );
+        assert_eq!(
+            parse_err(
+                r#"<yin:grouping name="a">
+                    <yin:container name="bar"><yin:uses name="b"/></yin:container>
+                </yin:grouping>
+                <yin:grouping name="b"><yin:uses name="a"/></yin:grouping>"#
+            )
+            .to_string(),
+            "The grouping a refers to itself at /bar: a -> b -> a."
+        );
+    }
+
+    #[test]
+    fn it_keeps_names_that_share_a_camel_case_name() {
+        let pkg = get_package(
+            r#"<?xml version="1.0"?>
+            <yin:container name="foo" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                <yin:container name="foo-bar"/>
+                <yin:choice name="baz">
+                    <yin:leaf name="foo_bar"><yin:type name="string"/></yin:leaf>
+                </yin:choice>
+            </yin:container>"#,
+        )
+        .unwrap();
+        let model = super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        let names: Vec<&String> = model.children.keys().collect();
+        assert_eq!(names, vec!["foo-bar", "foo_bar"]);
+
+        assert!(matches!(
+            model.get_child("foo-bar"),
+            Some(Model::Container(_))
+        ));
+        assert!(matches!(model.get_child("foo_bar"), Some(Model::Leaf(_))));
+        assert!(model.get_child("fooBar").is_none());
+        assert!(model
+            .get_choice("baz")
+            .unwrap()
+            .get_case("foo_bar")
+            .is_some());
+    }
+
+    #[test]
+    fn it_finds_children_by_yang_name() {
+        let pkg = get_package(MODEL_WITH_USES).unwrap();
+        let model = super::Container::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+
+        assert!(model.get_child("hold-time").is_some());
+        assert!(model.get_child("holdTime").is_some());
+        assert!(model.get_child("hold_time").is_none());
     }
 }
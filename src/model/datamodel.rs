@@ -1,43 +1,354 @@
+use indexmap::IndexMap;
+use std::collections::HashMap;
 use std::sync::Arc;
 use sxd_document::*;
 
+use super::choice::Choice;
 use super::container::Container;
+use super::error::ModelError;
+use super::leafref::resolve_targets;
+use super::scope::Scope;
+use super::util::*;
 
+/// The data tree defined by a set of modules. Its `root` is an unnamed
+/// container at the top of the tree, whose children are the top-level data
+/// nodes of every module, or just the one picked with `with_root`, keyed by
+/// their qualified names.
 #[derive(Debug)]
 pub struct DataModel {
     pub root: Arc<Container>,
+    /// The bases of every identity the modules define, by name qualified
+    /// with the name of its module, e.g. `iana-if-type:ethernetCsmacd`.
+    pub identities: HashMap<String, Vec<String>>,
+}
+
+fn get_modules(root_el: dom::Element) -> Vec<dom::Element> {
+    match root_el.name().local_part() {
+        "module" | "submodule" => vec![root_el],
+        _ => root_el
+            .children()
+            .into_iter()
+            .filter_map(|c| c.element())
+            .collect(),
+    }
+}
+
+fn parse_identities(modules: &[dom::Element]) -> Result<HashMap<String, Vec<String>>, ModelError> {
+    modules
+        .iter()
+        .flat_map(|m| get_yin_children(*m, "identity"))
+        .map(|e| {
+            let bases = get_yin_children(e, "base")
+                .into_iter()
+                .map(|b| qualify_identity(b, &get_raw_name(b, "/")?, "/"))
+                .collect::<Result<_, _>>()?;
+
+            Ok((qualify_identity(e, &get_raw_name(e, "/")?, "/")?, bases))
+        })
+        .collect()
+}
+
+fn new_root(children: IndexMap<String, Model>, choices: Vec<Arc<Choice>>) -> Container {
+    Container {
+        name: String::new(),
+        yang_name: String::new(),
+        module: None,
+        namespace: None,
+        prefix: None,
+        children,
+        choices,
+        presence: false,
+        config: true,
+        augmented_by: None,
+        when: Vec::new(),
+        musts: Vec::new(),
+    }
 }
 
 impl DataModel {
-    pub fn new(root_el: dom::Element) -> DataModel {
-        DataModel {
-            root: Arc::new(Container::new(root_el)),
+    /// Builds the data model from either a single module or the set of
+    /// modules loaded by a `ModuleLoader`.
+    pub fn new(root_el: dom::Element) -> Result<DataModel, ModelError> {
+        let modules = get_modules(root_el);
+
+        // Augments are applied as the tree is built, so they are gathered
+        // from every module before anything else.
+        let scope = modules
+            .iter()
+            .try_fold(Scope::default(), |scope, m| scope.with_augments(*m))?;
+
+        let mut children = IndexMap::new();
+        let mut choices = Vec::new();
+
+        for module_el in &modules {
+            let module_scope = scope.with_module(*module_el);
+            let (mut x, mut module_choices) = parse_children(*module_el, &module_scope)?;
+
+            // The root is in the namespace of no module.
+            qualify(&mut x, None);
+            for choice in &mut module_choices {
+                Arc::make_mut(choice).qualify(None);
+            }
+
+            children.extend(x);
+            choices.extend(module_choices);
         }
+
+        for module_el in &modules {
+            scope.check_augments(*module_el)?;
+        }
+
+        Ok(DataModel {
+            root: resolve_targets(Arc::new(new_root(children, choices))),
+            identities: parse_identities(&modules)?,
+        })
+    }
+
+    /// Returns a data model that only contains the named top-level node.
+    pub fn with_root(&self, name: &str) -> Option<DataModel> {
+        let (key, child) = self.root.get_child_entry(name)?;
+        let mut children = IndexMap::new();
+        children.insert(key.clone(), child.clone());
+
+        Some(DataModel {
+            root: Arc::new(new_root(children, Vec::new())),
+            identities: self.identities.clone(),
+        })
+    }
+
+    /// Returns the identities that `identity` is derived from, directly or
+    /// through other identities. All are qualified with their module name.
+    pub fn get_bases(&self, identity: &str) -> Vec<String> {
+        let mut bases: Vec<String> = Vec::new();
+        let mut pending = vec![identity.to_string()];
+
+        while let Some(name) = pending.pop() {
+            for base in self.identities.get(&name).into_iter().flatten() {
+                if !bases.contains(base) {
+                    bases.push(base.clone());
+                    pending.push(base.clone());
+                }
+            }
+        }
+
+        bases
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::error::ModelError;
     use crate::model::util::*;
 
     const DATA_MODEL: &str = r#"<?xml version="1.0"?>
-    <yin:container name="root" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
-        <yin:leaf name="state">
-            <yin:type name="enumeration">
-                <yin:enum name="enabled"/>
-                <yin:enum name="disabled"/>
-            </yin:type>
-            <yin:default value="enabled"/>
-        </yin:leaf>
-        <yin:leaf name="desired-tx-interval">
-            <yin:type name="uint32"/>
-        </yin:leaf>
-    </yin:container>"#;
+    <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:container name="root">
+            <yin:leaf name="state">
+                <yin:type name="enumeration">
+                    <yin:enum name="enabled"/>
+                    <yin:enum name="disabled"/>
+                </yin:type>
+                <yin:default value="enabled"/>
+            </yin:leaf>
+            <yin:leaf name="desired-tx-interval">
+                <yin:type name="uint32"/>
+            </yin:leaf>
+        </yin:container>
+        <yin:list name="other-root">
+            <yin:key value="name"/>
+            <yin:leaf name="name">
+                <yin:type name="string"/>
+            </yin:leaf>
+        </yin:list>
+    </yin:module>"#;
+
+    fn parse_err(body: &str) -> ModelError {
+        let xml = format!(
+            r#"<?xml version="1.0"?>
+            <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">{}</yin:module>"#,
+            body
+        );
+        let pkg = get_package(&xml).unwrap();
+        super::DataModel::new(get_root_el(&pkg).unwrap()).unwrap_err()
+    }
 
     #[test]
     fn it_parses() {
-        let pkg = get_package(DATA_MODEL);
-        let model = super::DataModel::new(get_root_el(&pkg));
-        assert_eq!(model.root.name, "root");
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let model = super::DataModel::new(get_root_el(&pkg).unwrap()).unwrap();
+        assert_eq!(model.root.name, "");
+        assert_eq!(model.root.children.len(), 2);
+        assert!(model.root.get_child("root").is_some());
+        assert!(model.root.get_child("other-root").is_some());
+    }
+
+    #[test]
+    fn it_picks_a_root() {
+        let pkg = get_package(DATA_MODEL).unwrap();
+        let model = super::DataModel::new(get_root_el(&pkg).unwrap())
+            .unwrap()
+            .with_root("other-root")
+            .unwrap();
+        assert_eq!(model.root.children.len(), 1);
+        assert!(model.root.get_child("otherRoot").is_some());
+    }
+
+    const MODULES: &str = r#"<?xml version="1.0"?>
+    <module-set>
+        <yin:module name="a" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:namespace uri="urn:example:a"/>
+            <yin:prefix value="a"/>
+            <yin:container name="system">
+                <yin:leaf name="enabled">
+                    <yin:type name="boolean"/>
+                </yin:leaf>
+            </yin:container>
+        </yin:module>
+        <yin:module name="b" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:namespace uri="urn:example:b"/>
+            <yin:prefix value="b"/>
+            <yin:import module="a">
+                <yin:prefix value="a"/>
+            </yin:import>
+            <yin:container name="system"/>
+            <yin:container name="logging"/>
+            <yin:augment target-node="/a:system">
+                <yin:leaf name="enabled">
+                    <yin:type name="boolean"/>
+                </yin:leaf>
+            </yin:augment>
+        </yin:module>
+    </module-set>"#;
+
+    #[test]
+    fn it_keys_nodes_from_other_modules_by_qualified_name() {
+        let pkg = get_package(MODULES).unwrap();
+        let model = super::DataModel::new(get_root_el(&pkg).unwrap()).unwrap();
+        let mut keys: Vec<&String> = model.root.children.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["a:system", "b:logging", "b:system"]);
+
+        let logging = model.with_root("logging").unwrap();
+        let keys: Vec<&String> = logging.root.children.keys().collect();
+        assert_eq!(keys, vec!["b:logging"]);
+
+        let system = match model.root.get_child("a:system") {
+            Some(Model::Container(x)) => x,
+            _ => panic!("Expected the system container."),
+        };
+        assert_eq!(system.namespace.as_deref(), Some("urn:example:a"));
+        assert_eq!(system.prefix.as_deref(), Some("a"));
+
+        let mut keys: Vec<&String> = system.children.keys().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["b:enabled", "enabled"]);
+
+        let enabled = system.get_child("b:enabled").unwrap();
+        assert_eq!(enabled.get_module(), Some("b"));
+        assert_eq!(enabled.get_namespace(), Some("urn:example:b"));
+        assert_eq!(enabled.get_prefix(), Some("b"));
+        assert_eq!(enabled.get_qualified_name(), "b:enabled");
+        assert_eq!(system.get_child("enabled").unwrap().get_module(), Some("a"));
+    }
+
+    #[test]
+    fn it_parses_identities() {
+        let pkg = get_package(
+            r#"<?xml version="1.0"?>
+            <module-set>
+                <yin:module name="a" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                    <yin:prefix value="a"/>
+                    <yin:identity name="if-type"/>
+                    <yin:identity name="ethernet">
+                        <yin:base name="a:if-type"/>
+                    </yin:identity>
+                </yin:module>
+                <yin:module name="b" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                    <yin:prefix value="b"/>
+                    <yin:import module="a">
+                        <yin:prefix value="x"/>
+                    </yin:import>
+                    <yin:identity name="ethernet">
+                        <yin:base name="x:ethernet"/>
+                    </yin:identity>
+                    <yin:identity name="fast-ethernet">
+                        <yin:base name="ethernet"/>
+                    </yin:identity>
+                </yin:module>
+            </module-set>"#,
+        )
+        .unwrap();
+        let model = super::DataModel::new(get_root_el(&pkg).unwrap()).unwrap();
+        assert_eq!(
+            model.get_bases("b:fast-ethernet"),
+            vec!["b:ethernet", "a:ethernet", "a:if-type"]
+        );
+        assert_eq!(model.get_bases("a:ethernet"), vec!["a:if-type"]);
+        assert!(model.get_bases("a:if-type").is_empty());
+    }
+
+    #[test]
+    fn it_reports_unknown_statements() {
+        let error =
+            parse_err(r#"<yin:container name="foo"><yin:leef name="bar"/></yin:container>"#);
+        assert_eq!(
+            error,
+            ModelError::UnknownStatement {
+                path: "/foo".to_string(),
+                statement: "leef".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn it_reports_unresolved_references() {
+        let error =
+            parse_err(r#"<yin:container name="foo"><yin:uses name="bar"/></yin:container>"#);
+        assert_eq!(error.to_string(), "Could not resolve the uses bar at /foo.");
+
+        let error = parse_err(
+            r#"<yin:container name="foo"/>
+            <yin:augment target-node="/foo/bar">
+                <yin:leaf name="baz">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:augment>"#,
+        );
+        assert_eq!(error.path(), Some("/"));
+        assert_eq!(
+            error.to_string(),
+            "Could not resolve the augment /foo/bar at /."
+        );
+    }
+
+    #[test]
+    fn it_reports_reference_cycles() {
+        let error = parse_err(
+            r#"<yin:typedef name="a"><yin:type name="b"/></yin:typedef>
+            <yin:typedef name="b"><yin:type name="a"/></yin:typedef>
+            <yin:leaf name="foo"><yin:type name="a"/></yin:leaf>"#,
+        );
+        assert_eq!(
+            error.to_string(),
+            "The typedef a refers to itself at /foo: a -> b -> a."
+        );
+
+        let error = parse_err(
+            r#"<yin:grouping name="a"><yin:uses name="a"/></yin:grouping>
+            <yin:container name="foo"><yin:uses name="a"/></yin:container>"#,
+        );
+        assert_eq!(
+            error.to_string(),
+            "The grouping a refers to itself at /foo: a -> a."
+        );
+    }
+
+    #[test]
+    fn it_reports_missing_attributes() {
+        let error = parse_err(r#"<yin:container name="foo"><yin:leaf/></yin:container>"#);
+        assert_eq!(
+            error.to_string(),
+            "The leaf statement at /foo has no name attribute."
+        );
     }
 }
@@ -0,0 +1,172 @@
+use std::error::Error;
+use std::fmt;
+
+/// An error found while loading modules or building a data model from them.
+/// Errors in the schema itself carry the `path` of the schema node they were
+/// found at, made of the YANG names of the nodes down from the top of the
+/// data tree, e.g. `/authority/router/name`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelError {
+    /// A module file could not be read.
+    Io { file: String, message: String },
+    /// A document is not well-formed XML, or has no root element.
+    Xml {
+        file: Option<String>,
+        message: String,
+    },
+    /// An XPath expression is invalid or selects no element.
+    XPath { expression: String, message: String },
+    /// No file for the module (or the requested revision of it) was found in
+    /// any of the search paths.
+    ModuleNotFound {
+        name: String,
+        revision: Option<String>,
+    },
+    /// The file found for a module does not define a module of that name.
+    ModuleNameMismatch { expected: String, found: String },
+    /// The modules import or include one another in a cycle.
+    ModuleCycle(Vec<String>),
+    /// A statement lacks the attribute holding its argument.
+    MissingAttribute {
+        path: String,
+        statement: String,
+        attribute: String,
+    },
+    /// A statement lacks a substatement it requires, such as the `type` of a
+    /// `leaf`.
+    MissingStatement {
+        path: String,
+        statement: String,
+        substatement: String,
+    },
+    /// A list has no `key`, or one of its keys is not a leaf of the list.
+    MissingKey { path: String, key: Option<String> },
+    /// A statement in the YIN namespace that is not a YANG keyword.
+    UnknownStatement { path: String, statement: String },
+    /// A `type`, `uses` or `augment` refers to a typedef, grouping or schema
+    /// node that cannot be found.
+    UnresolvedReference {
+        path: String,
+        statement: String,
+        name: String,
+    },
+    /// A typedef or grouping refers to itself, directly or through the other
+    /// `names` it refers to, which ends with the first one again.
+    ReferenceCycle {
+        path: String,
+        statement: String,
+        names: Vec<String>,
+    },
+    /// The argument of a statement is not valid for it, such as a `range`
+    /// outside of its type's value space.
+    InvalidArgument {
+        path: String,
+        statement: String,
+        value: String,
+    },
+}
+
+impl ModelError {
+    /// The schema path of the node the error was found at, if the error is
+    /// in the schema itself.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            ModelError::MissingAttribute { path, .. }
+            | ModelError::MissingStatement { path, .. }
+            | ModelError::MissingKey { path, .. }
+            | ModelError::UnknownStatement { path, .. }
+            | ModelError::UnresolvedReference { path, .. }
+            | ModelError::ReferenceCycle { path, .. }
+            | ModelError::InvalidArgument { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::Io { file, message } => write!(f, "Could not read {}: {}", file, message),
+            ModelError::Xml {
+                file: Some(file),
+                message,
+            } => write!(f, "Could not parse XML in {}: {}", file, message),
+            ModelError::Xml { message, .. } => write!(f, "Could not parse XML: {}", message),
+            ModelError::XPath {
+                expression,
+                message,
+            } => write!(f, "Could not evaluate {}: {}", expression, message),
+            ModelError::ModuleNotFound {
+                name,
+                revision: Some(r),
+            } => write!(f, "Could not find module {}@{}.", name, r),
+            ModelError::ModuleNotFound { name, .. } => {
+                write!(f, "Could not find module {}.", name)
+            }
+            ModelError::ModuleNameMismatch { expected, found } => write!(
+                f,
+                "Expected module {} but the file defines {}.",
+                expected, found
+            ),
+            ModelError::ModuleCycle(x) => {
+                write!(f, "Modules depend on each other: {}.", x.join(" -> "))
+            }
+            ModelError::MissingAttribute {
+                path,
+                statement,
+                attribute,
+            } => write!(
+                f,
+                "The {} statement at {} has no {} attribute.",
+                statement, path, attribute
+            ),
+            ModelError::MissingStatement {
+                path,
+                statement,
+                substatement,
+            } => write!(
+                f,
+                "The {} statement at {} has no {} statement.",
+                statement, path, substatement
+            ),
+            ModelError::MissingKey { path, key: None } => {
+                write!(f, "The list at {} has no key.", path)
+            }
+            ModelError::MissingKey {
+                path,
+                key: Some(key),
+            } => write!(f, "The list at {} has no key leaf {}.", path, key),
+            ModelError::UnknownStatement { path, statement } => {
+                write!(f, "Unknown statement {} at {}.", statement, path)
+            }
+            ModelError::UnresolvedReference {
+                path,
+                statement,
+                name,
+            } => write!(
+                f,
+                "Could not resolve the {} {} at {}.",
+                statement, name, path
+            ),
+            ModelError::ReferenceCycle {
+                path,
+                statement,
+                names,
+            } => write!(
+                f,
+                "The {} {} refers to itself at {}: {}.",
+                statement,
+                names[0],
+                path,
+                names.join(" -> ")
+            ),
+            ModelError::InvalidArgument {
+                path,
+                statement,
+                value,
+            } => write!(f, "Invalid {} {} at {}.", statement, value, path),
+        }
+    }
+}
+
+impl Error for ModelError {}
@@ -1,20 +1,62 @@
+use inflector::cases::camelcase::to_camel_case;
 use sxd_document::*;
 
+use super::constraint::{parse_musts, parse_when, Must, When};
+use super::error::ModelError;
+use super::leaftype::LeafType;
+use super::scope::Scope;
 use super::util::*;
 
 #[derive(Debug, Clone)]
 pub struct Leaf {
     pub name: String,
+    pub yang_name: String,
+    pub module: Option<String>,
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub leaf_type: LeafType,
+    pub mandatory: bool,
+    pub config: bool,
+    /// The value the leaf has when it has no data, from its `default` or
+    /// that of its type. Mandatory leaves have none.
+    pub default: Option<String>,
+    pub augmented_by: Option<String>,
+    pub when: Vec<When>,
+    pub musts: Vec<Must>,
 }
 
 impl Leaf {
-    pub fn new(el: dom::Element) -> Leaf {
-        Leaf { name: get_name(el) }
+    pub fn new<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Leaf, ModelError> {
+        let path = scope.path();
+        let type_el = get_required_child(el, "type", &path)?;
+        let yang_name = get_raw_name(el, &path)?;
+        let leaf_type = LeafType::new(type_el, &path)?;
+        let mandatory = parse_mandatory(el, scope)?;
+        let default = match mandatory {
+            true => None,
+            false => parse_defaults(el, scope, &leaf_type)?.into_iter().next(),
+        };
+
+        Ok(Leaf {
+            name: to_camel_case(&yang_name),
+            yang_name,
+            module: scope.module(),
+            namespace: scope.namespace(),
+            prefix: scope.prefix(),
+            leaf_type,
+            mandatory,
+            config: scope.config(),
+            default,
+            augmented_by: None,
+            when: parse_when(el, false, &path)?.into_iter().collect(),
+            musts: parse_musts(el, scope)?,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::scope::Scope;
     use crate::model::util::*;
 
     const MODEL: &str = r#"<?xml version="1.0"?>
@@ -24,8 +66,60 @@ mod tests {
 
     #[test]
     fn it_parses_name() {
-        let pkg = get_package(MODEL);
-        let model = super::Leaf::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "foo");
     }
+
+    #[test]
+    fn it_parses_type() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.leaf_type.name(), "string");
+    }
+
+    #[test]
+    fn it_parses_default() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.default, None);
+
+        let xml = MODEL.replace(
+            r#"<yin:type name="string"/>"#,
+            r#"<yin:type name="string"/><yin:default value="bar"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.default, Some("bar".to_string()));
+    }
+
+    #[test]
+    fn it_rejects_invalid_defaults() {
+        let parse_err = |statements: &str| {
+            let xml = MODEL.replace(r#"<yin:type name="string"/>"#, statements);
+            let pkg = get_package(&xml).unwrap();
+            super::Leaf::new(get_root_el(&pkg).unwrap(), &Scope::default())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            parse_err(r#"<yin:type name="uint8"/><yin:default value="300"/>"#),
+            "Invalid default 300 at /."
+        );
+        assert_eq!(
+            parse_err(
+                r#"<yin:type name="string"><yin:length value="1..3"/></yin:type>
+                <yin:default value="abcd"/>"#
+            ),
+            "Invalid default abcd at /."
+        );
+        assert_eq!(
+            parse_err(
+                r#"<yin:type name="enumeration"><yin:enum name="up"/></yin:type>
+                <yin:default value="down"/>"#
+            ),
+            "Invalid default down at /."
+        );
+    }
 }
@@ -1,20 +1,67 @@
+use inflector::cases::camelcase::to_camel_case;
 use sxd_document::*;
 
+use super::constraint::{parse_musts, parse_when, Must, When};
+use super::error::ModelError;
+use super::leaftype::LeafType;
+use super::scope::Scope;
 use super::util::*;
 
 #[derive(Debug, Clone)]
 pub struct LeafList {
     pub name: String,
+    pub yang_name: String,
+    pub module: Option<String>,
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub leaf_type: LeafType,
+    pub config: bool,
+    /// The values the leaf-list has when it has no entries, from its
+    /// `default` statements or that of its type. A leaf-list that requires
+    /// entries has none.
+    pub defaults: Vec<String>,
+    pub min_elements: u64,
+    pub max_elements: Option<u64>,
+    pub ordered_by_user: bool,
+    pub augmented_by: Option<String>,
+    pub when: Vec<When>,
+    pub musts: Vec<Must>,
 }
 
 impl LeafList {
-    pub fn new(el: dom::Element) -> LeafList {
-        LeafList { name: get_name(el) }
+    pub fn new<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<LeafList, ModelError> {
+        let path = scope.path();
+        let type_el = get_required_child(el, "type", &path)?;
+        let yang_name = get_raw_name(el, &path)?;
+        let (min_elements, max_elements) = parse_elements(el, scope)?;
+        let leaf_type = LeafType::new(type_el, &path)?;
+        let defaults = match min_elements {
+            0 => parse_defaults(el, scope, &leaf_type)?,
+            _ => Vec::new(),
+        };
+
+        Ok(LeafList {
+            name: to_camel_case(&yang_name),
+            yang_name,
+            module: scope.module(),
+            namespace: scope.namespace(),
+            prefix: scope.prefix(),
+            leaf_type,
+            config: scope.config(),
+            defaults,
+            min_elements,
+            max_elements,
+            ordered_by_user: parse_ordered_by(el, &path)?,
+            augmented_by: None,
+            when: parse_when(el, false, &path)?.into_iter().collect(),
+            musts: parse_musts(el, scope)?,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::scope::Scope;
     use crate::model::util::*;
 
     const MODEL: &str = r#"<?xml version="1.0"?>
@@ -24,8 +71,49 @@ mod tests {
 
     #[test]
     fn it_parses_name() {
-        let pkg = get_package(MODEL);
-        let model = super::LeafList::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::LeafList::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "foo");
     }
+
+    #[test]
+    fn it_parses_type() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::LeafList::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.leaf_type.name(), "string");
+    }
+
+    #[test]
+    fn it_parses_defaults() {
+        let xml = MODEL.replace(
+            r#"<yin:type name="string"/>"#,
+            r#"<yin:type name="string"/><yin:default value="a"/><yin:default value="b"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::LeafList::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.defaults, vec!["a", "b"]);
+
+        let xml = xml.replace(
+            "<yin:default",
+            r#"<yin:min-elements value="1"/><yin:default"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::LeafList::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.defaults.is_empty());
+    }
+
+    #[test]
+    fn it_parses_ordered_by() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::LeafList::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(!model.ordered_by_user);
+
+        let xml = MODEL.replace(
+            r#"<yin:type name="string"/>"#,
+            r#"<yin:type name="string"/><yin:ordered-by value="user"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::LeafList::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.ordered_by_user);
+    }
 }
@@ -0,0 +1,470 @@
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use super::case::Case;
+use super::choice::Choice;
+use super::container::Container;
+use super::leaftype::{BuiltinType, LeafType};
+use super::list::List;
+use super::util::{split_prefix, Model, WithChildren};
+
+/// Where the steps of a leafref path start from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathStart {
+    /// The top of the data tree, for an absolute path.
+    Root,
+    /// The leaf holding the reference, from which `up` `..` steps are taken.
+    Current { up: usize },
+    /// The targets of the leafref selected by `path`, as with `deref()`, from
+    /// which `up` `..` steps are taken.
+    Deref { path: Box<LeafrefPath>, up: usize },
+}
+
+/// A `[key = current()/../x]` predicate that selects the entries of a list
+/// whose `key` equals the leaf found by going `up` and then down `steps`
+/// from the leaf holding the reference.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathPredicate {
+    pub key: String,
+    pub up: usize,
+    pub steps: Vec<String>,
+}
+
+/// A step down to the child named `name`, which is the camelCase form of
+/// the name in the path, without its prefix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathStep {
+    pub name: String,
+    pub predicates: Vec<PathPredicate>,
+}
+
+/// The parsed argument of a leafref's `path` statement (RFC 7950 section
+/// 9.9.2).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafrefPath {
+    pub start: PathStart,
+    pub steps: Vec<PathStep>,
+    text: String,
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+fn parse_node_identifier(s: &str) -> Option<String> {
+    let (prefix, name) = split_prefix(s.trim());
+
+    if prefix.is_some_and(|p| !is_identifier(p)) || !is_identifier(name) {
+        return None;
+    }
+
+    Some(name.to_string())
+}
+
+/// Splits `s` at each `/` that is not within brackets or parentheses.
+fn split_steps(s: &str) -> Vec<&str> {
+    let mut steps = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            '/' if depth == 0 => {
+                steps.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    steps.push(&s[start..]);
+    steps
+}
+
+/// Returns the number of leading `..` steps and the steps that follow them.
+fn count_up<'a>(steps: &[&'a str]) -> (usize, Vec<&'a str>) {
+    let up = steps.iter().take_while(|s| s.trim() == "..").count();
+
+    (up, steps[up..].to_vec())
+}
+
+fn parse_predicate(s: &str) -> Option<PathPredicate> {
+    let (key, expr) = s.split_once('=')?;
+    let expr = expr.trim().strip_prefix("current")?.trim_start();
+    let expr = expr.strip_prefix('(')?.trim_start().strip_prefix(')')?;
+    let expr = expr.trim_start().strip_prefix('/')?;
+
+    let (up, steps) = count_up(&split_steps(expr));
+    if up == 0 || steps.is_empty() {
+        return None;
+    }
+
+    Some(PathPredicate {
+        key: parse_node_identifier(key)?,
+        up,
+        steps: steps
+            .into_iter()
+            .map(parse_node_identifier)
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn parse_step(s: &str) -> Option<PathStep> {
+    let (name, mut rest) = match s.find('[') {
+        Some(i) => (&s[..i], &s[i..]),
+        None => (s, ""),
+    };
+    let mut predicates = Vec::new();
+
+    while !rest.is_empty() {
+        let body = rest.strip_prefix('[')?;
+        let end = body.find(']')?;
+        predicates.push(parse_predicate(&body[..end])?);
+        rest = body[end + 1..].trim_start();
+    }
+
+    Some(PathStep {
+        name: parse_node_identifier(name)?,
+        predicates,
+    })
+}
+
+fn parse_steps(steps: Vec<&str>) -> Option<Vec<PathStep>> {
+    if steps.is_empty() {
+        return None;
+    }
+
+    steps.into_iter().map(parse_step).collect()
+}
+
+/// Parses `../..` steps followed by a descendant path.
+fn parse_relative(s: &str) -> Option<(usize, Vec<PathStep>)> {
+    let (up, steps) = count_up(&split_steps(s));
+
+    if up == 0 {
+        return None;
+    }
+
+    Some((up, parse_steps(steps)?))
+}
+
+impl LeafrefPath {
+    /// Parses an absolute path, a relative path or a path starting with
+    /// `deref()`, returning `None` if it is not valid.
+    pub fn parse(s: &str) -> Option<LeafrefPath> {
+        let text = s.trim();
+
+        let (start, steps) = if let Some(rest) = text.strip_prefix("deref(") {
+            let end = rest.char_indices().try_fold(1, |depth, (i, c)| match c {
+                '(' => Ok(depth + 1),
+                ')' if depth == 1 => Err(i),
+                ')' => Ok(depth - 1),
+                _ => Ok(depth),
+            });
+            let end = end.err()?;
+            let (up, steps) = parse_relative(rest[end + 1..].trim().strip_prefix('/')?)?;
+            let path = Box::new(LeafrefPath::parse(&rest[..end])?);
+
+            (PathStart::Deref { path, up }, steps)
+        } else if let Some(rest) = text.strip_prefix('/') {
+            (PathStart::Root, parse_steps(split_steps(rest))?)
+        } else {
+            let (up, steps) = parse_relative(text)?;
+
+            (PathStart::Current { up }, steps)
+        };
+
+        Some(LeafrefPath {
+            start,
+            steps,
+            text: text.to_string(),
+        })
+    }
+}
+
+/// Formats the path as it was written.
+impl fmt::Display for LeafrefPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+fn get_schema_child(model: &Model, name: &str) -> Option<Model> {
+    match model {
+        Model::Container(x) => x.get_child(name).cloned(),
+        Model::List(x) => x.get_child(name).cloned(),
+        _ => None,
+    }
+}
+
+fn get_leaf_type(model: &Model) -> Option<&LeafType> {
+    match model {
+        Model::Leaf(x) => Some(&x.leaf_type),
+        Model::LeafList(x) => Some(&x.leaf_type),
+        _ => None,
+    }
+}
+
+/// The address of the node a model refers to, which identifies it however
+/// many times it is shared.
+fn get_address(model: &Model) -> usize {
+    match model {
+        Model::Leaf(x) => Arc::as_ptr(x) as usize,
+        Model::LeafList(x) => Arc::as_ptr(x) as usize,
+        Model::Container(x) => Arc::as_ptr(x) as usize,
+        Model::List(x) => Arc::as_ptr(x) as usize,
+    }
+}
+
+/// Returns the leaf or leaf-list that `path` selects from a node whose
+/// ancestors are `ancestors`, along with the target's own ancestors.
+fn find_target(path: &LeafrefPath, ancestors: &[Model]) -> Option<(Vec<Model>, Model)> {
+    let mut nodes: Vec<Model> = match &path.start {
+        PathStart::Root => vec![ancestors.first()?.clone()],
+        PathStart::Current { up } => ancestors[..ancestors.len().checked_sub(up - 1)?].to_vec(),
+        PathStart::Deref { path, up } => {
+            let (inner_ancestors, inner) = find_target(path, ancestors)?;
+            let inner_path = match &get_leaf_type(&inner)?.builtin {
+                BuiltinType::Leafref { path, .. } => path,
+                _ => return None,
+            };
+            let (target_ancestors, _) = find_target(inner_path, &inner_ancestors)?;
+
+            target_ancestors[..target_ancestors.len().checked_sub(up - 1)?].to_vec()
+        }
+    };
+
+    let (last, steps) = path.steps.split_last()?;
+
+    for step in steps {
+        let child = get_schema_child(nodes.last()?, &step.name)?;
+        nodes.push(child);
+    }
+
+    let target = get_schema_child(nodes.last()?, &last.name)?;
+    get_leaf_type(&target)?;
+
+    Some((nodes, target))
+}
+
+/// Fills in the types of the targets of the leafrefs in `leaf_type`, of a
+/// node whose ancestors are `ancestors`. `visited` holds the leafrefs being
+/// followed, which may refer to one another in a cycle.
+fn fill_targets(leaf_type: &mut LeafType, ancestors: &[Model], visited: &mut Vec<usize>) {
+    match &mut leaf_type.builtin {
+        BuiltinType::Leafref { path, target, .. } => {
+            *target = find_target(path, ancestors).and_then(|(target_ancestors, model)| {
+                let address = get_address(&model);
+                if visited.contains(&address) {
+                    return None;
+                }
+
+                let mut target_type = get_leaf_type(&model)?.clone();
+
+                visited.push(address);
+                fill_targets(&mut target_type, &target_ancestors, visited);
+                visited.pop();
+
+                Some(Box::new(target_type))
+            });
+        }
+        BuiltinType::Union(members) => {
+            for member in members {
+                fill_targets(member, ancestors, visited);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Whether `leaf_type` is a leafref, or a union with a leafref member.
+pub fn has_leafrefs(leaf_type: &LeafType) -> bool {
+    match &leaf_type.builtin {
+        BuiltinType::Leafref { .. } => true,
+        BuiltinType::Union(members) => members.iter().any(has_leafrefs),
+        _ => false,
+    }
+}
+
+/// Rebuilds the schema tree below the data nodes `ancestors` with the types
+/// of the targets of its leafrefs filled in. The nodes that choices share
+/// with the node they are in are rebuilt once, keyed by their address in
+/// `resolved`.
+#[derive(Default)]
+struct Resolver {
+    ancestors: Vec<Model>,
+    resolved: HashMap<usize, Model>,
+}
+
+impl Resolver {
+    fn resolve_children(&mut self, children: &IndexMap<String, Model>) -> IndexMap<String, Model> {
+        children
+            .iter()
+            .map(|(k, m)| (k.clone(), self.resolve(m)))
+            .collect()
+    }
+
+    fn resolve_choices(&mut self, choices: &[Arc<Choice>]) -> Vec<Arc<Choice>> {
+        choices
+            .iter()
+            .map(|c| {
+                Arc::new(Choice {
+                    children: self.resolve_children(&c.children),
+                    cases: c
+                        .cases
+                        .iter()
+                        .map(|x| Case {
+                            children: self.resolve_children(&x.children),
+                            choices: self.resolve_choices(&x.choices),
+                            ..x.clone()
+                        })
+                        .collect(),
+                    ..(**c).clone()
+                })
+            })
+            .collect()
+    }
+
+    fn resolve(&mut self, model: &Model) -> Model {
+        let address = get_address(model);
+        if let Some(x) = self.resolved.get(&address) {
+            return x.clone();
+        }
+
+        let resolved = match model {
+            Model::Leaf(x) if has_leafrefs(&x.leaf_type) => {
+                let mut leaf = (**x).clone();
+                fill_targets(&mut leaf.leaf_type, &self.ancestors, &mut Vec::new());
+                Model::Leaf(Arc::new(leaf))
+            }
+            Model::LeafList(x) if has_leafrefs(&x.leaf_type) => {
+                let mut leaf_list = (**x).clone();
+                fill_targets(&mut leaf_list.leaf_type, &self.ancestors, &mut Vec::new());
+                Model::LeafList(Arc::new(leaf_list))
+            }
+            Model::Container(x) => {
+                self.ancestors.push(model.clone());
+                let container = Container {
+                    children: self.resolve_children(&x.children),
+                    choices: self.resolve_choices(&x.choices),
+                    ..(**x).clone()
+                };
+                self.ancestors.pop();
+                Model::Container(Arc::new(container))
+            }
+            Model::List(x) => {
+                self.ancestors.push(model.clone());
+                let list = List {
+                    children: self.resolve_children(&x.children),
+                    choices: self.resolve_choices(&x.choices),
+                    ..(**x).clone()
+                };
+                self.ancestors.pop();
+                Model::List(Arc::new(list))
+            }
+            x => x.clone(),
+        };
+
+        self.resolved.insert(address, resolved.clone());
+        resolved
+    }
+}
+
+/// Returns the schema tree of `root` with the type of the node each leafref
+/// refers to filled in, as found from where the leafref is. Their values
+/// are then parsed and serialized as those of their targets.
+pub fn resolve_targets(root: Arc<Container>) -> Arc<Container> {
+    match Resolver::default().resolve(&Model::Container(root.clone())) {
+        Model::Container(x) => x,
+        _ => root,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(name: &str) -> PathStep {
+        PathStep {
+            name: name.to_string(),
+            predicates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_parses_absolute_paths() {
+        let path = LeafrefPath::parse("/t:authority/t:tenant/t:name").unwrap();
+
+        assert_eq!(path.start, PathStart::Root);
+        assert_eq!(
+            path.steps,
+            vec![step("authority"), step("tenant"), step("name")]
+        );
+        assert_eq!(path.to_string(), "/t:authority/t:tenant/t:name");
+    }
+
+    #[test]
+    fn it_parses_relative_paths() {
+        let path = LeafrefPath::parse("../../service-route/next-hop").unwrap();
+
+        assert_eq!(path.start, PathStart::Current { up: 2 });
+        assert_eq!(path.steps, vec![step("service-route"), step("next-hop")]);
+    }
+
+    #[test]
+    fn it_parses_predicates() {
+        let path = LeafrefPath::parse(
+            "/router[name = current()/../../router-name]/node[ name=current()/../node ]/name",
+        )
+        .unwrap();
+
+        assert_eq!(
+            path.steps[0].predicates,
+            vec![PathPredicate {
+                key: "name".to_string(),
+                up: 2,
+                steps: vec!["router-name".to_string()],
+            }]
+        );
+        assert_eq!(path.steps[1].predicates[0].up, 1);
+        assert_eq!(path.steps[2], step("name"));
+    }
+
+    #[test]
+    fn it_parses_deref() {
+        let path = LeafrefPath::parse("deref(../interface)/../type").unwrap();
+
+        assert_eq!(
+            path.start,
+            PathStart::Deref {
+                path: Box::new(LeafrefPath::parse("../interface").unwrap()),
+                up: 1,
+            }
+        );
+        assert_eq!(path.steps, vec![step("type")]);
+    }
+
+    #[test]
+    fn it_rejects_invalid_paths() {
+        for path in [
+            "",
+            "name",
+            "/",
+            "../",
+            "/a[b = ../c]/d",
+            "/a[b = current()/c]",
+            "/a b",
+        ] {
+            assert_eq!(LeafrefPath::parse(path), None, "{}", path);
+        }
+    }
+}
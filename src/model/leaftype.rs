@@ -0,0 +1,1305 @@
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use sxd_document::*;
+
+use super::error::ModelError;
+use super::leafref::LeafrefPath;
+use super::util::*;
+
+const MAX_FRACTION_DIGITS: u8 = 18;
+const MAX_INTEGER_DIGITS: usize = 20;
+
+/// An exact decimal number, used for range and length bounds as well as
+/// `decimal64` values.
+#[derive(Debug, Clone, Copy)]
+pub struct Number {
+    value: i128,
+    fraction_digits: u8,
+}
+
+impl Number {
+    pub fn new(value: i128, fraction_digits: u8) -> Number {
+        Number {
+            value,
+            fraction_digits,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Number> {
+        let s = s.trim();
+        let (negative, unsigned) = match s.strip_prefix('-') {
+            Some(x) => (true, x),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (integer, fraction) = match unsigned.find('.') {
+            Some(i) => (&unsigned[..i], &unsigned[i + 1..]),
+            None => (unsigned, ""),
+        };
+
+        let all_digits = |x: &str| x.chars().all(|c| c.is_ascii_digit());
+        if integer.is_empty()
+            || integer.len() > MAX_INTEGER_DIGITS
+            || fraction.len() > MAX_FRACTION_DIGITS as usize
+            || !all_digits(integer)
+            || !all_digits(fraction)
+            || (unsigned.contains('.') && fraction.is_empty())
+        {
+            return None;
+        }
+
+        let magnitude: i128 = format!("{}{}", integer, fraction).parse().ok()?;
+
+        Some(Number {
+            value: if negative { -magnitude } else { magnitude },
+            fraction_digits: fraction.len() as u8,
+        })
+    }
+
+    /// The unscaled value, i.e. `12.5` with one fraction digit is `125`.
+    pub fn value(&self) -> i128 {
+        self.value
+    }
+
+    pub fn fraction_digits(&self) -> u8 {
+        self.fraction_digits
+    }
+
+    /// Returns the value scaled to `fraction_digits`, or `None` if that would
+    /// lose precision.
+    pub fn rescale(&self, fraction_digits: u8) -> Option<i128> {
+        if fraction_digits >= self.fraction_digits {
+            let factor = 10i128.pow(u32::from(fraction_digits - self.fraction_digits));
+            self.value.checked_mul(factor)
+        } else {
+            let factor = 10i128.pow(u32::from(self.fraction_digits - fraction_digits));
+            if self.value % factor == 0 {
+                Some(self.value / factor)
+            } else {
+                None
+            }
+        }
+    }
+
+    fn normalized(&self) -> i128 {
+        // Parsing bounds the magnitude well below i128::MAX / 10^18.
+        self.rescale(MAX_FRACTION_DIGITS).unwrap()
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.normalized() == other.normalized()
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.normalized().hash(state);
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Number) -> Ordering {
+        self.normalized().cmp(&other.normalized())
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.fraction_digits == 0 {
+            return write!(f, "{}", self.value);
+        }
+
+        let factor = 10i128.pow(u32::from(self.fraction_digits));
+        let sign = if self.value < 0 { "-" } else { "" };
+        let integer = (self.value / factor).abs();
+        let fraction = format!(
+            "{:0width$}",
+            (self.value % factor).abs(),
+            width = self.fraction_digits as usize
+        );
+        let trimmed = fraction.trim_end_matches('0');
+
+        write!(
+            f,
+            "{}{}.{}",
+            sign,
+            integer,
+            if trimmed.is_empty() { "0" } else { trimmed }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RangePart {
+    pub min: Number,
+    pub max: Number,
+}
+
+/// A `range` or `length` restriction, made of one or more disjoint intervals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub parts: Vec<RangePart>,
+}
+
+impl Range {
+    pub fn new(min: Number, max: Number) -> Range {
+        Range {
+            parts: vec![RangePart { min, max }],
+        }
+    }
+
+    /// Parses a range expression such as `1..10 | 20 | 30..max`, where `min`
+    /// and `max` refer to the bounds of `base`. Each part has to stay within
+    /// `base`, since a restriction can only narrow it, and the parts have to
+    /// be in ascending order without overlapping.
+    pub fn parse(expr: &str, base: &Range) -> Option<Range> {
+        let parse_bound = |s: &str| match s.trim() {
+            "min" => Some(base.min()),
+            "max" => Some(base.max()),
+            x => Number::parse(x),
+        };
+
+        let mut parts = Vec::new();
+
+        for part in expr.split('|') {
+            let mut bounds = part.splitn(2, "..");
+            let min = parse_bound(bounds.next()?)?;
+            let max = match bounds.next() {
+                Some(x) => parse_bound(x)?,
+                None => min,
+            };
+
+            let within_base = base.parts.iter().any(|b| b.min <= min && max <= b.max);
+            let after_previous = parts.last().is_none_or(|p: &RangePart| p.max < min);
+
+            if min > max || !within_base || !after_previous {
+                return None;
+            }
+
+            parts.push(RangePart { min, max });
+        }
+
+        Some(Range { parts })
+    }
+
+    pub fn min(&self) -> Number {
+        self.parts[0].min
+    }
+
+    pub fn max(&self) -> Number {
+        self.parts[self.parts.len() - 1].max
+    }
+
+    pub fn contains(&self, n: Number) -> bool {
+        self.parts.iter().any(|p| p.min <= n && n <= p.max)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parts: Vec<String> = self
+            .parts
+            .iter()
+            .map(|p| {
+                if p.min == p.max {
+                    p.min.to_string()
+                } else {
+                    format!("{}..{}", p.min, p.max)
+                }
+            })
+            .collect();
+
+        write!(f, "{}", parts.join(" | "))
+    }
+}
+
+/// A `pattern` restriction, whose XSD regex is compiled when the model is
+/// built.
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    pub regex: String,
+    pub invert_match: bool,
+    compiled: Regex,
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.regex == other.regex && self.invert_match == other.invert_match
+    }
+}
+
+/// Translates an XSD regex (XML Schema part 2, appendix F) to the syntax of
+/// the `regex` crate. XSD has no anchors, so `^` and `$` are literal outside
+/// of character classes, `\i` and `\c` are the XML name character classes,
+/// and a class is subtracted from another with `-[...]`.
+fn translate_regex(regex: &str) -> String {
+    const INITIAL: &str = r"_:A-Za-z\u{C0}-\u{D6}\u{D8}-\u{F6}\u{F8}-\u{2FF}\u{370}-\u{37D}\u{37F}-\u{1FFF}\u{200C}-\u{200D}\u{2070}-\u{218F}\u{2C00}-\u{2FEF}\u{3001}-\u{D7FF}\u{F900}-\u{FDCF}\u{FDF0}-\u{FFFD}\u{10000}-\u{EFFFF}";
+    const NAME: &str = r"\-.0-9\u{B7}\u{300}-\u{36F}\u{203F}-\u{2040}";
+
+    let mut translated = String::new();
+    let mut depth = 0;
+    let mut chars = regex.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('i') if depth > 0 => translated.push_str(INITIAL),
+                Some('i') => translated.push_str(&format!("[{}]", INITIAL)),
+                Some('I') => translated.push_str(&format!("[^{}]", INITIAL)),
+                Some('c') if depth > 0 => translated.push_str(&format!("{}{}", INITIAL, NAME)),
+                Some('c') => translated.push_str(&format!("[{}{}]", INITIAL, NAME)),
+                Some('C') => translated.push_str(&format!("[^{}{}]", INITIAL, NAME)),
+                Some(x) => {
+                    translated.push('\\');
+                    translated.push(x);
+                }
+                None => translated.push('\\'),
+            },
+            '[' => {
+                depth += 1;
+                translated.push(c);
+            }
+            ']' if depth > 0 => {
+                depth -= 1;
+                translated.push(c);
+            }
+            '-' if depth > 0 && chars.peek() == Some(&'[') => translated.push_str("--"),
+            '^' | '$' if depth == 0 => {
+                translated.push('\\');
+                translated.push(c);
+            }
+            _ => translated.push(c),
+        }
+    }
+
+    translated
+}
+
+impl Pattern {
+    /// Compiles the XSD regex of a `pattern`, which implicitly matches the
+    /// whole value.
+    pub fn new(regex: &str, invert_match: bool, path: &str) -> Result<Pattern, ModelError> {
+        let compiled = Regex::new(&format!("^(?:{})$", translate_regex(regex)))
+            .map_err(|_| invalid_argument(path, "pattern", regex))?;
+
+        Ok(Pattern {
+            regex: regex.to_string(),
+            invert_match,
+            compiled,
+        })
+    }
+
+    /// Whether `value` is allowed by the pattern, taking `invert-match` into
+    /// account.
+    pub fn allows(&self, value: &str) -> bool {
+        self.compiled.is_match(value) != self.invert_match
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Enum {
+    pub name: String,
+    pub value: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bit {
+    pub name: String,
+    pub position: u32,
+}
+
+/// The YANG built-in types (RFC 7950 section 4.2.4), along with the
+/// substatements that are mandatory for or specific to each of them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuiltinType {
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    Decimal64 {
+        fraction_digits: u8,
+    },
+    String,
+    Boolean,
+    Enumeration(Vec<Enum>),
+    Bits(Vec<Bit>),
+    Binary,
+    Empty,
+    Union(Vec<LeafType>),
+    /// The bases a value has to be derived from, qualified with the name of
+    /// the module defining them.
+    Identityref(Vec<String>),
+    Leafref {
+        path: LeafrefPath,
+        require_instance: bool,
+        /// The type of the node `path` refers to, which depends on where the
+        /// leafref is used, and so is filled in once the whole data model is
+        /// built.
+        target: Option<Box<LeafType>>,
+    },
+    InstanceIdentifier {
+        require_instance: bool,
+    },
+}
+
+impl BuiltinType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BuiltinType::Int8 => "int8",
+            BuiltinType::Int16 => "int16",
+            BuiltinType::Int32 => "int32",
+            BuiltinType::Int64 => "int64",
+            BuiltinType::Uint8 => "uint8",
+            BuiltinType::Uint16 => "uint16",
+            BuiltinType::Uint32 => "uint32",
+            BuiltinType::Uint64 => "uint64",
+            BuiltinType::Decimal64 { .. } => "decimal64",
+            BuiltinType::String => "string",
+            BuiltinType::Boolean => "boolean",
+            BuiltinType::Enumeration(_) => "enumeration",
+            BuiltinType::Bits(_) => "bits",
+            BuiltinType::Binary => "binary",
+            BuiltinType::Empty => "empty",
+            BuiltinType::Union(_) => "union",
+            BuiltinType::Identityref(_) => "identityref",
+            BuiltinType::Leafref { .. } => "leafref",
+            BuiltinType::InstanceIdentifier { .. } => "instance-identifier",
+        }
+    }
+
+    /// The value space of a numeric type, which any `range` restriction must
+    /// stay within.
+    pub fn base_range(&self) -> Option<Range> {
+        let range =
+            |min: i128, max: i128| Some(Range::new(Number::new(min, 0), Number::new(max, 0)));
+
+        match self {
+            BuiltinType::Int8 => range(i8::MIN.into(), i8::MAX.into()),
+            BuiltinType::Int16 => range(i16::MIN.into(), i16::MAX.into()),
+            BuiltinType::Int32 => range(i32::MIN.into(), i32::MAX.into()),
+            BuiltinType::Int64 => range(i64::MIN.into(), i64::MAX.into()),
+            BuiltinType::Uint8 => range(0, u8::MAX.into()),
+            BuiltinType::Uint16 => range(0, u16::MAX.into()),
+            BuiltinType::Uint32 => range(0, u32::MAX.into()),
+            BuiltinType::Uint64 => range(0, u64::MAX.into()),
+            BuiltinType::Decimal64 { fraction_digits } => Some(Range::new(
+                Number::new(i64::MIN.into(), *fraction_digits),
+                Number::new(i64::MAX.into(), *fraction_digits),
+            )),
+            _ => None,
+        }
+    }
+
+    /// The value space of a `length` restriction for the types that allow one.
+    pub fn base_length(&self) -> Option<Range> {
+        match self {
+            BuiltinType::String | BuiltinType::Binary => Some(Range::new(
+                Number::new(0, 0),
+                Number::new(u64::MAX.into(), 0),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// The resolved type of a `leaf` or `leaf-list`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeafType {
+    pub builtin: BuiltinType,
+    /// The name of the `typedef` this type was resolved through, if any.
+    pub typedef: Option<String>,
+    pub range: Option<Range>,
+    pub length: Option<Range>,
+    pub patterns: Vec<Pattern>,
+    /// The `default` of the typedefs this type was resolved through, if any,
+    /// which leaves and leaf-lists without a default of their own use.
+    pub default: Option<String>,
+}
+
+pub const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Decodes the lexical form of a binary value, which is base64 (RFC 4648
+/// section 4) that may contain whitespace.
+pub fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let s: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if !s.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let mut bytes = Vec::new();
+
+    for chunk in s.chunks(4) {
+        let padding = chunk.iter().rev().take_while(|b| **b == b'=').count();
+        if padding > 2 {
+            return None;
+        }
+
+        let mut group = 0u32;
+        for (i, b) in chunk[..4 - padding].iter().enumerate() {
+            let index = BASE64_ALPHABET.iter().position(|x| x == b)? as u32;
+            group |= index << (18 - 6 * i);
+        }
+
+        for i in 0..3 - padding {
+            bytes.push((group >> (16 - 8 * i)) as u8);
+        }
+    }
+
+    Some(bytes)
+}
+
+fn get_value_attribute(el: dom::Element, statement: &str) -> Option<String> {
+    get_yin_children(el, statement)
+        .first()
+        .and_then(|e| e.attribute_value("value"))
+        .map(|x| x.to_string())
+}
+
+fn invalid_argument(path: &str, statement: &str, value: &str) -> ModelError {
+    ModelError::InvalidArgument {
+        path: path.to_string(),
+        statement: statement.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn parse_require_instance(el: dom::Element) -> bool {
+    get_value_attribute(el, "require-instance").is_none_or(|x| x == "true")
+}
+
+fn parse_enums(el: dom::Element, path: &str) -> Result<Vec<Enum>, ModelError> {
+    let mut enums: Vec<Enum> = Vec::new();
+
+    for enum_el in get_yin_children(el, "enum") {
+        let name = get_raw_name(enum_el, path)?;
+        let value = match get_value_attribute(enum_el, "value") {
+            Some(x) => x.parse().map_err(|_| invalid_argument(path, "value", &x))?,
+            // The value after the highest one so far, which must not overflow.
+            None => match enums.iter().map(|e| e.value).max() {
+                Some(x) => x
+                    .checked_add(1)
+                    .ok_or_else(|| invalid_argument(path, "enum", &name))?,
+                None => 0,
+            },
+        };
+
+        enums.push(Enum { name, value });
+    }
+
+    Ok(enums)
+}
+
+fn parse_bits(el: dom::Element, path: &str) -> Result<Vec<Bit>, ModelError> {
+    let mut bits: Vec<Bit> = Vec::new();
+
+    for bit_el in get_yin_children(el, "bit") {
+        let name = get_raw_name(bit_el, path)?;
+        let position = match get_value_attribute(bit_el, "position") {
+            Some(x) => x
+                .parse()
+                .map_err(|_| invalid_argument(path, "position", &x))?,
+            None => match bits.iter().map(|b| b.position).max() {
+                Some(x) => x
+                    .checked_add(1)
+                    .ok_or_else(|| invalid_argument(path, "bit", &name))?,
+                None => 0,
+            },
+        };
+
+        bits.push(Bit { name, position });
+    }
+
+    Ok(bits)
+}
+
+fn parse_fraction_digits(el: dom::Element, path: &str) -> Result<u8, ModelError> {
+    let value = get_attribute(
+        get_required_child(el, "fraction-digits", path)?,
+        "value",
+        path,
+    )?;
+
+    value
+        .parse()
+        .ok()
+        .filter(|x| (1..=MAX_FRACTION_DIGITS).contains(x))
+        .ok_or_else(|| invalid_argument(path, "fraction-digits", &value))
+}
+
+fn parse_builtin<'d>(
+    el: dom::Element<'d>,
+    path: &str,
+    typedefs: &[dom::Element<'d>],
+) -> Result<Option<BuiltinType>, ModelError> {
+    let builtin = match get_raw_name(el, path)?.as_str() {
+        "int8" => BuiltinType::Int8,
+        "int16" => BuiltinType::Int16,
+        "int32" => BuiltinType::Int32,
+        "int64" => BuiltinType::Int64,
+        "uint8" => BuiltinType::Uint8,
+        "uint16" => BuiltinType::Uint16,
+        "uint32" => BuiltinType::Uint32,
+        "uint64" => BuiltinType::Uint64,
+        "decimal64" => BuiltinType::Decimal64 {
+            fraction_digits: parse_fraction_digits(el, path)?,
+        },
+        "string" => BuiltinType::String,
+        "boolean" => BuiltinType::Boolean,
+        "enumeration" => BuiltinType::Enumeration(parse_enums(el, path)?),
+        "bits" => BuiltinType::Bits(parse_bits(el, path)?),
+        "binary" => BuiltinType::Binary,
+        "empty" => BuiltinType::Empty,
+        "union" => BuiltinType::Union(
+            get_yin_children(el, "type")
+                .into_iter()
+                .map(|t| parse_type(t, path, typedefs))
+                .collect::<Result<_, _>>()?,
+        ),
+        "identityref" => BuiltinType::Identityref(
+            get_yin_children(el, "base")
+                .into_iter()
+                .map(|b| qualify_identity(b, &get_raw_name(b, path)?, path))
+                .collect::<Result<_, _>>()?,
+        ),
+        "leafref" => BuiltinType::Leafref {
+            path: parse_leafref_path(el, path)?,
+            require_instance: parse_require_instance(el),
+            target: None,
+        },
+        "instance-identifier" => BuiltinType::InstanceIdentifier {
+            require_instance: parse_require_instance(el),
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some(builtin))
+}
+
+fn parse_leafref_path(el: dom::Element, path: &str) -> Result<LeafrefPath, ModelError> {
+    let value = get_attribute(get_required_child(el, "path", path)?, "value", path)?;
+
+    LeafrefPath::parse(&value).ok_or_else(|| invalid_argument(path, "path", &value))
+}
+
+fn parse_patterns(el: dom::Element, path: &str) -> Result<Vec<Pattern>, ModelError> {
+    get_yin_children(el, "pattern")
+        .into_iter()
+        .map(|p| {
+            Pattern::new(
+                &get_attribute(p, "value", path)?,
+                get_value_attribute(p, "modifier").is_some_and(|x| x == "invert-match"),
+                path,
+            )
+        })
+        .collect()
+}
+
+/// Returns the enums of the base type that `el` keeps, which have to be
+/// among them with the same values.
+fn restrict_enums(enums: &[Enum], el: dom::Element, path: &str) -> Result<Vec<Enum>, ModelError> {
+    parse_enums(el, path)?
+        .into_iter()
+        .zip(get_yin_children(el, "enum"))
+        .map(|(e, enum_el)| {
+            let has_value = get_value_attribute(enum_el, "value").is_some();
+
+            match enums.iter().find(|x| x.name == e.name) {
+                Some(x) if !has_value || x.value == e.value => Ok(x.clone()),
+                _ => Err(invalid_argument(path, "enum", &e.name)),
+            }
+        })
+        .collect()
+}
+
+/// Returns the bits of the base type that `el` keeps, which have to be
+/// among them at the same positions.
+fn restrict_bits(bits: &[Bit], el: dom::Element, path: &str) -> Result<Vec<Bit>, ModelError> {
+    parse_bits(el, path)?
+        .into_iter()
+        .zip(get_yin_children(el, "bit"))
+        .map(|(b, bit_el)| {
+            let has_position = get_value_attribute(bit_el, "position").is_some();
+
+            match bits.iter().find(|x| x.name == b.name) {
+                Some(x) if !has_position || x.position == b.position => Ok(x.clone()),
+                _ => Err(invalid_argument(path, "bit", &b.name)),
+            }
+        })
+        .collect()
+}
+
+/// Resolves the typedef `el`, which the type being parsed refers to through
+/// the `typedefs` that are already being resolved.
+fn resolve_typedef<'d>(
+    el: dom::Element<'d>,
+    path: &str,
+    typedefs: &[dom::Element<'d>],
+) -> Result<LeafType, ModelError> {
+    check_cycle(el, typedefs, path)?;
+
+    let mut resolving = typedefs.to_vec();
+    resolving.push(el);
+
+    let type_el = get_required_child(el, "type", path)?;
+    let mut leaf_type = parse_type(type_el, path, &resolving)?;
+
+    leaf_type.typedef = Some(get_raw_name(el, path)?);
+
+    if let Some(default) = get_value_attribute(el, "default") {
+        leaf_type.default = Some(default);
+    }
+
+    Ok(leaf_type)
+}
+
+fn parse_type<'d>(
+    el: dom::Element<'d>,
+    path: &str,
+    typedefs: &[dom::Element<'d>],
+) -> Result<LeafType, ModelError> {
+    let leaf_type = match parse_builtin(el, path, typedefs)? {
+        Some(builtin) => LeafType {
+            builtin,
+            typedef: None,
+            range: None,
+            length: None,
+            patterns: Vec::new(),
+            default: None,
+        },
+        None => {
+            let name = get_raw_name(el, path)?;
+            let typedef_el = find_definition(el, "typedef", &name).ok_or_else(|| {
+                ModelError::UnresolvedReference {
+                    path: path.to_string(),
+                    statement: "type".to_string(),
+                    name: name.clone(),
+                }
+            })?;
+
+            resolve_typedef(typedef_el, path, typedefs)?.derive(el, path)?
+        }
+    };
+
+    leaf_type.restrict(el, path)
+}
+
+impl LeafType {
+    /// Parses a `type` statement of the schema node at `path`, resolving it
+    /// through any chain of typedefs down to its built-in type.
+    pub fn new(el: dom::Element, path: &str) -> Result<LeafType, ModelError> {
+        parse_type(el, path, &[])
+    }
+
+    /// Applies the restrictions of a `type` statement that refers to this
+    /// type. Ranges and lengths replace the existing ones, taking `min` and
+    /// `max` from them, while patterns accumulate since a value has to match
+    /// all of them. Each restriction is only allowed on the types it applies
+    /// to.
+    fn restrict(mut self, el: dom::Element, path: &str) -> Result<LeafType, ModelError> {
+        if let Some(x) = get_value_attribute(el, "range") {
+            let range = self
+                .range
+                .clone()
+                .or_else(|| self.builtin.base_range())
+                .and_then(|base| Range::parse(&x, &base))
+                .ok_or_else(|| invalid_argument(path, "range", &x))?;
+            self.range = Some(range);
+        }
+
+        if let Some(x) = get_value_attribute(el, "length") {
+            let length = self
+                .length
+                .clone()
+                .or_else(|| self.builtin.base_length())
+                .and_then(|base| Range::parse(&x, &base))
+                .ok_or_else(|| invalid_argument(path, "length", &x))?;
+            self.length = Some(length);
+        }
+
+        let patterns = parse_patterns(el, path)?;
+
+        if let (Some(p), false) = (patterns.first(), self.builtin == BuiltinType::String) {
+            return Err(invalid_argument(path, "pattern", &p.regex));
+        }
+
+        self.patterns.extend(patterns);
+        Ok(self)
+    }
+
+    /// Applies the substatements a derived type may use to narrow an
+    /// enumeration, bits or reference type.
+    fn derive(mut self, el: dom::Element, path: &str) -> Result<LeafType, ModelError> {
+        let has_enums = !get_yin_children(el, "enum").is_empty();
+        let has_bits = !get_yin_children(el, "bit").is_empty();
+        let require_instance = get_value_attribute(el, "require-instance").map(|x| x == "true");
+
+        match &mut self.builtin {
+            BuiltinType::Enumeration(enums) if has_enums => {
+                *enums = restrict_enums(enums, el, path)?;
+            }
+            BuiltinType::Bits(bits) if has_bits => {
+                *bits = restrict_bits(bits, el, path)?;
+            }
+            BuiltinType::Leafref {
+                require_instance: x,
+                ..
+            }
+            | BuiltinType::InstanceIdentifier {
+                require_instance: x,
+            } => {
+                if let Some(r) = require_instance {
+                    *x = r;
+                }
+            }
+            _ => (),
+        }
+
+        Ok(self)
+    }
+
+    /// Returns the name of the built-in type this type is derived from.
+    pub fn name(&self) -> &'static str {
+        self.builtin.name()
+    }
+
+    /// Parses the lexical form of an integer or decimal64 value in the value
+    /// space of the built-in type, whatever its `range`.
+    pub fn parse_number(&self, text: &str) -> Option<Number> {
+        let n = Number::parse(text)?;
+        let is_decimal = matches!(self.builtin, BuiltinType::Decimal64 { .. });
+
+        if (!is_decimal && n.fraction_digits() != 0) || !self.builtin.base_range()?.contains(n) {
+            return None;
+        }
+
+        Some(n)
+    }
+
+    pub fn find_enum(&self, name: &str) -> Option<&Enum> {
+        match &self.builtin {
+            BuiltinType::Enumeration(enums) => enums.iter().find(|e| e.name == name),
+            _ => None,
+        }
+    }
+
+    /// Parses the lexical form of a bits value, a space-separated list of the
+    /// names of the bits that are set, returning them ordered by position.
+    pub fn find_bits(&self, text: &str) -> Option<Vec<Bit>> {
+        let bits = match &self.builtin {
+            BuiltinType::Bits(bits) => bits,
+            _ => return None,
+        };
+        let mut set = Vec::new();
+
+        for name in text.split_whitespace() {
+            let bit = bits.iter().find(|b| b.name == name)?;
+
+            if !set.contains(bit) {
+                set.push(bit.clone());
+            }
+        }
+
+        set.sort_by_key(|b| b.position);
+        Some(set)
+    }
+
+    /// Whether a number is within the `range` of the type, if it has one.
+    pub fn allows_number(&self, n: Number) -> bool {
+        self.range.as_ref().is_none_or(|r| r.contains(n))
+    }
+
+    /// Whether a string or binary value of `length` characters or bytes is
+    /// within the `length` of the type, if it has one.
+    pub fn allows_length(&self, length: usize) -> bool {
+        self.length
+            .as_ref()
+            .is_none_or(|l| l.contains(Number::new(length as i128, 0)))
+    }
+
+    /// Whether `text` is the lexical form (RFC 7950 section 9) of a value of
+    /// the type that meets all of its restrictions. Identities and instance
+    /// identifiers are only checked against the data they refer to.
+    pub fn accepts(&self, text: &str) -> bool {
+        match &self.builtin {
+            BuiltinType::Union(members) => members.iter().any(|m| m.accepts(text)),
+            BuiltinType::Leafref {
+                target: Some(t), ..
+            } => t.accepts(text),
+            BuiltinType::Decimal64 { fraction_digits } => self
+                .parse_number(text)
+                .is_some_and(|n| n.rescale(*fraction_digits).is_some() && self.allows_number(n)),
+            BuiltinType::String => {
+                self.allows_length(text.chars().count())
+                    && self.patterns.iter().all(|p| p.allows(text))
+            }
+            BuiltinType::Boolean => text == "true" || text == "false",
+            BuiltinType::Enumeration(_) => self.find_enum(text).is_some(),
+            BuiltinType::Bits(_) => self.find_bits(text).is_some(),
+            BuiltinType::Binary => decode_base64(text).is_some_and(|b| self.allows_length(b.len())),
+            BuiltinType::Empty => text.is_empty(),
+            BuiltinType::Identityref(_)
+            | BuiltinType::InstanceIdentifier { .. }
+            | BuiltinType::Leafref { .. } => true,
+            _ => self
+                .parse_number(text)
+                .is_some_and(|n| self.allows_number(n)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> LeafType {
+        let xml = format!(
+            r#"<?xml version="1.0"?><yin:type xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1" {}"#,
+            xml
+        );
+        let pkg = get_package(&xml).unwrap();
+        LeafType::new(get_root_el(&pkg).unwrap(), "/foo").unwrap()
+    }
+
+    #[test]
+    fn it_parses_integer_ranges() {
+        let leaf_type = parse(r#"name="uint8"><yin:range value="1..10 | 20..max"/></yin:type>"#);
+        let range = leaf_type.range.unwrap();
+
+        assert_eq!(leaf_type.builtin, BuiltinType::Uint8);
+        assert_eq!(range.to_string(), "1..10 | 20..255");
+        assert!(range.contains(Number::new(5, 0)));
+        assert!(!range.contains(Number::new(15, 0)));
+    }
+
+    #[test]
+    fn it_parses_decimal64() {
+        let leaf_type = parse(
+            r#"name="decimal64">
+                <yin:fraction-digits value="2"/>
+                <yin:range value="0.5..10"/>
+            </yin:type>"#,
+        );
+
+        assert_eq!(
+            leaf_type.builtin,
+            BuiltinType::Decimal64 { fraction_digits: 2 }
+        );
+        assert_eq!(leaf_type.range.unwrap().to_string(), "0.5..10");
+    }
+
+    #[test]
+    fn it_parses_string_restrictions() {
+        let leaf_type = parse(
+            r#"name="string">
+                <yin:length value="min..64"/>
+                <yin:pattern value="[a-z]+"/>
+                <yin:pattern value="x.*">
+                    <yin:modifier value="invert-match"/>
+                </yin:pattern>
+            </yin:type>"#,
+        );
+
+        assert_eq!(leaf_type.length.unwrap().to_string(), "0..64");
+        assert_eq!(leaf_type.patterns.len(), 2);
+        assert!(!leaf_type.patterns[0].invert_match);
+        assert!(leaf_type.patterns[1].invert_match);
+        assert!(leaf_type.patterns[0].allows("abc"));
+        assert!(!leaf_type.patterns[0].allows("abc1"));
+        assert!(!leaf_type.patterns[1].allows("xyz"));
+    }
+
+    #[test]
+    fn it_translates_xsd_patterns() {
+        let allows =
+            |regex: &str, value: &str| Pattern::new(regex, false, "/").unwrap().allows(value);
+
+        assert!(allows("a$b^", "a$b^"));
+        assert!(allows("[a-z-[aeiou]]+", "xyz"));
+        assert!(!allows("[a-z-[aeiou]]+", "xaz"));
+        assert!(allows(r"\i\c*", "ns:foo-bar.1"));
+        assert!(!allows(r"\i\c*", "1foo"));
+        assert!(allows(r"[\i-[:]][\c-[:]]*", "foo"));
+        assert!(!allows(r"[\i-[:]][\c-[:]]*", "ns:foo"));
+    }
+
+    #[test]
+    fn it_assigns_enum_values() {
+        let leaf_type = parse(
+            r#"name="enumeration">
+                <yin:enum name="a"/>
+                <yin:enum name="b"><yin:value value="10"/></yin:enum>
+                <yin:enum name="c"/>
+            </yin:type>"#,
+        );
+
+        let values: Vec<i32> = match leaf_type.builtin {
+            BuiltinType::Enumeration(x) => x.iter().map(|e| e.value).collect(),
+            _ => panic!("Expected an enumeration."),
+        };
+        assert_eq!(values, vec![0, 10, 11]);
+    }
+
+    #[test]
+    fn it_assigns_bit_positions() {
+        let leaf_type = parse(
+            r#"name="bits">
+                <yin:bit name="a"><yin:position value="3"/></yin:bit>
+                <yin:bit name="b"/>
+            </yin:type>"#,
+        );
+
+        let positions: Vec<u32> = match leaf_type.builtin {
+            BuiltinType::Bits(x) => x.iter().map(|b| b.position).collect(),
+            _ => panic!("Expected bits."),
+        };
+        assert_eq!(positions, vec![3, 4]);
+    }
+
+    #[test]
+    fn it_parses_nested_unions() {
+        let leaf_type = parse(
+            r#"name="union">
+                <yin:type name="int32"/>
+                <yin:type name="union">
+                    <yin:type name="boolean"/>
+                    <yin:type name="empty"/>
+                </yin:type>
+            </yin:type>"#,
+        );
+
+        let members = match leaf_type.builtin {
+            BuiltinType::Union(x) => x,
+            _ => panic!("Expected a union."),
+        };
+        assert_eq!(members[0].builtin, BuiltinType::Int32);
+        assert_eq!(
+            members[1].builtin,
+            BuiltinType::Union(vec![
+                parse(r#"name="boolean"/>"#),
+                parse(r#"name="empty"/>"#)
+            ])
+        );
+    }
+
+    #[test]
+    fn it_parses_leafref() {
+        let leaf_type = parse(
+            r#"name="leafref">
+                <yin:path value="../../tenant/name"/>
+                <yin:require-instance value="false"/>
+            </yin:type>"#,
+        );
+
+        assert_eq!(
+            leaf_type.builtin,
+            BuiltinType::Leafref {
+                path: LeafrefPath::parse("../../tenant/name").unwrap(),
+                require_instance: false,
+                target: None,
+            }
+        );
+    }
+
+    #[test]
+    fn it_resolves_typedef_chains() {
+        const MODEL: &str = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:typedef name="percent">
+                <yin:type name="uint8">
+                    <yin:range value="0..100"/>
+                </yin:type>
+                <yin:default value="50"/>
+            </yin:typedef>
+            <yin:typedef name="small-percent">
+                <yin:type name="percent">
+                    <yin:range value="min..50"/>
+                </yin:type>
+            </yin:typedef>
+            <yin:container name="foo">
+                <yin:typedef name="word">
+                    <yin:type name="string">
+                        <yin:pattern value="[a-z]+"/>
+                    </yin:type>
+                </yin:typedef>
+                <yin:leaf name="bar">
+                    <yin:type name="small-percent">
+                        <yin:range value="10..max"/>
+                    </yin:type>
+                </yin:leaf>
+                <yin:leaf name="baz">
+                    <yin:type name="word">
+                        <yin:pattern value="[a-c]+"/>
+                    </yin:type>
+                </yin:leaf>
+            </yin:container>
+        </yin:module>"#;
+
+        let pkg = get_package(MODEL).unwrap();
+        let root = get_root_el(&pkg).unwrap();
+
+        let bar_el = evaluate_get_yin_xpath("//yin:leaf[@name='bar']/yin:type", &root).unwrap();
+        let bar = LeafType::new(bar_el, "/foo/bar").unwrap();
+        assert_eq!(bar.builtin, BuiltinType::Uint8);
+        assert_eq!(bar.typedef, Some("small-percent".to_string()));
+        assert_eq!(bar.range.unwrap().to_string(), "10..50");
+        assert_eq!(bar.default, Some("50".to_string()));
+
+        let baz_el = evaluate_get_yin_xpath("//yin:leaf[@name='baz']/yin:type", &root).unwrap();
+        let baz = LeafType::new(baz_el, "/foo/baz").unwrap();
+        assert_eq!(baz.typedef, Some("word".to_string()));
+        assert_eq!(baz.patterns.len(), 2);
+        assert_eq!(baz.default, None);
+    }
+
+    #[test]
+    fn it_reports_typedef_cycles() {
+        let parse_err = |typedefs: &str| {
+            let xml = format!(
+                r#"<?xml version="1.0"?>
+                <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                    {}
+                    <yin:leaf name="foo">
+                        <yin:type name="a"/>
+                    </yin:leaf>
+                </yin:module>"#,
+                typedefs
+            );
+            let pkg = get_package(&xml).unwrap();
+            let root = get_root_el(&pkg).unwrap();
+            let type_el = evaluate_get_yin_xpath("//yin:leaf/yin:type", &root).unwrap();
+            LeafType::new(type_el, "/foo").unwrap_err()
+        };
+
+        assert_eq!(
+            parse_err(r#"<yin:typedef name="a"><yin:type name="a"/></yin:typedef>"#),
+            ModelError::ReferenceCycle {
+                path: "/foo".to_string(),
+                statement: "typedef".to_string(),
+                names: vec!["a".to_string(), "a".to_string()],
+            }
+        );
+        assert_eq!(
+            parse_err(
+                r#"<yin:typedef name="a"><yin:type name="b"/></yin:typedef>
+                <yin:typedef name="b">
+                    <yin:type name="union">
+                        <yin:type name="string"/>
+                        <yin:type name="a"/>
+                    </yin:type>
+                </yin:typedef>"#
+            )
+            .to_string(),
+            "The typedef a refers to itself at /foo: a -> b -> a."
+        );
+    }
+
+    #[test]
+    fn it_reports_invalid_derived_types() {
+        let parse_err = |leaf_type: &str| {
+            let xml = format!(
+                r#"<?xml version="1.0"?>
+                <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+                    <yin:typedef name="percent">
+                        <yin:type name="uint8"><yin:range value="0..100"/></yin:type>
+                    </yin:typedef>
+                    <yin:typedef name="name">
+                        <yin:type name="string"><yin:length value="1..32"/></yin:type>
+                    </yin:typedef>
+                    <yin:typedef name="color">
+                        <yin:type name="enumeration">
+                            <yin:enum name="red"/>
+                            <yin:enum name="blue"/>
+                        </yin:type>
+                    </yin:typedef>
+                    <yin:typedef name="flags">
+                        <yin:type name="bits">
+                            <yin:bit name="up"/>
+                        </yin:type>
+                    </yin:typedef>
+                    <yin:leaf name="foo">{}</yin:leaf>
+                </yin:module>"#,
+                leaf_type
+            );
+            let pkg = get_package(&xml).unwrap();
+            let root = get_root_el(&pkg).unwrap();
+            let type_el = evaluate_get_yin_xpath("//yin:leaf/yin:type", &root).unwrap();
+            LeafType::new(type_el, "/foo").unwrap_err().to_string()
+        };
+
+        assert_eq!(
+            parse_err(r#"<yin:type name="percent"><yin:range value="0..300"/></yin:type>"#),
+            "Invalid range 0..300 at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"<yin:type name="name"><yin:length value="0..16"/></yin:type>"#),
+            "Invalid length 0..16 at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"<yin:type name="color"><yin:enum name="green"/></yin:type>"#),
+            "Invalid enum green at /foo."
+        );
+        assert_eq!(
+            parse_err(
+                r#"<yin:type name="color">
+                    <yin:enum name="blue"><yin:value value="0"/></yin:enum>
+                </yin:type>"#
+            ),
+            "Invalid enum blue at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"<yin:type name="flags"><yin:bit name="down"/></yin:type>"#),
+            "Invalid bit down at /foo."
+        );
+    }
+
+    #[test]
+    fn it_restricts_derived_enumerations() {
+        const MODEL: &str = r#"<?xml version="1.0"?>
+        <yin:module name="test" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+            <yin:typedef name="color">
+                <yin:type name="enumeration">
+                    <yin:enum name="red"/>
+                    <yin:enum name="green"/>
+                    <yin:enum name="blue"/>
+                </yin:type>
+            </yin:typedef>
+            <yin:leaf name="foo">
+                <yin:type name="color">
+                    <yin:enum name="blue"/>
+                </yin:type>
+            </yin:leaf>
+        </yin:module>"#;
+
+        let pkg = get_package(MODEL).unwrap();
+        let root = get_root_el(&pkg).unwrap();
+        let type_el = evaluate_get_yin_xpath("//yin:leaf/yin:type", &root).unwrap();
+
+        assert_eq!(
+            LeafType::new(type_el, "/foo").unwrap().builtin,
+            BuiltinType::Enumeration(vec![Enum {
+                name: "blue".to_string(),
+                value: 2
+            }])
+        );
+    }
+
+    #[test]
+    fn it_reports_invalid_types() {
+        let parse_err = |xml: &str| {
+            let xml = format!(
+                r#"<?xml version="1.0"?><yin:type xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1" {}"#,
+                xml
+            );
+            let pkg = get_package(&xml).unwrap();
+            LeafType::new(get_root_el(&pkg).unwrap(), "/foo").unwrap_err()
+        };
+
+        assert_eq!(
+            parse_err(r#"name="uint8"><yin:range value="1..300"/></yin:type>"#),
+            ModelError::InvalidArgument {
+                path: "/foo".to_string(),
+                statement: "range".to_string(),
+                value: "1..300".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_err(r#"name="uint8"><yin:range value="1..5 | 3..10"/></yin:type>"#).to_string(),
+            "Invalid range 1..5 | 3..10 at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"name="uint8"><yin:range value="10 | 1..5"/></yin:type>"#).to_string(),
+            "Invalid range 10 | 1..5 at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"name="boolean"><yin:range value="0..1"/></yin:type>"#).to_string(),
+            "Invalid range 0..1 at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"name="int32"><yin:length value="1..5"/></yin:type>"#).to_string(),
+            "Invalid length 1..5 at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"name="binary"><yin:pattern value="a+"/></yin:type>"#).to_string(),
+            "Invalid pattern a+ at /foo."
+        );
+        assert_eq!(
+            parse_err(
+                r#"name="enumeration">
+                    <yin:enum name="a"><yin:value value="2147483647"/></yin:enum>
+                    <yin:enum name="b"/>
+                </yin:type>"#
+            )
+            .to_string(),
+            "Invalid enum b at /foo."
+        );
+        assert_eq!(
+            parse_err(
+                r#"name="bits">
+                    <yin:bit name="a"><yin:position value="4294967295"/></yin:bit>
+                    <yin:bit name="b"/>
+                </yin:type>"#
+            )
+            .to_string(),
+            "Invalid bit b at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"name="string"><yin:pattern value="(a"/></yin:type>"#).to_string(),
+            "Invalid pattern (a at /foo."
+        );
+        assert_eq!(
+            parse_err(r#"name="decimal64"/>"#).to_string(),
+            "The type statement at /foo has no fraction-digits statement."
+        );
+        assert_eq!(
+            parse_err(r#"name="ip-address"/>"#).to_string(),
+            "Could not resolve the type ip-address at /foo."
+        );
+    }
+
+    #[test]
+    fn it_formats_numbers() {
+        assert_eq!(Number::parse("-0.50").unwrap().to_string(), "-0.5");
+        assert_eq!(Number::parse("12").unwrap().to_string(), "12");
+        assert_eq!(Number::parse("1.5").unwrap(), Number::new(150, 2));
+        assert!(Number::parse("1.").is_none());
+        assert!(Number::parse("abc").is_none());
+    }
+
+    #[test]
+    fn it_accepts_lexical_values() {
+        let leaf_type = parse(r#"name="uint8"><yin:range value="1..10"/></yin:type>"#);
+        assert!(leaf_type.accepts("7"));
+        assert!(!leaf_type.accepts("70"));
+        assert!(!leaf_type.accepts("1.5"));
+
+        let leaf_type = parse(r#"name="decimal64"><yin:fraction-digits value="1"/></yin:type>"#);
+        assert!(leaf_type.accepts("1.50"));
+        assert!(!leaf_type.accepts("1.55"));
+
+        let leaf_type = parse(
+            r#"name="union">
+                <yin:type name="string"><yin:length value="1..2"/></yin:type>
+                <yin:type name="boolean"/>
+            </yin:type>"#,
+        );
+        assert!(leaf_type.accepts("ab"));
+        assert!(leaf_type.accepts("false"));
+        assert!(!leaf_type.accepts("abc"));
+    }
+}
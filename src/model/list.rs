@@ -1,42 +1,159 @@
+use indexmap::IndexMap;
 use inflector::cases::camelcase::to_camel_case;
-use std::collections::HashMap;
+use std::sync::Arc;
 use sxd_document::*;
 
+use super::choice::Choice;
+use super::constraint::{parse_musts, parse_when, Must, When};
+use super::error::ModelError;
+use super::scope::Scope;
 use super::util::*;
 
 #[derive(Debug, Clone)]
 pub struct List {
     pub name: String,
-    pub children: HashMap<String, Model>,
+    pub yang_name: String,
+    pub module: Option<String>,
+    pub namespace: Option<String>,
+    pub prefix: Option<String>,
+    pub children: IndexMap<String, Model>,
+    pub choices: Vec<Arc<Choice>>,
     pub keys: Vec<String>,
+    pub config: bool,
+    /// The `unique` statements of the list, each a set of descendant leaves
+    /// given as paths of YANG names such as `ip/port`.
+    pub unique: Vec<Vec<String>>,
+    pub min_elements: u64,
+    pub max_elements: Option<u64>,
+    pub ordered_by_user: bool,
+    pub augmented_by: Option<String>,
+    pub when: Vec<When>,
+    pub musts: Vec<Must>,
 }
 
-fn parse_keys(el: dom::Element) -> Vec<String> {
-    let key_el = evaluate_get_yin_xpath("./yin:key", &el).expect("A list must have keys defined.");
-    let key_attr_value = key_el.attribute("value").unwrap().value().to_string();
-    let key_split = key_attr_value.split(' ');
+/// Parses the `key` of a list, which only lists of state data may lack.
+fn parse_keys(
+    el: dom::Element,
+    children: &IndexMap<String, Model>,
+    config: bool,
+    path: &str,
+) -> Result<Vec<String>, ModelError> {
+    let key_el = match get_yin_children(el, "key").into_iter().next() {
+        Some(x) => x,
+        None if !config => return Ok(Vec::new()),
+        None => {
+            return Err(ModelError::MissingKey {
+                path: path.to_string(),
+                key: None,
+            })
+        }
+    };
+    let key_attr_value = get_attribute(key_el, "value", path)?;
+
+    key_attr_value
+        .split_whitespace()
+        .map(|k| {
+            let key = split_prefix(k).1;
 
-    key_split.map(|s| to_camel_case(s)).collect()
+            match children.get(key) {
+                Some(Model::Leaf(_)) => Ok(key.to_string()),
+                _ => Err(ModelError::MissingKey {
+                    path: path.to_string(),
+                    key: Some(k.to_string()),
+                }),
+            }
+        })
+        .collect()
 }
 
-impl List {
-    pub fn new(el: dom::Element) -> List {
-        List {
-            name: get_name(el),
-            children: parse_children(el),
-            keys: parse_keys(el),
+/// Returns the path of a descendant leaf named in a `unique` statement
+/// without its prefixes, e.g. `t:endpoint/t:port` as `endpoint/port`.
+fn parse_unique_leaf(
+    descendant: &str,
+    children: &IndexMap<String, Model>,
+    path: &str,
+) -> Result<String, ModelError> {
+    let names: Vec<String> = descendant
+        .split('/')
+        .map(|n| split_prefix(n).1.to_string())
+        .collect();
+
+    let mut models = children;
+    for (i, name) in names.iter().enumerate() {
+        match (models.get(name), i == names.len() - 1) {
+            (Some(Model::Leaf(_)), true) => return Ok(names.join("/")),
+            (Some(Model::Container(x)), false) => models = &x.children,
+            _ => break,
         }
     }
+
+    Err(ModelError::UnresolvedReference {
+        path: path.to_string(),
+        statement: "unique".to_string(),
+        name: descendant.to_string(),
+    })
+}
+
+fn parse_unique(
+    el: dom::Element,
+    children: &IndexMap<String, Model>,
+    path: &str,
+) -> Result<Vec<Vec<String>>, ModelError> {
+    get_yin_children(el, "unique")
+        .into_iter()
+        .map(|u| {
+            get_attribute(u, "tag", path)?
+                .split_whitespace()
+                .map(|d| parse_unique_leaf(d, children, path))
+                .collect()
+        })
+        .collect()
+}
+
+impl List {
+    pub fn new<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<List, ModelError> {
+        let path = scope.path();
+        let (children, choices) = parse_children(el, scope)?;
+        let keys = parse_keys(el, &children, scope.config(), &path)?;
+        let unique = parse_unique(el, &children, &path)?;
+        let yang_name = get_raw_name(el, &path)?;
+        let (min_elements, max_elements) = parse_elements(el, scope)?;
+
+        Ok(List {
+            name: to_camel_case(&yang_name),
+            yang_name,
+            module: scope.module(),
+            namespace: scope.namespace(),
+            prefix: scope.prefix(),
+            children,
+            choices,
+            keys,
+            config: scope.config(),
+            unique,
+            min_elements,
+            max_elements,
+            ordered_by_user: parse_ordered_by(el, &path)?,
+            augmented_by: None,
+            when: parse_when(el, false, &path)?.into_iter().collect(),
+            musts: parse_musts(el, scope)?,
+        })
+    }
 }
 
 impl WithChildren for List {
-    fn get_children(&self) -> &HashMap<String, Model> {
+    fn get_children(&self) -> &IndexMap<String, Model> {
         &self.children
     }
+
+    fn get_choices(&self) -> &[Arc<Choice>] {
+        &self.choices
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::model::error::ModelError;
+    use crate::model::scope::Scope;
     use crate::model::util::*;
 
     const MODEL: &str = r#"<?xml version="1.0"?>
@@ -47,24 +164,131 @@ mod tests {
         </yin:leaf>
     </yin:list>"#;
 
+    const MODEL_WITHOUT_KEY_LEAF: &str = r#"<?xml version="1.0"?>
+    <yin:list name="peer" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:key value="id"/>
+        <yin:leaf name="name">
+            <yin:type name="string"/>
+        </yin:leaf>
+    </yin:list>"#;
+
     #[test]
     fn it_parses_name() {
-        let pkg = get_package(MODEL);
-        let model = super::List::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.name, "peer");
     }
 
     #[test]
     fn it_parses_keys() {
-        let pkg = get_package(MODEL);
-        let model = super::List::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.keys, vec!["name"]);
     }
 
     #[test]
     fn it_parses_children() {
-        let pkg = get_package(MODEL);
-        let model = super::List::new(get_root_el(&pkg));
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
         assert_eq!(model.children.len(), 1);
     }
+
+    #[test]
+    fn it_fails_on_missing_keys() {
+        let pkg = get_package(MODEL_WITHOUT_KEY_LEAF).unwrap();
+        let scope = Scope::default().descend("peer");
+        let error = super::List::new(get_root_el(&pkg).unwrap(), &scope).unwrap_err();
+        assert_eq!(
+            error,
+            ModelError::MissingKey {
+                path: "/peer".to_string(),
+                key: Some("id".to_string()),
+            }
+        );
+        assert_eq!(error.to_string(), "The list at /peer has no key leaf id.");
+    }
+
+    #[test]
+    fn it_allows_state_lists_without_keys() {
+        let scope = Scope::default().descend("peer");
+
+        let xml = MODEL.replace(r#"<yin:key value="name"/>"#, "");
+        let pkg = get_package(&xml).unwrap();
+        let error = super::List::new(get_root_el(&pkg).unwrap(), &scope).unwrap_err();
+        assert_eq!(error.to_string(), "The list at /peer has no key.");
+
+        let xml = MODEL.replace(
+            r#"<yin:key value="name"/>"#,
+            r#"<yin:config value="false"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let el = get_root_el(&pkg).unwrap();
+        let model = super::List::new(el, &scope.with_config(el).unwrap()).unwrap();
+        assert!(model.keys.is_empty());
+    }
+
+    #[test]
+    fn it_parses_elements() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!((model.min_elements, model.max_elements), (0, None));
+
+        let xml = MODEL.replace(
+            r#"<yin:key value="name"/>"#,
+            r#"<yin:key value="name"/><yin:min-elements value="1"/><yin:max-elements value="3"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!((model.min_elements, model.max_elements), (1, Some(3)));
+
+        let xml = MODEL.replace(
+            r#"<yin:key value="name"/>"#,
+            r#"<yin:key value="name"/><yin:max-elements value="0"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let error = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid max-elements 0 at /.");
+    }
+
+    #[test]
+    fn it_parses_ordered_by() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(!model.ordered_by_user);
+
+        let xml = MODEL.replace(
+            r#"<yin:key value="name"/>"#,
+            r#"<yin:key value="name"/><yin:ordered-by value="user"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert!(model.ordered_by_user);
+
+        let xml = xml.replace(r#"value="user""#, r#"value="random""#);
+        let pkg = get_package(&xml).unwrap();
+        let error = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid ordered-by random at /.");
+    }
+
+    #[test]
+    fn it_parses_unique() {
+        let xml = MODEL.replace(
+            r#"<yin:key value="name"/>"#,
+            r#"<yin:key value="name"/><yin:unique tag="p:name"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let model = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap();
+        assert_eq!(model.unique, vec![vec!["name".to_string()]]);
+
+        let xml = MODEL.replace(
+            r#"<yin:key value="name"/>"#,
+            r#"<yin:key value="name"/><yin:unique tag="name address"/>"#,
+        );
+        let pkg = get_package(&xml).unwrap();
+        let error = super::List::new(get_root_el(&pkg).unwrap(), &Scope::default()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Could not resolve the unique address at /."
+        );
+    }
 }
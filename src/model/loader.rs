@@ -0,0 +1,395 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use sxd_document::{dom, parser, Package};
+
+use super::error::ModelError;
+use super::util::*;
+
+/// The element that the loaded modules are gathered under.
+const MODULE_SET: &str = "module-set";
+
+/// Loads YIN modules, along with the modules they import and the submodules
+/// they include, from a set of search directories. Files are looked up by
+/// their RFC 6020 names, i.e. `name.yin` or `name@revision.yin`.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleLoader {
+    search_paths: Vec<PathBuf>,
+}
+
+struct LoadState<'d> {
+    document: dom::Document<'d>,
+    loaded: HashSet<String>,
+    stack: Vec<String>,
+}
+
+fn get_revision(module_el: dom::Element) -> Option<String> {
+    get_yin_children(module_el, "revision")
+        .iter()
+        .filter_map(|r| r.attribute_value("date"))
+        .max()
+        .map(|x| x.to_string())
+}
+
+/// Copies `el` and everything under it into `document`.
+fn copy_element<'d>(document: dom::Document<'d>, el: dom::Element) -> dom::Element<'d> {
+    let copy = document.create_element(el.name());
+
+    for attribute in el.attributes() {
+        copy.set_attribute_value(attribute.name(), attribute.value());
+    }
+
+    for child in el.children() {
+        match child {
+            dom::ChildOfElement::Element(e) => copy.append_child(copy_element(document, e)),
+            dom::ChildOfElement::Text(t) => copy.append_child(document.create_text(t.text())),
+            _ => (),
+        }
+    }
+
+    copy
+}
+
+impl ModuleLoader {
+    pub fn new<P: AsRef<Path>>(search_paths: &[P]) -> ModuleLoader {
+        ModuleLoader {
+            search_paths: search_paths
+                .iter()
+                .map(|p| p.as_ref().to_path_buf())
+                .collect(),
+        }
+    }
+
+    /// Loads the named modules and everything they depend on into a single
+    /// document, under which each module is a top-level element.
+    pub fn load(&self, names: &[&str]) -> Result<Package, ModelError> {
+        let package = Package::new();
+
+        {
+            let mut state = self.start(&package);
+
+            for name in names {
+                self.load_module(&mut state, name, None)?;
+            }
+        }
+
+        Ok(package)
+    }
+
+    /// Like `load`, but starts from a module file rather than a module name.
+    pub fn load_file<P: AsRef<Path>>(&self, path: P) -> Result<Package, ModelError> {
+        let package = Package::new();
+
+        {
+            let mut state = self.start(&package);
+            self.load_path(&mut state, path.as_ref(), None)?;
+        }
+
+        Ok(package)
+    }
+
+    fn start<'d>(&self, package: &'d Package) -> LoadState<'d> {
+        let document = package.as_document();
+        document
+            .root()
+            .append_child(document.create_element(MODULE_SET));
+
+        LoadState {
+            document,
+            loaded: HashSet::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    fn find(&self, name: &str, revision: Option<&str>) -> Option<PathBuf> {
+        for dir in &self.search_paths {
+            if let Some(r) = revision {
+                let path = dir.join(format!("{}@{}.yin", name, r));
+                if path.is_file() {
+                    return Some(path);
+                }
+            }
+
+            let mut revisions: Vec<PathBuf> = fs::read_dir(dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name().and_then(|f| f.to_str()).is_some_and(|f| {
+                        f.starts_with(&format!("{}@", name)) && f.ends_with(".yin")
+                    })
+                })
+                .collect();
+            revisions.sort();
+
+            let path = dir.join(format!("{}.yin", name));
+            match (revision, revisions.pop()) {
+                (None, Some(latest)) => return Some(latest),
+                _ if path.is_file() => return Some(path),
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    fn load_module(
+        &self,
+        state: &mut LoadState,
+        name: &str,
+        revision: Option<&str>,
+    ) -> Result<(), ModelError> {
+        if state.loaded.contains(name) {
+            return Ok(());
+        }
+
+        if state.stack.iter().any(|x| x == name) {
+            let mut cycle = state.stack.clone();
+            cycle.push(name.to_string());
+            return Err(ModelError::ModuleCycle(cycle));
+        }
+
+        let path = self
+            .find(name, revision)
+            .ok_or_else(|| ModelError::ModuleNotFound {
+                name: name.to_string(),
+                revision: revision.map(|x| x.to_string()),
+            })?;
+
+        self.load_path(state, &path, Some((name, revision)))
+    }
+
+    fn load_path(
+        &self,
+        state: &mut LoadState,
+        path: &Path,
+        expected: Option<(&str, Option<&str>)>,
+    ) -> Result<(), ModelError> {
+        let module_xml = read_xml_from_file(path)?;
+        let package = parser::parse(&module_xml).map_err(|e| ModelError::Xml {
+            file: Some(path.display().to_string()),
+            message: e.to_string(),
+        })?;
+        let module_el = get_root_el(&package)?;
+        let name = get_raw_name(module_el, "/")?;
+
+        if let Some((expected_name, expected_revision)) = expected {
+            if expected_name != name {
+                return Err(ModelError::ModuleNameMismatch {
+                    expected: expected_name.to_string(),
+                    found: name,
+                });
+            }
+
+            if expected_revision.is_some()
+                && get_revision(module_el).as_deref() != expected_revision
+            {
+                return Err(ModelError::ModuleNotFound {
+                    name,
+                    revision: expected_revision.map(|x| x.to_string()),
+                });
+            }
+        }
+
+        state.stack.push(name.clone());
+
+        for dependency in ["import", "include"].iter() {
+            for dependency_el in get_yin_children(module_el, dependency) {
+                let dependency_name = get_attribute(dependency_el, "module", "/")?;
+                let revision = get_yin_children(dependency_el, "revision-date")
+                    .first()
+                    .and_then(|r| r.attribute_value("date"))
+                    .map(|x| x.to_string());
+
+                self.load_module(state, &dependency_name, revision.as_deref())?;
+            }
+        }
+
+        state.stack.pop();
+
+        let module_set_el = state.document.root().children()[0].element().unwrap();
+        module_set_el.append_child(copy_element(state.document, module_el));
+        state.loaded.insert(name);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::model::leaftype::BuiltinType;
+    use crate::model::parser::parse_modules;
+
+    const MAIN: &str = r#"<?xml version="1.0"?>
+    <module name="main" xmlns="urn:ietf:params:xml:ns:yang:yin:1">
+        <prefix value="m"/>
+        <import module="types">
+            <prefix value="t"/>
+        </import>
+        <include module="main-sub"/>
+        <container name="authority">
+            <uses name="t:named"/>
+            <leaf name="port">
+                <type name="t:port"/>
+            </leaf>
+            <leaf name="label">
+                <type name="label"/>
+            </leaf>
+        </container>
+    </module>"#;
+
+    const MAIN_SUB: &str = r#"<?xml version="1.0"?>
+    <submodule name="main-sub" xmlns="urn:ietf:params:xml:ns:yang:yin:1">
+        <belongs-to module="main">
+            <prefix value="m"/>
+        </belongs-to>
+        <typedef name="label">
+            <type name="string">
+                <length value="1..16"/>
+            </type>
+        </typedef>
+    </submodule>"#;
+
+    const TYPES: &str = r#"<?xml version="1.0"?>
+    <module name="types" xmlns="urn:ietf:params:xml:ns:yang:yin:1">
+        <prefix value="t"/>
+        <revision date="2020-01-01"/>
+        <typedef name="port">
+            <type name="uint16"/>
+        </typedef>
+        <grouping name="named">
+            <leaf name="name">
+                <type name="string"/>
+            </leaf>
+        </grouping>
+    </module>"#;
+
+    const CYCLIC: &str = r#"<?xml version="1.0"?>
+    <module name="cyclic" xmlns="urn:ietf:params:xml:ns:yang:yin:1">
+        <prefix value="c"/>
+        <import module="cyclic">
+            <prefix value="c2"/>
+        </import>
+    </module>"#;
+
+    fn write_modules(test: &str, modules: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("yinz-loader-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for (file, contents) in modules {
+            fs::write(dir.join(file), contents).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn it_loads_dependencies() {
+        let dir = write_modules(
+            "dependencies",
+            &[
+                ("main.yin", MAIN),
+                ("main-sub.yin", MAIN_SUB),
+                ("types@2020-01-01.yin", TYPES),
+            ],
+        );
+
+        let package = ModuleLoader::new(&[&dir]).load(&["main"]).unwrap();
+        let root = get_root_el(&package).unwrap();
+
+        assert!(find_module_el(root, "main").is_some());
+        assert!(find_module_el(root, "main-sub").is_some());
+        assert!(find_module_el(root, "types").is_some());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_resolves_definitions_across_modules() {
+        let dir = write_modules(
+            "definitions",
+            &[
+                ("main.yin", MAIN),
+                ("main-sub.yin", MAIN_SUB),
+                ("types@2020-01-01.yin", TYPES),
+            ],
+        );
+
+        let loader = ModuleLoader::new(&[&dir]);
+        let model = parse_modules(&loader, &["main"]).unwrap();
+        let authority = match model.root.get_child("authority") {
+            Some(Model::Container(x)) => x.clone(),
+            _ => panic!("Expected the authority container."),
+        };
+
+        // Nodes from a grouping are in the namespace of the module using it.
+        let name = authority.get_child("name").unwrap();
+        assert_eq!(name.get_module(), Some("main"));
+
+        match authority.get_child("port") {
+            Some(Model::Leaf(x)) => {
+                assert_eq!(x.leaf_type.builtin, BuiltinType::Uint16);
+                assert_eq!(x.leaf_type.typedef, Some("port".to_string()));
+            }
+            _ => panic!("Expected the port leaf."),
+        }
+
+        match authority.get_child("label") {
+            Some(Model::Leaf(x)) => {
+                assert_eq!(x.leaf_type.length.as_ref().unwrap().to_string(), "1..16");
+            }
+            _ => panic!("Expected the label leaf."),
+        }
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_fails_on_missing_modules() {
+        let dir = write_modules("missing", &[("main.yin", MAIN)]);
+        let result = ModuleLoader::new(&[&dir]).load(&["main"]);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Could not find module types."
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_fails_on_missing_revisions() {
+        let dir = write_modules("revision", &[("types@2020-01-01.yin", TYPES)]);
+        let loader = ModuleLoader::new(&[&dir]);
+
+        assert!(loader.load(&["types"]).is_ok());
+
+        let package = Package::new();
+        let mut state = loader.start(&package);
+        let result = loader.load_module(&mut state, "types", Some("2021-01-01"));
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Could not find module types@2021-01-01."
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn it_fails_on_cycles() {
+        let dir = write_modules("cycle", &[("cyclic.yin", CYCLIC)]);
+        let result = ModuleLoader::new(&[&dir]).load(&["cyclic"]);
+
+        assert_eq!(
+            result.err().unwrap().to_string(),
+            "Modules depend on each other: cyclic -> cyclic."
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,9 +1,15 @@
 pub mod case;
 pub mod choice;
+pub mod constraint;
 pub mod container;
 pub mod datamodel;
+pub mod error;
 pub mod leaf;
 pub mod leaflist;
+pub mod leafref;
+pub mod leaftype;
 pub mod list;
+pub mod loader;
 pub mod parser;
+pub mod scope;
 pub mod util;
@@ -1,36 +1,97 @@
-use std::error::Error;
-use std::fmt;
 use std::path::Path;
-use sxd_document::parser;
+use sxd_document::Package;
 
 use super::datamodel::DataModel;
+use super::error::ModelError;
+use super::loader::ModuleLoader;
 use super::util::*;
 
-#[derive(Debug, Clone)]
-struct ParseError;
+fn parse_package(package: &Package) -> Result<DataModel, ModelError> {
+    DataModel::new(get_root_el(package)?)
+}
 
-impl fmt::Display for ParseError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Could not parse data model XML.")
-    }
+/// Parses the data model of a single YIN module file, loading the modules it
+/// depends on from the same directory.
+pub fn parse<P: AsRef<Path>>(path: P) -> Result<DataModel, ModelError> {
+    let path = path.as_ref();
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    let package = ModuleLoader::new(&[dir]).load_file(path)?;
+
+    parse_package(&package)
 }
 
-impl Error for ParseError {
-    fn description(&self) -> &str {
-        "Could not parse data model XML."
-    }
+/// Parses the data model assembled from the named modules, which are looked
+/// up along with their dependencies by `loader`.
+pub fn parse_modules(loader: &ModuleLoader, names: &[&str]) -> Result<DataModel, ModelError> {
+    let package = loader.load(names)?;
 
-    fn cause(&self) -> Option<&(dyn Error)> {
-        None
-    }
+    parse_package(&package)
 }
 
-pub fn parse<P: AsRef<Path>>(path: P) -> Result<DataModel, Box<dyn Error>> {
-    let model_xml = read_xml_from_file(path).unwrap();
-    let package = parser::parse(&model_xml)?;
-    let root_el = get_root_el(&package);
+#[cfg(test)]
+mod tests {
+    use crate::model::constraint::{Condition, When};
+    use crate::model::util::*;
 
-    let root = evaluate_get_yin_xpath("//yin:container[@name=\"authority\"]", &root_el)?;
+    const MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:module name="t128" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:container name="authority">
+            <yin:list name="router">
+                <yin:key value="name"/>
+                <yin:leaf name="name">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:list>
+        </yin:container>
+        <yin:augment target-node="/t128:authority/t128:router">
+            <yin:when condition="name != 'foo'"/>
+            <yin:container name="bfd">
+                <yin:leaf name="state">
+                    <yin:type name="string"/>
+                </yin:leaf>
+            </yin:container>
+        </yin:augment>
+        <yin:augment target-node="/t128:authority/t128:router/t128:bfd">
+            <yin:leaf name="multiplier">
+                <yin:type name="uint8"/>
+            </yin:leaf>
+        </yin:augment>
+    </yin:module>"#;
 
-    Ok(DataModel::new(root))
+    #[test]
+    fn it_applies_augments() {
+        let pkg = get_package(MODEL).unwrap();
+        let model = super::parse_package(&pkg).unwrap();
+        let authority = match model.root.get_child("authority") {
+            Some(Model::Container(x)) => x.clone(),
+            _ => panic!("Expected the authority container."),
+        };
+
+        let router = match authority.get_child("router") {
+            Some(Model::List(x)) => x.clone(),
+            _ => panic!("Expected the router list."),
+        };
+        let bfd = match router.get_child("bfd") {
+            Some(Model::Container(x)) => x.clone(),
+            _ => panic!("Expected the bfd container."),
+        };
+
+        assert_eq!(bfd.augmented_by, Some("t128".to_string()));
+        assert_eq!(
+            bfd.when,
+            vec![When {
+                condition: Condition::new("name != 'foo'").unwrap(),
+                in_parent: true,
+            }]
+        );
+        assert!(bfd.get_child("state").is_some());
+
+        match bfd.get_child("multiplier") {
+            Some(Model::Leaf(x)) => {
+                assert_eq!(x.augmented_by, Some("t128".to_string()));
+                assert!(x.when.is_empty());
+            }
+            _ => panic!("Expected the multiplier leaf."),
+        }
+    }
 }
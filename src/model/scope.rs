@@ -0,0 +1,317 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use sxd_document::*;
+
+use super::error::ModelError;
+use super::util::*;
+
+#[derive(Debug, Clone)]
+struct Targeted<'d> {
+    path: Vec<String>,
+    el: dom::Element<'d>,
+}
+
+/// Statements that apply to a schema node from outside of its definition,
+/// such as the `refine` and `augment` statements of a `uses`. Their target
+/// paths are kept relative to the node being built, and are narrowed down as
+/// the model tree is descended. The scope also tracks the schema path of the
+/// node being built, for reporting errors.
+#[derive(Debug, Clone, Default)]
+pub struct Scope<'d> {
+    path: Vec<String>,
+    module: Option<String>,
+    namespace: Option<String>,
+    prefix: Option<String>,
+    refines: Vec<Targeted<'d>>,
+    augments: Vec<Targeted<'d>>,
+    /// Whether the current node is state data, which it is if it or one of
+    /// its ancestors is `config false`.
+    state: bool,
+    /// The groupings being expanded into the current node, innermost last.
+    groupings: Vec<dom::Element<'d>>,
+    /// The augments that have been applied anywhere in the tree, which is
+    /// shared by every scope derived from the same one.
+    applied: Rc<RefCell<Vec<dom::Element<'d>>>>,
+}
+
+fn split_target(target: &str) -> Vec<String> {
+    target
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| split_prefix(s).1.to_string())
+        .collect()
+}
+
+fn get_targeted<'d>(
+    el: dom::Element<'d>,
+    statement: &str,
+    path: &str,
+) -> Result<Vec<Targeted<'d>>, ModelError> {
+    get_yin_children(el, statement)
+        .into_iter()
+        .map(|e| {
+            Ok(Targeted {
+                path: split_target(&get_attribute(e, "target-node", path)?),
+                el: e,
+            })
+        })
+        .collect()
+}
+
+fn descend_targeted<'d>(targeted: &[Targeted<'d>], name: &str) -> Vec<Targeted<'d>> {
+    targeted
+        .iter()
+        .filter(|t| t.path.first().map(|x| x.as_str()) == Some(name))
+        .map(|t| Targeted {
+            path: t.path[1..].to_vec(),
+            el: t.el,
+        })
+        .collect()
+}
+
+fn get_current<'d>(targeted: &[Targeted<'d>]) -> Vec<dom::Element<'d>> {
+    targeted
+        .iter()
+        .filter(|t| t.path.is_empty())
+        .map(|t| t.el)
+        .collect()
+}
+
+impl<'d> Scope<'d> {
+    /// Returns this scope extended with the `refine` and `augment` statements
+    /// of a `uses`, which target nodes relative to the node it appears in.
+    pub fn with_uses(&self, uses_el: dom::Element<'d>) -> Result<Scope<'d>, ModelError> {
+        let mut scope = self.with_augments(uses_el)?;
+
+        scope
+            .refines
+            .extend(get_targeted(uses_el, "refine", &self.path())?);
+        Ok(scope)
+    }
+
+    /// Returns this scope for the contents of the grouping `el`, which cannot
+    /// be expanded within itself.
+    pub fn with_grouping(&self, el: dom::Element<'d>) -> Result<Scope<'d>, ModelError> {
+        check_cycle(el, &self.groupings, &self.path())?;
+
+        let mut scope = self.clone();
+        scope.groupings.push(el);
+        Ok(scope)
+    }
+
+    /// Returns this scope extended with the `augment` statements directly
+    /// under `el`. At the top of a module these use absolute paths, which are
+    /// then relative to the module itself.
+    pub fn with_augments(&self, el: dom::Element<'d>) -> Result<Scope<'d>, ModelError> {
+        let mut scope = self.clone();
+
+        scope
+            .augments
+            .extend(get_targeted(el, "augment", &self.path())?);
+        Ok(scope)
+    }
+
+    /// Returns the scope of the child schema node with the given YANG name.
+    pub fn descend(&self, name: &str) -> Scope<'d> {
+        let mut path = self.path.clone();
+        path.push(name.to_string());
+
+        Scope {
+            path,
+            module: self.module.clone(),
+            namespace: self.namespace.clone(),
+            prefix: self.prefix.clone(),
+            refines: descend_targeted(&self.refines, name),
+            augments: descend_targeted(&self.augments, name),
+            state: self.state,
+            groupings: self.groupings.clone(),
+            applied: self.applied.clone(),
+        }
+    }
+
+    /// Returns this scope for nodes in the namespace of the module `el` is
+    /// defined in, such as the top-level nodes of a module or those added by
+    /// an augment.
+    pub fn with_module(&self, el: dom::Element<'d>) -> Scope<'d> {
+        let mut scope = self.clone();
+
+        scope.module = get_module_name(el);
+        scope.namespace = get_module_namespace(el);
+        scope.prefix = get_module_el(el).and_then(get_module_prefix);
+        scope
+    }
+
+    /// Returns this scope with the `config` statement of the node defined by
+    /// `el` applied, which a `refine` may replace. Nodes without one inherit
+    /// it from their parent, and state data cannot contain configuration.
+    pub fn with_config(&self, el: dom::Element<'d>) -> Result<Scope<'d>, ModelError> {
+        let mut scope = self.clone();
+
+        match get_refinable_value(el, "config", self)?.as_deref() {
+            None => (),
+            Some("false") => scope.state = true,
+            Some("true") if !self.state => (),
+            Some(x) => {
+                return Err(ModelError::InvalidArgument {
+                    path: self.path(),
+                    statement: "config".to_string(),
+                    value: x.to_string(),
+                })
+            }
+        }
+
+        Ok(scope)
+    }
+
+    /// Whether the current node is configuration rather than state data.
+    pub fn config(&self) -> bool {
+        !self.state
+    }
+
+    /// The module whose namespace the current node is in. Nodes from a
+    /// grouping are in the namespace of the module using it.
+    pub fn module(&self) -> Option<String> {
+        self.module.clone()
+    }
+
+    /// The namespace URI of the module the current node is in.
+    pub fn namespace(&self) -> Option<String> {
+        self.namespace.clone()
+    }
+
+    /// The prefix of the module the current node is in.
+    pub fn prefix(&self) -> Option<String> {
+        self.prefix.clone()
+    }
+
+    /// The schema path of the current node.
+    pub fn path(&self) -> String {
+        format!("/{}", self.path.join("/"))
+    }
+
+    /// The `refine` statements targeting the current node.
+    pub fn refines(&self) -> Vec<dom::Element<'d>> {
+        get_current(&self.refines)
+    }
+
+    /// The `augment` statements targeting the current node, which are then
+    /// considered to be applied.
+    pub fn augments(&self) -> Vec<dom::Element<'d>> {
+        let augments = get_current(&self.augments);
+
+        self.applied.borrow_mut().extend(augments.iter().cloned());
+        augments
+    }
+
+    /// Checks that every `augment` directly under `el` has been applied,
+    /// which is not the case if its target node does not exist.
+    pub fn check_augments(&self, el: dom::Element<'d>) -> Result<(), ModelError> {
+        let applied = self.applied.borrow();
+
+        match get_yin_children(el, "augment")
+            .into_iter()
+            .find(|a| !applied.contains(a))
+        {
+            Some(a) => Err(ModelError::UnresolvedReference {
+                path: self.path(),
+                statement: "augment".to_string(),
+                name: a
+                    .attribute_value("target-node")
+                    .unwrap_or_default()
+                    .to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the `statement` substatement of a node's definition, unless a
+    /// `refine` targeting the node replaces it.
+    pub fn get_substatement(
+        &self,
+        el: dom::Element<'d>,
+        statement: &str,
+    ) -> Option<dom::Element<'d>> {
+        self.refines()
+            .into_iter()
+            .rev()
+            .chain(std::iter::once(el))
+            .find_map(|e| get_yin_children(e, statement).into_iter().next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::model::util::*;
+
+    const MODEL: &str = r#"<?xml version="1.0"?>
+    <yin:uses name="foo" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:refine target-node="bar/baz">
+            <yin:mandatory value="true"/>
+        </yin:refine>
+        <yin:augment target-node="pfx:bar">
+            <yin:leaf name="qux">
+                <yin:type name="string"/>
+            </yin:leaf>
+        </yin:augment>
+    </yin:uses>"#;
+
+    const NODE: &str = r#"<?xml version="1.0"?>
+    <yin:leaf name="baz" xmlns:yin="urn:ietf:params:xml:ns:yang:yin:1">
+        <yin:type name="string"/>
+        <yin:mandatory value="false"/>
+        <yin:config value="false"/>
+    </yin:leaf>"#;
+
+    #[test]
+    fn it_descends_to_targets() {
+        let pkg = get_package(MODEL).unwrap();
+        let scope = super::Scope::default()
+            .with_uses(get_root_el(&pkg).unwrap())
+            .unwrap();
+
+        assert!(scope.refines().is_empty());
+        assert_eq!(scope.descend("bar").augments().len(), 1);
+        assert!(scope.descend("bar").refines().is_empty());
+        assert_eq!(scope.descend("bar").descend("baz").refines().len(), 1);
+        assert!(scope.descend("baz").descend("bar").refines().is_empty());
+        assert_eq!(scope.descend("bar").descend("baz").path(), "/bar/baz");
+    }
+
+    #[test]
+    fn it_prefers_refined_substatements() {
+        let pkg = get_package(MODEL).unwrap();
+        let node_pkg = get_package(NODE).unwrap();
+        let scope = super::Scope::default()
+            .with_uses(get_root_el(&pkg).unwrap())
+            .unwrap()
+            .descend("bar")
+            .descend("baz");
+        let node = get_root_el(&node_pkg).unwrap();
+
+        let mandatory = scope.get_substatement(node, "mandatory").unwrap();
+        assert_eq!(mandatory.attribute_value("value"), Some("true"));
+
+        let config = scope.get_substatement(node, "config").unwrap();
+        assert_eq!(config.attribute_value("value"), Some("false"));
+    }
+
+    #[test]
+    fn it_inherits_config() {
+        let node_pkg = get_package(NODE).unwrap();
+        let node = get_root_el(&node_pkg).unwrap();
+        let scope = super::Scope::default();
+        assert!(scope.config());
+
+        let state = scope.descend("baz").with_config(node).unwrap();
+        assert!(!state.config());
+        assert!(!state.descend("qux").config());
+
+        let xml = NODE.replace(r#"value="false"/>"#, r#"value="true"/>"#);
+        let pkg = get_package(&xml).unwrap();
+        let error = state
+            .descend("qux")
+            .with_config(get_root_el(&pkg).unwrap())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid config true at /baz/qux.");
+    }
+}
@@ -1,7 +1,4 @@
-use inflector::cases::camelcase::to_camel_case;
-use std::collections::HashMap;
-use std::error::Error;
-use std::fmt;
+use indexmap::IndexMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufReader;
@@ -12,13 +9,91 @@ use sxd_xpath::nodeset::Node;
 use sxd_xpath::{Context, Factory, Value};
 
 use super::choice::Choice;
+use super::constraint::{parse_when, Must, When};
 use super::container::Container;
+use super::error::ModelError;
 use super::leaf::Leaf;
 use super::leaflist::LeafList;
+use super::leaftype::LeafType;
 use super::list::List;
+use super::scope::Scope;
 
 const YIN_NS: &str = "urn:ietf:params:xml:ns:yang:yin:1";
 
+/// The statements defined by YANG 1.1 (RFC 7950 section 14), along with the
+/// elements YIN uses for the arguments of some of them.
+const YANG_KEYWORDS: &[&str] = &[
+    "action",
+    "anydata",
+    "anyxml",
+    "argument",
+    "augment",
+    "base",
+    "belongs-to",
+    "bit",
+    "case",
+    "choice",
+    "config",
+    "contact",
+    "container",
+    "default",
+    "description",
+    "deviate",
+    "deviation",
+    "enum",
+    "error-app-tag",
+    "error-message",
+    "extension",
+    "feature",
+    "fraction-digits",
+    "grouping",
+    "identity",
+    "if-feature",
+    "import",
+    "include",
+    "input",
+    "key",
+    "leaf",
+    "leaf-list",
+    "length",
+    "list",
+    "mandatory",
+    "max-elements",
+    "min-elements",
+    "modifier",
+    "module",
+    "must",
+    "namespace",
+    "notification",
+    "ordered-by",
+    "organization",
+    "output",
+    "path",
+    "pattern",
+    "position",
+    "prefix",
+    "presence",
+    "range",
+    "reference",
+    "refine",
+    "require-instance",
+    "revision",
+    "revision-date",
+    "rpc",
+    "status",
+    "submodule",
+    "text",
+    "type",
+    "typedef",
+    "unique",
+    "units",
+    "uses",
+    "value",
+    "when",
+    "yang-version",
+    "yin-element",
+];
+
 #[derive(Debug, Clone)]
 pub enum Model {
     Leaf(Arc<Leaf>),
@@ -36,127 +111,785 @@ pub enum Child {
     Choice(Choice),
 }
 
-pub fn read_xml_from_file<P: AsRef<Path>>(path: P) -> Result<String, Box<dyn Error>> {
-    let file = File::open(path)?;
+pub fn read_xml_from_file<P: AsRef<Path>>(path: P) -> Result<String, ModelError> {
+    let path = path.as_ref();
+    let to_error = |e: std::io::Error| ModelError::Io {
+        file: path.display().to_string(),
+        message: e.to_string(),
+    };
+
+    let file = File::open(path).map_err(to_error)?;
     let mut buf_reader = BufReader::new(file);
     let mut contents = String::new();
-    buf_reader.read_to_string(&mut contents)?;
+    buf_reader.read_to_string(&mut contents).map_err(to_error)?;
     Ok(contents)
 }
 
-#[derive(Debug, Clone)]
-struct NotFound;
+pub fn evaluate_get_yin_xpath<'a>(
+    xpath: &str,
+    el: &'a dom::Element,
+) -> Result<dom::Element<'a>, ModelError> {
+    let to_error = |message: String| ModelError::XPath {
+        expression: xpath.to_string(),
+        message,
+    };
+
+    let mut context = Context::new();
+    context.set_namespace("yin", YIN_NS);
+
+    let factory = Factory::new();
+    let compiled = factory
+        .build(xpath)
+        .map_err(|e| to_error(e.to_string()))?
+        .ok_or_else(|| to_error("The expression is empty.".to_string()))?;
+    let result = compiled
+        .evaluate(&context, *el)
+        .map_err(|e| to_error(e.to_string()))?;
+
+    match result {
+        Value::Nodeset(x) => match x.document_order_first() {
+            Some(Node::Element(x)) => Ok(x),
+            _ => Err(to_error("No element was found.".to_string())),
+        },
+        _ => Err(to_error(
+            "The expression does not select nodes.".to_string(),
+        )),
+    }
+}
+
+/// Returns the value of an attribute of the statement `el`, which is part of
+/// the schema node at `path`.
+pub fn get_attribute(el: dom::Element, attribute: &str, path: &str) -> Result<String, ModelError> {
+    el.attribute_value(attribute)
+        .map(|x| x.to_string())
+        .ok_or_else(|| ModelError::MissingAttribute {
+            path: path.to_string(),
+            statement: el.name().local_part().to_string(),
+            attribute: attribute.to_string(),
+        })
+}
+
+/// Returns the `statement` substatement of `el`, which is required for the
+/// schema node at `path`.
+pub fn get_required_child<'d>(
+    el: dom::Element<'d>,
+    statement: &str,
+    path: &str,
+) -> Result<dom::Element<'d>, ModelError> {
+    get_yin_children(el, statement)
+        .into_iter()
+        .next()
+        .ok_or_else(|| ModelError::MissingStatement {
+            path: path.to_string(),
+            statement: el.name().local_part().to_string(),
+            substatement: statement.to_string(),
+        })
+}
+
+pub fn get_raw_name(el: dom::Element, path: &str) -> Result<String, ModelError> {
+    get_attribute(el, "name", path)
+}
+
+pub fn get_yin_children<'d>(el: dom::Element<'d>, statement: &str) -> Vec<dom::Element<'d>> {
+    el.children()
+        .into_iter()
+        .filter_map(|c| c.element())
+        .filter(|e| e.name().namespace_uri() == Some(YIN_NS) && e.name().local_part() == statement)
+        .collect()
+}
+
+pub fn split_prefix(name: &str) -> (Option<&str>, &str) {
+    match name.find(':') {
+        Some(i) => (Some(&name[..i]), &name[i + 1..]),
+        None => (None, name),
+    }
+}
+
+fn is_module(el: dom::Element) -> bool {
+    el.name().namespace_uri() == Some(YIN_NS)
+        && matches!(el.name().local_part(), "module" | "submodule")
+}
+
+/// Returns the `module` or `submodule` statement that `el` is part of.
+pub fn get_module_el(el: dom::Element) -> Option<dom::Element> {
+    let mut current = Some(el);
+
+    while let Some(e) = current {
+        if is_module(e) {
+            return Some(e);
+        }
 
-impl fmt::Display for NotFound {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Could not parse data model XML.")
+        current = e.parent().and_then(|p| p.element());
     }
+
+    None
+}
+
+/// Finds a module or submodule by name among those loaded into the same
+/// document as `el`.
+pub fn find_module_el<'d>(el: dom::Element<'d>, name: &str) -> Option<dom::Element<'d>> {
+    let root = get_root_el_of(el);
+    let candidates = if is_module(root) {
+        vec![root]
+    } else {
+        root.children()
+            .into_iter()
+            .filter_map(|c| c.element())
+            .collect()
+    };
+
+    candidates
+        .into_iter()
+        .find(|m| is_module(*m) && m.attribute_value("name") == Some(name))
 }
 
-impl Error for NotFound {
-    fn description(&self) -> &str {
-        "Could not parse data model XML."
+fn get_root_el_of(el: dom::Element) -> dom::Element {
+    let mut root = el;
+
+    while let Some(parent) = root.parent().and_then(|p| p.element()) {
+        root = parent;
     }
 
-    fn cause(&self) -> Option<&(dyn Error)> {
-        None
+    root
+}
+
+fn get_argument_of(el: dom::Element, statement: &str, attribute: &str) -> Option<String> {
+    get_yin_children(el, statement)
+        .first()
+        .and_then(|e| e.attribute_value(attribute))
+        .map(|x| x.to_string())
+}
+
+/// Returns the prefix a module or submodule uses to refer to its own
+/// definitions.
+pub fn get_module_prefix(module_el: dom::Element) -> Option<String> {
+    match module_el.name().local_part() {
+        "submodule" => get_yin_children(module_el, "belongs-to")
+            .first()
+            .and_then(|b| get_argument_of(*b, "prefix", "value")),
+        _ => get_argument_of(module_el, "prefix", "value"),
     }
 }
 
-pub fn evaluate_get_yin_xpath<'a>(
-    xpath: &str,
-    el: &'a dom::Element,
-) -> Result<dom::Element<'a>, Box<dyn Error>> {
-    let mut context = Context::new();
-    context.set_namespace("yin", YIN_NS);
+/// Returns the namespace URI of the module `el` is defined in, which for a
+/// submodule is that of the module it belongs to.
+pub fn get_module_namespace(el: dom::Element) -> Option<String> {
+    let module_el = get_module_el(el)?;
+    let main_el = match module_el.name().local_part() {
+        "submodule" => find_module_el(
+            module_el,
+            &get_argument_of(module_el, "belongs-to", "module")?,
+        )?,
+        _ => module_el,
+    };
 
-    let factory = Factory::new();
-    let xpath = factory.build(xpath)?;
-    let result = xpath.ok_or(NotFound)?.evaluate(&context, *el)?;
+    get_argument_of(main_el, "namespace", "uri")
+}
+
+/// Returns the module `el` belongs to along with all of its submodules,
+/// whose top-level definitions are all visible to one another.
+fn get_module_family(module_el: dom::Element) -> Vec<dom::Element> {
+    let main_el = match module_el.name().local_part() {
+        "submodule" => get_argument_of(module_el, "belongs-to", "module")
+            .and_then(|m| find_module_el(module_el, &m))
+            .unwrap_or(module_el),
+        _ => module_el,
+    };
+
+    let mut family = vec![main_el];
+
+    for include_el in get_yin_children(main_el, "include") {
+        if let Some(m) = include_el
+            .attribute_value("module")
+            .and_then(|m| find_module_el(main_el, m))
+        {
+            family.push(m);
+        }
+    }
+
+    if !family.contains(&module_el) {
+        family.push(module_el);
+    }
+
+    family
+}
+
+/// Resolves a prefix used within `el`'s module to the module it refers to,
+/// which is either the module itself or one that it imports.
+pub fn resolve_prefix<'d>(el: dom::Element<'d>, prefix: &str) -> Option<dom::Element<'d>> {
+    let module_el = get_module_el(el)?;
+
+    if get_module_prefix(module_el).as_deref() == Some(prefix) {
+        return Some(module_el);
+    }
+
+    get_yin_children(module_el, "import")
+        .into_iter()
+        .find(|i| get_argument_of(*i, "prefix", "value").as_deref() == Some(prefix))
+        .and_then(|i| i.attribute_value("module"))
+        .and_then(|m| find_module_el(el, m))
+}
+
+/// Checks that the typedef or grouping `el` is not already being resolved,
+/// as the last of the `resolving` ones, which would then never end.
+pub fn check_cycle(
+    el: dom::Element,
+    resolving: &[dom::Element],
+    path: &str,
+) -> Result<(), ModelError> {
+    let start = match resolving.iter().position(|r| *r == el) {
+        Some(x) => x,
+        None => return Ok(()),
+    };
+
+    Err(ModelError::ReferenceCycle {
+        path: path.to_string(),
+        statement: el.name().local_part().to_string(),
+        names: resolving[start..]
+            .iter()
+            .chain(std::iter::once(&el))
+            .map(|e| e.attribute_value("name").unwrap_or_default().to_string())
+            .collect(),
+    })
+}
+
+/// Finds the definition of a `typedef` or `grouping` that is in scope at
+/// `el`. Unprefixed names are searched for in each enclosing statement
+/// outwards to the module and then in its submodules, while prefixed names
+/// are searched for at the top level of the module the prefix refers to.
+pub fn find_definition<'d>(
+    el: dom::Element<'d>,
+    statement: &str,
+    name: &str,
+) -> Option<dom::Element<'d>> {
+    let (prefix, local_name) = split_prefix(name);
+    let module_el = get_module_el(el);
+    let find_in = |s: dom::Element<'d>| {
+        get_yin_children(s, statement)
+            .into_iter()
+            .find(|d| d.attribute_value("name") == Some(local_name))
+    };
+
+    if let Some(p) = prefix {
+        let target_el = resolve_prefix(el, p)?;
+
+        if Some(target_el) != module_el {
+            return get_module_family(target_el).into_iter().find_map(find_in);
+        }
+    }
+
+    let mut scope = el.parent().and_then(|p| p.element());
+
+    while let Some(s) = scope {
+        let found = find_in(s);
 
-    let found = match result {
-        Value::Nodeset(x) => match x.document_order_first().ok_or(NotFound)? {
-            Node::Element(x) => x,
-            _ => return Err(NotFound.into()),
+        if found.is_some() || Some(s) == module_el {
+            return found.or_else(|| {
+                get_module_family(s)
+                    .into_iter()
+                    .filter(|m| *m != s)
+                    .find_map(find_in)
+            });
+        }
+
+        scope = s.parent().and_then(|p| p.element());
+    }
+
+    None
+}
+
+impl Model {
+    /// The camelCase form of the YANG name, which instances use.
+    pub fn get_name(&self) -> &str {
+        match self {
+            Model::Leaf(x) => &x.name,
+            Model::LeafList(x) => &x.name,
+            Model::Container(x) => &x.name,
+            Model::List(x) => &x.name,
+        }
+    }
+
+    /// The name as written in the module.
+    pub fn get_yang_name(&self) -> &str {
+        match self {
+            Model::Leaf(x) => &x.yang_name,
+            Model::LeafList(x) => &x.yang_name,
+            Model::Container(x) => &x.yang_name,
+            Model::List(x) => &x.yang_name,
+        }
+    }
+
+    /// The module whose namespace the node is in, if known. Nodes from a
+    /// grouping are in that of the module using it.
+    pub fn get_module(&self) -> Option<&str> {
+        match self {
+            Model::Leaf(x) => x.module.as_deref(),
+            Model::LeafList(x) => x.module.as_deref(),
+            Model::Container(x) => x.module.as_deref(),
+            Model::List(x) => x.module.as_deref(),
+        }
+    }
+
+    pub fn get_namespace(&self) -> Option<&str> {
+        match self {
+            Model::Leaf(x) => x.namespace.as_deref(),
+            Model::LeafList(x) => x.namespace.as_deref(),
+            Model::Container(x) => x.namespace.as_deref(),
+            Model::List(x) => x.namespace.as_deref(),
+        }
+    }
+
+    pub fn get_prefix(&self) -> Option<&str> {
+        match self {
+            Model::Leaf(x) => x.prefix.as_deref(),
+            Model::LeafList(x) => x.prefix.as_deref(),
+            Model::Container(x) => x.prefix.as_deref(),
+            Model::List(x) => x.prefix.as_deref(),
+        }
+    }
+
+    /// The camelCase name qualified with the module, as in `module:name`, if
+    /// the module is known.
+    pub fn get_qualified_name(&self) -> String {
+        match self.get_module() {
+            Some(m) => format!("{}:{}", m, self.get_name()),
+            None => self.get_name().to_string(),
+        }
+    }
+
+    /// The key of the node among the children of a node in the namespace of
+    /// `parent_module`, which is its YANG name, qualified with its module if
+    /// that is another one, as for nodes added by the augment of another
+    /// module. The root is in no module, so the top-level nodes are always
+    /// qualified.
+    pub fn get_key(&self, parent_module: Option<&str>) -> String {
+        match self.get_module() {
+            Some(m) if Some(m) != parent_module => format!("{}:{}", m, self.get_yang_name()),
+            _ => self.get_yang_name().to_string(),
+        }
+    }
+
+    /// Records that this node was added to its parent by `augment_el`.
+    pub fn set_augmented_by(&mut self, augment_el: dom::Element) {
+        let module = get_module_name(augment_el);
+
+        match self {
+            Model::Leaf(x) => Arc::make_mut(x).augmented_by = module,
+            Model::LeafList(x) => Arc::make_mut(x).augmented_by = module,
+            Model::Container(x) => Arc::make_mut(x).augmented_by = module,
+            Model::List(x) => Arc::make_mut(x).augmented_by = module,
+        }
+    }
+
+    /// Makes this node conditional on the `when` of a statement it is in,
+    /// such as an `augment` or `uses`, in addition to its own.
+    pub fn add_when(&mut self, when: When) {
+        match self {
+            Model::Leaf(x) => Arc::make_mut(x).when.push(when),
+            Model::LeafList(x) => Arc::make_mut(x).when.push(when),
+            Model::Container(x) => Arc::make_mut(x).when.push(when),
+            Model::List(x) => Arc::make_mut(x).when.push(when),
+        }
+    }
+
+    pub fn get_when(&self) -> &[When] {
+        match self {
+            Model::Leaf(x) => &x.when,
+            Model::LeafList(x) => &x.when,
+            Model::Container(x) => &x.when,
+            Model::List(x) => &x.when,
+        }
+    }
+
+    /// Whether the node is configuration rather than state data, as given by
+    /// its `config` statement or else inherited from its parent.
+    pub fn is_config(&self) -> bool {
+        match self {
+            Model::Leaf(x) => x.config,
+            Model::LeafList(x) => x.config,
+            Model::Container(x) => x.config,
+            Model::List(x) => x.config,
+        }
+    }
+
+    pub fn get_musts(&self) -> &[Must] {
+        match self {
+            Model::Leaf(x) => &x.musts,
+            Model::LeafList(x) => &x.musts,
+            Model::Container(x) => &x.musts,
+            Model::List(x) => &x.musts,
+        }
+    }
+}
+
+impl Child {
+    /// Returns the data nodes of this child keyed as the children of a node
+    /// in the namespace of `parent_module`, where a choice contributes the
+    /// data nodes of all of its cases as it keys them.
+    pub fn into_models(self, parent_module: Option<&str>) -> Vec<(String, Model)> {
+        let model = match self {
+            Child::Leaf(x) => Model::Leaf(Arc::new(x)),
+            Child::LeafList(x) => Model::LeafList(Arc::new(x)),
+            Child::Container(x) => Model::Container(Arc::new(x)),
+            Child::List(x) => Model::List(Arc::new(x)),
+            Child::Choice(x) => return x.children.into_iter().collect(),
+        };
+
+        vec![(model.get_key(parent_module), model)]
+    }
+
+    /// Makes this child, or the data nodes of a choice, conditional on the
+    /// `when` of a statement it is in.
+    pub fn add_when(&mut self, when: When) {
+        match self {
+            Child::Leaf(x) => x.when.push(when),
+            Child::LeafList(x) => x.when.push(when),
+            Child::Container(x) => x.when.push(when),
+            Child::List(x) => x.when.push(when),
+            Child::Choice(x) => x.add_when(when),
+        }
+    }
+}
+
+/// Keys `models` as the children of a node in the namespace of
+/// `parent_module`.
+pub fn qualify(models: &mut IndexMap<String, Model>, parent_module: Option<&str>) {
+    *models = models
+        .drain(..)
+        .map(|(_, m)| (m.get_key(parent_module), m))
+        .collect();
+}
+
+/// Returns the name of the module that `el` is defined in, which for a
+/// submodule is the module it belongs to.
+pub fn get_module_name(el: dom::Element) -> Option<String> {
+    let module_el = get_module_el(el)?;
+
+    match module_el.name().local_part() {
+        "submodule" => get_argument_of(module_el, "belongs-to", "module"),
+        _ => module_el.attribute_value("name").map(|x| x.to_string()),
+    }
+}
+
+/// Qualifies the name of an identity, which `el` refers to by a YANG prefix,
+/// with the name of the module defining it.
+pub fn qualify_identity(el: dom::Element, name: &str, path: &str) -> Result<String, ModelError> {
+    let (prefix, local_name) = split_prefix(name);
+    let module_el = match prefix {
+        Some(p) => resolve_prefix(el, p),
+        None => get_module_el(el),
+    };
+
+    match module_el.and_then(get_module_name) {
+        Some(module) => Ok(format!("{}:{}", module, local_name)),
+        None => Err(ModelError::UnresolvedReference {
+            path: path.to_string(),
+            statement: "identity".to_string(),
+            name: name.to_string(),
+        }),
+    }
+}
+
+/// Returns the `value` of a node's `statement`, which a `refine` may
+/// replace.
+pub fn get_refinable_value<'d>(
+    el: dom::Element<'d>,
+    statement: &str,
+    scope: &Scope<'d>,
+) -> Result<Option<String>, ModelError> {
+    scope
+        .get_substatement(el, statement)
+        .map(|e| get_attribute(e, "value", &scope.path()))
+        .transpose()
+}
+
+/// Returns whether a leaf or choice is `mandatory`.
+pub fn parse_mandatory<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<bool, ModelError> {
+    match get_refinable_value(el, "mandatory", scope)?.as_deref() {
+        None | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(x) => Err(ModelError::InvalidArgument {
+            path: scope.path(),
+            statement: "mandatory".to_string(),
+            value: x.to_string(),
+        }),
+    }
+}
+
+/// Returns whether a list or leaf-list is `ordered-by user`, in which case
+/// the order of its entries is meaningful.
+pub fn parse_ordered_by(el: dom::Element, path: &str) -> Result<bool, ModelError> {
+    let value = get_yin_children(el, "ordered-by")
+        .into_iter()
+        .next()
+        .map(|e| get_attribute(e, "value", path))
+        .transpose()?;
+
+    match value.as_deref() {
+        None | Some("system") => Ok(false),
+        Some("user") => Ok(true),
+        Some(x) => Err(ModelError::InvalidArgument {
+            path: path.to_string(),
+            statement: "ordered-by".to_string(),
+            value: x.to_string(),
+        }),
+    }
+}
+
+/// Returns the `default` values of a leaf or leaf-list, which a `refine` may
+/// replace, or else the default of its type. Each has to be a valid value of
+/// `leaf_type`.
+pub fn parse_defaults<'d>(
+    el: dom::Element<'d>,
+    scope: &Scope<'d>,
+    leaf_type: &LeafType,
+) -> Result<Vec<String>, ModelError> {
+    let defaults = scope
+        .refines()
+        .into_iter()
+        .rev()
+        .chain(std::iter::once(el))
+        .map(|e| {
+            get_yin_children(e, "default")
+                .into_iter()
+                .filter_map(|d| d.attribute_value("value"))
+                .map(|x| x.to_string())
+                .collect::<Vec<_>>()
+        })
+        .find(|d| !d.is_empty())
+        .unwrap_or_else(|| leaf_type.default.iter().cloned().collect::<Vec<_>>());
+
+    for default in &defaults {
+        if !leaf_type.accepts(default) {
+            return Err(ModelError::InvalidArgument {
+                path: scope.path(),
+                statement: "default".to_string(),
+                value: default.clone(),
+            });
+        }
+    }
+
+    Ok(defaults)
+}
+
+/// Returns the `min-elements` and `max-elements` of a list or leaf-list,
+/// where there is no maximum if `max-elements` is absent or `unbounded`.
+pub fn parse_elements<'d>(
+    el: dom::Element<'d>,
+    scope: &Scope<'d>,
+) -> Result<(u64, Option<u64>), ModelError> {
+    let invalid = |statement: &str, value: &str| ModelError::InvalidArgument {
+        path: scope.path(),
+        statement: statement.to_string(),
+        value: value.to_string(),
+    };
+
+    let min = match get_refinable_value(el, "min-elements", scope)? {
+        Some(x) => x.parse().map_err(|_| invalid("min-elements", &x))?,
+        None => 0,
+    };
+
+    let max = match get_refinable_value(el, "max-elements", scope)? {
+        Some(x) if x == "unbounded" => None,
+        Some(x) => match x.parse() {
+            Ok(n) if n > 0 && n >= min => Some(n),
+            _ => return Err(invalid("max-elements", &x)),
         },
-        _ => return Err(NotFound.into()),
+        None => None,
     };
 
-    Ok(found)
-}
-
-pub fn get_name(el: dom::Element) -> String {
-    to_camel_case(el.attribute("name").unwrap().value())
-}
-
-pub fn parse_children(el: dom::Element) -> HashMap<String, Model> {
-    let mut children: HashMap<String, Model> = HashMap::new();
-
-    for child in el.children() {
-        if let dom::ChildOfElement::Element(e) = child {
-            let child = parse_child(e);
-
-            if let Some(c) = child {
-                match c {
-                    Child::Leaf(x) => {
-                        children.insert(x.name.clone(), Model::Leaf(Arc::new(x)));
-                    }
-                    Child::LeafList(x) => {
-                        children.insert(x.name.clone(), Model::LeafList(Arc::new(x)));
-                    }
-                    Child::Container(x) => {
-                        children.insert(x.name.clone(), Model::Container(Arc::new(x)));
-                    }
-                    Child::List(x) => {
-                        children.insert(x.name.clone(), Model::List(Arc::new(x)));
-                    }
-                    Child::Choice(x) => {
-                        children.extend(x.children);
-                    }
+    Ok((min, max))
+}
+
+/// Checks that a statement found under the schema node at `path` is a YANG
+/// statement if it is in the YIN namespace. Elements in other namespaces are
+/// extensions, which are left alone.
+pub fn check_statement(el: dom::Element, path: &str) -> Result<(), ModelError> {
+    let name = el.name();
+
+    if name.namespace_uri() == Some(YIN_NS) && !YANG_KEYWORDS.contains(&name.local_part()) {
+        return Err(ModelError::UnknownStatement {
+            path: path.to_string(),
+            statement: name.local_part().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Parses the data definition statements directly under `el`, expanding any
+/// `uses` into the contents of the grouping it refers to.
+pub fn parse_child_statements<'d>(
+    el: dom::Element<'d>,
+    scope: &Scope<'d>,
+) -> Result<Vec<Child>, ModelError> {
+    let mut children: Vec<Child> = Vec::new();
+    let path = scope.path();
+
+    for e in el.children().into_iter().filter_map(|c| c.element()) {
+        check_statement(e, &path)?;
+
+        if e.name().local_part() == "uses" {
+            let name = get_raw_name(e, &path)?;
+            let grouping_el = find_definition(e, "grouping", &name).ok_or_else(|| {
+                ModelError::UnresolvedReference {
+                    path: path.clone(),
+                    statement: "uses".to_string(),
+                    name,
+                }
+            })?;
+
+            let when = parse_when(e, true, &path)?;
+
+            let grouping_scope = scope.with_uses(e)?.with_grouping(grouping_el)?;
+
+            for mut child in parse_child_statements(grouping_el, &grouping_scope)? {
+                if let Some(w) = &when {
+                    child.add_when(w.clone());
                 }
+
+                children.push(child);
             }
+
+            scope.check_augments(e)?;
+        } else if let Some(c) = parse_child(e, scope)? {
+            children.push(c);
         }
     }
 
-    children
+    Ok(children)
 }
 
-pub fn get_root_el(package: &Package) -> dom::Element {
-    match package.as_document().root().children()[0] {
-        dom::ChildOfRoot::Element(x) => x,
-        _ => panic!("Root must be an element."),
+/// The data nodes of a node keyed by name, which include those of its
+/// choices, along with the choices themselves.
+pub type Children = (IndexMap<String, Model>, Vec<Arc<Choice>>);
+
+/// Returns the only item `is_named` holds for, if there is just one.
+pub fn find_unique<T>(items: impl Iterator<Item = T>, is_named: impl Fn(&T) -> bool) -> Option<T> {
+    let mut matches = items.filter(|x| is_named(x));
+    let first = matches.next()?;
+
+    matches.next().is_none().then_some(first)
+}
+
+pub fn parse_children<'d>(el: dom::Element<'d>, scope: &Scope<'d>) -> Result<Children, ModelError> {
+    let mut children: IndexMap<String, Model> = IndexMap::new();
+    let mut choices: Vec<Arc<Choice>> = Vec::new();
+
+    let module = scope.module();
+
+    for mut child in parse_child_statements(el, scope)? {
+        if let Child::Choice(x) = &mut child {
+            x.qualify(module.as_deref());
+            choices.push(Arc::new(x.clone()));
+        }
+
+        children.extend(child.into_models(module.as_deref()));
     }
+
+    for augment_el in scope.augments() {
+        let augment_scope = scope.with_module(augment_el);
+        let when = parse_when(augment_el, true, &scope.path())?;
+
+        for mut child in parse_child_statements(augment_el, &augment_scope)? {
+            if let Some(w) = &when {
+                child.add_when(w.clone());
+            }
+
+            if let Child::Choice(x) = &mut child {
+                x.set_augmented_by(augment_el);
+                x.qualify(module.as_deref());
+                choices.push(Arc::new(x.clone()));
+            }
+
+            for (name, mut model) in child.into_models(module.as_deref()) {
+                model.set_augmented_by(augment_el);
+                children.insert(name, model);
+            }
+        }
+    }
+
+    Ok((children, choices))
+}
+
+pub fn get_root_el(package: &Package) -> Result<dom::Element<'_>, ModelError> {
+    package
+        .as_document()
+        .root()
+        .children()
+        .into_iter()
+        .find_map(|c| c.element())
+        .ok_or_else(|| ModelError::Xml {
+            file: None,
+            message: "The document has no root element.".to_string(),
+        })
 }
 
-pub fn get_package(xml_str: &str) -> Package {
-    parser::parse(xml_str).expect("Failed to parse")
+pub fn get_package(xml_str: &str) -> Result<Package, ModelError> {
+    parser::parse(xml_str).map_err(|e| ModelError::Xml {
+        file: None,
+        message: e.to_string(),
+    })
 }
 
-pub fn parse_child(el: dom::Element) -> Option<Child> {
+pub fn parse_child<'d>(
+    el: dom::Element<'d>,
+    scope: &Scope<'d>,
+) -> Result<Option<Child>, ModelError> {
     let model_type = el.name().local_part();
+    let child_scope = || -> Result<Scope, ModelError> {
+        scope
+            .descend(&get_raw_name(el, &scope.path())?)
+            .with_config(el)
+    };
 
-    match model_type {
-        "leaf" => Some(Child::Leaf(Leaf::new(el))),
-        "container" => Some(Child::Container(Container::new(el))),
-        "list" => Some(Child::List(List::new(el))),
-        "leaf-list" => Some(Child::LeafList(LeafList::new(el))),
-        "choice" => Some(Child::Choice(Choice::new(el))),
-        _ => None,
-    }
+    let child = match model_type {
+        "leaf" => Child::Leaf(Leaf::new(el, &child_scope()?)?),
+        "container" => Child::Container(Container::new(el, &child_scope()?)?),
+        "list" => Child::List(List::new(el, &child_scope()?)?),
+        "leaf-list" => Child::LeafList(LeafList::new(el, &child_scope()?)?),
+        "choice" => Child::Choice(Choice::new(el, &child_scope()?)?),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(child))
+}
+
+/// Returns the key and node of the child that `WithChildren::get_child`
+/// finds among `children`.
+pub fn find_child_entry<'a>(
+    children: &'a IndexMap<String, Model>,
+    name: &str,
+) -> Option<(&'a String, &'a Model)> {
+    children
+        .get_key_value(name)
+        .or_else(|| find_unique(children.iter(), |(_, m)| m.get_yang_name() == name))
+        .or_else(|| find_unique(children.iter(), |(_, m)| m.get_name() == name))
 }
 
 pub trait WithChildren {
-    fn get_children(&self) -> &HashMap<String, Model>;
+    fn get_children(&self) -> &IndexMap<String, Model>;
+
+    /// The choices among the children, whose cases hold the data nodes that
+    /// `get_children` also has.
+    fn get_choices(&self) -> &[Arc<Choice>];
+
+    /// Returns the choice with the YANG or camelCase name `name`, which may
+    /// be nested in the case of another.
+    fn get_choice(&self, name: &str) -> Option<&Choice> {
+        self.get_choices().iter().find_map(|c| c.find(name))
+    }
 
+    /// Returns the child with the key, YANG name or camelCase name `name`.
+    /// Children from another module are keyed by their qualified name, which
+    /// can be left out. Different YANG names may have the same camelCase
+    /// name, which then refers to none of them.
     fn get_child(&self, name: &str) -> Option<&Model> {
-        let children = self.get_children();
-        let has_exact_match = children.contains_key(name);
+        self.get_child_entry(name).map(|(_, m)| m)
+    }
 
-        if has_exact_match {
-            children.get(name)
-        } else {
-            let camel_case_name = to_camel_case(name);
-            children.get(&camel_case_name)
-        }
+    /// Returns the child `get_child` finds along with its key.
+    fn get_child_entry(&self, name: &str) -> Option<(&String, &Model)> {
+        find_child_entry(self.get_children(), name)
     }
 }